@@ -33,7 +33,7 @@ impl Allocator {
     /// Panics if the system's memory map could not be retrieved.
     pub fn initialize(&self) {
         let (start, end) = memory_map().expect("failed to find memory map");
-        *self.0.lock() = Some(imp::Allocator::new(start, end));
+        *self.0.lock_irqsave() = Some(imp::Allocator::new(start, end));
     }
 }
 
@@ -60,7 +60,7 @@ unsafe impl alloc::alloc::GlobalAlloc for Allocator {
     /// size or alignment constraints (`AllocError::Unsupported`).
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         self.0
-            .lock()
+            .lock_irqsave()
             .as_mut()
             .expect("allocator uninitialized")
             .alloc(layout)
@@ -81,7 +81,7 @@ unsafe impl alloc::alloc::GlobalAlloc for Allocator {
     /// behavior.
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         self.0
-            .lock()
+            .lock_irqsave()
             .as_mut()
             .expect("allocator uninitialized")
             .dealloc(ptr, layout);
@@ -0,0 +1,169 @@
+//! Per-core state and secondary core bring-up.
+
+/// The number of Cortex-A53 cores.
+pub const CORES: usize = 4;
+
+/// The size of each core's boot stack. This must match `CPU_STACK_SIZE` in
+/// `kernel.S` and the stacks in `layout.ld`.
+pub const STACK_SIZE: usize = 0x1000;
+
+/// The spin-table release addresses of each core. The firmware parks the
+/// secondary cores in a loop that waits for an entry point to be written to
+/// their release address.
+const SPIN_TABLE: [usize; CORES] = [0xd8, 0xe0, 0xe8, 0xf0];
+
+extern "C" {
+    /// The end of core 0's boot stack. The other cores' stacks follow it.
+    static __cpu0_stack_end: u8;
+
+    /// The entry point of the secondary cores in `kernel.S`.
+    fn _secondary_start();
+}
+
+/// Returns the number of the core this is running on.
+#[cfg(target_arch = "aarch64")]
+pub fn cpu_id() -> usize {
+    let mpidr: u64;
+    unsafe {
+        core::arch::asm!("mrs {0}, MPIDR_EL1", out(reg) mpidr);
+    }
+    // The affinity level 0 field holds the core number.
+    (mpidr & 0b11) as usize
+}
+
+/// Returns the number of the core this is running on. Host builds, i.e. unit
+/// tests, always run as core 0.
+#[cfg(not(target_arch = "aarch64"))]
+pub fn cpu_id() -> usize {
+    0
+}
+
+/// Masks all interrupts on this core (DAIF) and returns the previous mask, to
+/// be given back to `restore_interrupts()`.
+#[cfg(target_arch = "aarch64")]
+pub fn mask_interrupts() -> u64 {
+    let daif: u64;
+    unsafe {
+        core::arch::asm!("mrs {0}, DAIF", "msr DAIFSet, #0b1111", out(reg) daif);
+    }
+    daif
+}
+
+/// Restores the interrupt mask `daif` returned by `mask_interrupts()`.
+#[cfg(target_arch = "aarch64")]
+pub fn restore_interrupts(daif: u64) {
+    unsafe {
+        core::arch::asm!("msr DAIF, {0}", in(reg) daif);
+    }
+}
+
+/// Host builds have no interrupts to mask.
+#[cfg(not(target_arch = "aarch64"))]
+pub fn mask_interrupts() -> u64 {
+    0
+}
+
+/// Host builds have no interrupts to mask.
+#[cfg(not(target_arch = "aarch64"))]
+pub fn restore_interrupts(_daif: u64) {}
+
+/// Waits for another core to signal an event with `send_event()`. May return
+/// early, so callers must check the condition they are waiting for again.
+pub fn wait_for_event() {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        core::arch::asm!("wfe");
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    core::hint::spin_loop();
+}
+
+/// Wakes every core waiting in `wait_for_event()`.
+pub fn send_event() {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        // Make prior writes visible before the other cores wake up.
+        core::arch::asm!("dsb ish", "sev");
+    }
+}
+
+/// Makes instructions written to `[start, start + len)` visible to instruction
+/// fetches on every core: cleans the data cache to the point of unification
+/// and invalidates the instruction cache over the range.
+#[cfg_attr(test, allow(dead_code))]
+pub fn sync_instruction_cache(start: usize, len: usize) {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        // CTR_EL0 holds log2 of the smallest cache line size in words.
+        let ctr: u64;
+        core::arch::asm!("mrs {0}, CTR_EL0", out(reg) ctr);
+        let dline = 4 << ((ctr >> 16) & 0xf);
+        let iline = 4 << (ctr & 0xf);
+        let end = start + len;
+
+        let mut addr = start & !(dline - 1);
+        while addr < end {
+            core::arch::asm!("dc cvau, {0}", in(reg) addr);
+            addr += dline;
+        }
+        core::arch::asm!("dsb ish");
+
+        let mut addr = start & !(iline - 1);
+        while addr < end {
+            core::arch::asm!("ic ivau, {0}", in(reg) addr);
+            addr += iline;
+        }
+        core::arch::asm!("dsb ish", "isb");
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = (start, len);
+}
+
+/// Returns the address of the end of `core`'s boot stack.
+#[cfg_attr(test, allow(dead_code))]
+pub fn stack_end(core: usize) -> usize {
+    let cpu0_stack_end = unsafe { &__cpu0_stack_end as *const u8 as usize };
+    cpu0_stack_end + core * STACK_SIZE
+}
+
+/// Releases the secondary cores from the firmware's spin table. Each core
+/// starts at `_secondary_start` and calls `kmain_secondary`.
+#[cfg_attr(test, allow(dead_code))]
+pub fn start_secondary_cores() {
+    let entry = _secondary_start as *const () as u64;
+    for &release_addr in &SPIN_TABLE[1..] {
+        unsafe {
+            core::ptr::write_volatile(release_addr as *mut u64, entry);
+        }
+    }
+
+    unsafe {
+        // Make the writes visible before waking the cores up.
+        core::arch::asm!("dsb sy", "sev");
+    }
+}
+
+/// A value with one instance per core.
+#[derive(Debug)]
+pub struct PerCore<T>([T; CORES]);
+
+#[cfg_attr(test, allow(dead_code))]
+impl<T> PerCore<T> {
+    /// Returns a new `PerCore` with `values[n]` belonging to core `n`.
+    pub const fn new(values: [T; CORES]) -> PerCore<T> {
+        PerCore(values)
+    }
+
+    /// Returns the instance of the current core.
+    pub fn get(&self) -> &T {
+        &self.0[cpu_id()]
+    }
+
+    /// Returns the instance of `core`.
+    #[allow(dead_code)] // not used yet.
+    pub fn get_for(&self, core: usize) -> &T {
+        &self.0[core]
+    }
+}
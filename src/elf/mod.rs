@@ -0,0 +1,439 @@
+//! ELF64 executable parsing and loading.
+//!
+//! Tavern runs without an MMU, so a process's image is loaded wherever the
+//! allocator finds room for it. Only position-independent executables
+//! (`ET_DYN`, e.g. linked with `-pie`) can run at an arbitrary address; their
+//! `R_AARCH64_RELATIVE` relocations are applied while loading.
+
+mod stack;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::stack::{auxv, InitialStack};
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The size of a page. Segments are loaded at page granularity.
+pub const PAGE_SIZE: u64 = 0x1000;
+
+const MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const CLASS_64: u8 = 2;
+const DATA_LITTLE_ENDIAN: u8 = 1;
+const VERSION_CURRENT: u8 = 1;
+const TYPE_DYN: u16 = 3;
+const MACHINE_AARCH64: u16 = 183;
+
+const HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const RELA_SIZE: u64 = 24;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_GNU_STACK: u32 = 0x6474_e551;
+
+const DT_NULL: u64 = 0;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+
+const R_AARCH64_NONE: u32 = 0;
+const R_AARCH64_RELATIVE: u32 = 1027;
+
+/// Errors from parsing or loading an ELF image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Error {
+    /// The image does not start with the ELF magic number.
+    BadMagic,
+    /// The image is not a 64-bit, little-endian, version 1 ELF file.
+    Unsupported,
+    /// The image is not built for AArch64.
+    WrongMachine(u16),
+    /// The image is a fixed-address executable (`ET_EXEC`), or not an
+    /// executable at all.
+    NotPositionIndependent(u16),
+    /// A header, segment or table lies outside the image.
+    Truncated,
+    /// A segment is malformed, e.g. its file size exceeds its memory size.
+    BadSegment,
+    /// The image has no loadable segments.
+    NoSegments,
+    /// The image needs a relocation the loader does not implement.
+    UnsupportedRelocation(u32),
+    /// A relocation targets memory outside the loaded segments.
+    BadRelocation,
+    /// The destination is too small or misaligned for the image.
+    BadDestination,
+    /// The arguments and environment do not fit on the stack.
+    StackOverflow,
+    /// Memory for the image or stack could not be allocated.
+    #[cfg_attr(test, allow(dead_code))]
+    OutOfMemory,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadMagic => write!(f, "not an ELF file"),
+            Error::Unsupported => write!(f, "not a 64-bit little-endian ELF file"),
+            Error::WrongMachine(machine) => write!(f, "machine {machine} is not AArch64"),
+            Error::NotPositionIndependent(kind) => {
+                write!(f, "type {kind} is not a position-independent executable")
+            }
+            Error::Truncated => write!(f, "truncated image"),
+            Error::BadSegment => write!(f, "malformed segment"),
+            Error::NoSegments => write!(f, "no loadable segments"),
+            Error::UnsupportedRelocation(kind) => write!(f, "unsupported relocation {kind}"),
+            Error::BadRelocation => write!(f, "relocation out of bounds"),
+            Error::BadDestination => write!(f, "destination does not fit the image"),
+            Error::StackOverflow => write!(f, "arguments do not fit on the stack"),
+            Error::OutOfMemory => write!(f, "out of memory"),
+        }
+    }
+}
+
+/// Access permissions of a segment.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Flags(u32);
+
+impl Flags {
+    pub const EXECUTE: Flags = Flags(1);
+    pub const WRITE: Flags = Flags(2);
+    pub const READ: Flags = Flags(4);
+
+    /// Returns `true` if every permission in `other` is set in `self`.
+    pub fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |flag, c| if self.contains(flag) { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(Flags::READ, 'r'),
+            flag(Flags::WRITE, 'w'),
+            flag(Flags::EXECUTE, 'x')
+        )
+    }
+}
+
+/// A program header.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ProgramHeader {
+    pub kind: u32,
+    pub flags: Flags,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+    pub align: u64,
+}
+
+/// A segment of a loaded image, at its load address.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Segment {
+    pub start: u64,
+    pub size: u64,
+    pub flags: Flags,
+}
+
+/// The result of loading an image.
+#[derive(Debug)]
+pub struct Loaded {
+    /// The address of the entry point.
+    pub entry: u64,
+    /// The loaded segments. Without an MMU their permissions are not
+    /// enforced, but they are kept for the process.
+    pub segments: Vec<Segment>,
+    /// Auxiliary vector entries describing the image, for `InitialStack`.
+    pub auxv: Vec<(u64, u64)>,
+}
+
+/// A parsed ELF64 executable.
+#[derive(Debug)]
+pub struct Elf<'a> {
+    bytes: &'a [u8],
+    entry: u64,
+    program_headers: Vec<ProgramHeader>,
+    program_header_offset: u64,
+}
+
+fn read<const N: usize>(bytes: &[u8], offset: u64) -> Result<[u8; N], Error> {
+    let start = usize::try_from(offset).map_err(|_| Error::Truncated)?;
+    let end = start.checked_add(N).ok_or(Error::Truncated)?;
+    let bytes = bytes.get(start..end).ok_or(Error::Truncated)?;
+    Ok(bytes.try_into().expect("N bytes"))
+}
+
+fn read_u16(bytes: &[u8], offset: u64) -> Result<u16, Error> {
+    read(bytes, offset).map(u16::from_le_bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: u64) -> Result<u32, Error> {
+    read(bytes, offset).map(u32::from_le_bytes)
+}
+
+fn read_u64(bytes: &[u8], offset: u64) -> Result<u64, Error> {
+    read(bytes, offset).map(u64::from_le_bytes)
+}
+
+fn align_down(addr: u64, align: u64) -> u64 {
+    addr & !(align - 1)
+}
+
+fn align_up(addr: u64, align: u64) -> Option<u64> {
+    Some(addr.checked_add(align - 1)? & !(align - 1))
+}
+
+impl<'a> Elf<'a> {
+    /// Parses and validates the ELF header and program headers of `bytes`.
+    pub fn parse(bytes: &'a [u8]) -> Result<Elf<'a>, Error> {
+        let ident: [u8; 16] = read(bytes, 0).map_err(|_| Error::BadMagic)?;
+        if ident[..4] != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        if ident[4] != CLASS_64 || ident[5] != DATA_LITTLE_ENDIAN || ident[6] != VERSION_CURRENT
+        {
+            return Err(Error::Unsupported);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(Error::Truncated);
+        }
+
+        let machine = read_u16(bytes, 18)?;
+        if machine != MACHINE_AARCH64 {
+            return Err(Error::WrongMachine(machine));
+        }
+        let kind = read_u16(bytes, 16)?;
+        if kind != TYPE_DYN {
+            return Err(Error::NotPositionIndependent(kind));
+        }
+
+        let entry = read_u64(bytes, 24)?;
+        let program_header_offset = read_u64(bytes, 32)?;
+        let program_header_size = read_u16(bytes, 54)? as usize;
+        let program_header_count = read_u16(bytes, 56)? as u64;
+        if program_header_size != PROGRAM_HEADER_SIZE {
+            return Err(Error::Unsupported);
+        }
+
+        let mut program_headers = Vec::new();
+        for i in 0..program_header_count {
+            let offset = program_header_offset
+                .checked_add(i * PROGRAM_HEADER_SIZE as u64)
+                .ok_or(Error::Truncated)?;
+            let header = read::<PROGRAM_HEADER_SIZE>(bytes, offset)?;
+            let header = ProgramHeader {
+                kind: read_u32(&header, 0)?,
+                flags: Flags(read_u32(&header, 4)? & 0b111),
+                offset: read_u64(&header, 8)?,
+                vaddr: read_u64(&header, 16)?,
+                file_size: read_u64(&header, 32)?,
+                mem_size: read_u64(&header, 40)?,
+                align: read_u64(&header, 48)?,
+            };
+            if header.kind == PT_LOAD || header.kind == PT_DYNAMIC {
+                Self::validate(bytes, &header)?;
+            }
+            program_headers.push(header);
+        }
+
+        let elf = Elf {
+            bytes,
+            entry,
+            program_headers,
+            program_header_offset,
+        };
+        if elf.loadable().next().is_none() {
+            return Err(Error::NoSegments);
+        }
+        Ok(elf)
+    }
+
+    fn validate(bytes: &[u8], header: &ProgramHeader) -> Result<(), Error> {
+        let file_end = header.offset.checked_add(header.file_size);
+        if file_end.is_none_or(|end| end > bytes.len() as u64) {
+            return Err(Error::Truncated);
+        }
+        if header.file_size > header.mem_size
+            || header.vaddr.checked_add(header.mem_size).is_none()
+            || (header.align > 1 && !header.align.is_power_of_two())
+        {
+            return Err(Error::BadSegment);
+        }
+        Ok(())
+    }
+
+    /// Returns the program headers of the `PT_LOAD` segments.
+    pub fn loadable(&self) -> impl Iterator<Item = &ProgramHeader> {
+        self.program_headers
+            .iter()
+            .filter(|header| header.kind == PT_LOAD)
+    }
+
+    /// Returns the stack size the program asks for, if any: the memory size
+    /// of its `PT_GNU_STACK` header, as set by `ld -z stack-size=`.
+    pub fn stack_size(&self) -> Option<u64> {
+        self.program_headers
+            .iter()
+            .find(|header| header.kind == PT_GNU_STACK)
+            .map(|header| header.mem_size)
+            .filter(|&size| size != 0)
+    }
+
+    /// Returns the page-aligned range of link-time addresses the loadable
+    /// segments occupy.
+    fn span(&self) -> (u64, u64) {
+        let start = self.loadable().map(|header| header.vaddr).min();
+        let end = self
+            .loadable()
+            .map(|header| header.vaddr + header.mem_size)
+            .max();
+        let start = align_down(start.unwrap_or(0), PAGE_SIZE);
+        let end = align_up(end.unwrap_or(0), PAGE_SIZE).unwrap_or(u64::MAX);
+        (start, end)
+    }
+
+    /// Returns the number of bytes of memory the loaded image needs.
+    pub fn mem_size(&self) -> u64 {
+        let (start, end) = self.span();
+        end - start
+    }
+
+    /// Returns the alignment the image must be loaded at.
+    pub fn align(&self) -> u64 {
+        self.loadable()
+            .map(|header| header.align)
+            .fold(PAGE_SIZE, u64::max)
+    }
+
+    /// Loads the image into `dest`, which lives at address `base`. `dest` must
+    /// be `mem_size()` bytes long and `base` aligned to `align()`. Copies each
+    /// segment, zero-fills the rest of the image (including `.bss`), and
+    /// applies relocations.
+    pub fn load(&self, dest: &mut [u8], base: u64) -> Result<Loaded, Error> {
+        if dest.len() as u64 != self.mem_size() || !base.is_multiple_of(self.align()) {
+            return Err(Error::BadDestination);
+        }
+
+        let (start, _) = self.span();
+        let bias = base.wrapping_sub(start);
+        dest.fill(0);
+
+        let mut segments = Vec::new();
+        for header in self.loadable() {
+            let at = (header.vaddr - start) as usize;
+            let offset = header.offset as usize;
+            let file_size = header.file_size as usize;
+            dest[at..at + file_size].copy_from_slice(&self.bytes[offset..offset + file_size]);
+            segments.push(Segment {
+                start: header.vaddr.wrapping_add(bias),
+                size: header.mem_size,
+                flags: header.flags,
+            });
+        }
+
+        self.relocate(dest, start, bias)?;
+
+        let entry = self.entry.wrapping_add(bias);
+        let mut auxv = alloc::vec![
+            (auxv::AT_PHENT, PROGRAM_HEADER_SIZE as u64),
+            (auxv::AT_PHNUM, self.program_headers.len() as u64),
+            (auxv::AT_PAGESZ, PAGE_SIZE),
+            (auxv::AT_BASE, 0),
+            (auxv::AT_ENTRY, entry),
+        ];
+        if let Some(phdr) = self.program_header_addr() {
+            auxv.insert(0, (auxv::AT_PHDR, phdr.wrapping_add(bias)));
+        }
+
+        Ok(Loaded {
+            entry,
+            segments,
+            auxv,
+        })
+    }
+
+    /// Returns the link-time address of the program headers if a loadable
+    /// segment contains them.
+    fn program_header_addr(&self) -> Option<u64> {
+        let offset = self.program_header_offset;
+        self.loadable()
+            .find(|header| {
+                header.offset <= offset && offset < header.offset + header.file_size
+            })
+            .map(|header| header.vaddr + (offset - header.offset))
+    }
+
+    /// Applies the relocations in the `PT_DYNAMIC` segment to the image in
+    /// `dest`, which was linked at `start` and loaded `bias` bytes away.
+    fn relocate(&self, dest: &mut [u8], start: u64, bias: u64) -> Result<(), Error> {
+        let Some(dynamic) = self
+            .program_headers
+            .iter()
+            .find(|header| header.kind == PT_DYNAMIC)
+        else {
+            return Ok(());
+        };
+
+        let (mut rela, mut rela_size, mut rela_entry) = (None, 0, RELA_SIZE);
+        // `parse()` checked that the segment lies within the file.
+        let mut offset = dynamic.offset;
+        while offset + 16 <= dynamic.offset + dynamic.file_size {
+            let tag = read_u64(self.bytes, offset)?;
+            let value = read_u64(self.bytes, offset + 8)?;
+            match tag {
+                DT_NULL => break,
+                DT_RELA => rela = Some(value),
+                DT_RELASZ => rela_size = value,
+                DT_RELAENT => rela_entry = value,
+                _ => {}
+            }
+            offset += 16;
+        }
+
+        let Some(rela) = rela else {
+            return Ok(());
+        };
+        if rela_entry != RELA_SIZE {
+            return Err(Error::BadRelocation);
+        }
+
+        let table = rela.checked_sub(start).ok_or(Error::BadRelocation)?;
+        for i in 0..rela_size / RELA_SIZE {
+            let field = |at: u64| {
+                let offset = table.checked_add(i * RELA_SIZE + at).ok_or(Error::BadRelocation)?;
+                read_u64(dest, offset).map_err(|_| Error::BadRelocation)
+            };
+            let target = field(0)?;
+            let kind = field(8)? as u32;
+            let addend = field(16)?;
+            match kind {
+                R_AARCH64_NONE => {}
+                R_AARCH64_RELATIVE => {
+                    let at = target.checked_sub(start).ok_or(Error::BadRelocation)?;
+                    let at = usize::try_from(at).map_err(|_| Error::BadRelocation)?;
+                    let value = bias.wrapping_add(addend).to_le_bytes();
+                    dest.get_mut(at..)
+                        .and_then(|rest| rest.get_mut(..8))
+                        .ok_or(Error::BadRelocation)?
+                        .copy_from_slice(&value);
+                }
+                _ => return Err(Error::UnsupportedRelocation(kind)),
+            }
+        }
+        Ok(())
+    }
+}
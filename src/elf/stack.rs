@@ -0,0 +1,126 @@
+use super::Error;
+
+/// Auxiliary vector entry types.
+pub mod auxv {
+    pub const AT_NULL: u64 = 0;
+    pub const AT_PHDR: u64 = 3;
+    pub const AT_PHENT: u64 = 4;
+    pub const AT_PHNUM: u64 = 5;
+    pub const AT_PAGESZ: u64 = 6;
+    pub const AT_BASE: u64 = 7;
+    pub const AT_ENTRY: u64 = 9;
+}
+
+/// The stack pointer must stay 16-byte aligned.
+const STACK_ALIGN: u64 = 16;
+
+/// The initial stack of a new process, laid out as the System V ABI
+/// describes. From the stack pointer up:
+///
+/// ```text
+/// argc
+/// argv[0] .. argv[argc - 1], NULL
+/// envp[0] .. envp[n - 1], NULL
+/// auxv pairs, (AT_NULL, 0)
+/// padding
+/// argument and environment strings
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct InitialStack {
+    /// The initial stack pointer, pointing at `argc`.
+    pub sp: u64,
+    pub argc: u64,
+    /// The address of `argv[0]`.
+    pub argv: u64,
+    /// The address of `envp[0]`.
+    pub envp: u64,
+}
+
+impl InitialStack {
+    /// Writes the initial stack for `argv`, `envp` and `auxv` to the end of
+    /// `stack`, the memory just below address `top`.
+    pub fn build(
+        stack: &mut [u8],
+        top: u64,
+        argv: &[&str],
+        envp: &[&str],
+        auxv: &[(u64, u64)],
+    ) -> Result<InitialStack, Error> {
+        let bottom = top
+            .checked_sub(stack.len() as u64)
+            .ok_or(Error::StackOverflow)?;
+        let mut writer = Writer { stack, bottom };
+
+        // Strings go at the very top, environment first so that argv[0] has
+        // the lowest address.
+        let mut cursor = top;
+        let mut push_str = |writer: &mut Writer, s: &str| -> Result<u64, Error> {
+            cursor = cursor
+                .checked_sub(s.len() as u64 + 1)
+                .ok_or(Error::StackOverflow)?;
+            writer.write(cursor, s.as_bytes())?;
+            writer.write(cursor + s.len() as u64, &[0])?;
+            Ok(cursor)
+        };
+        let mut env_addrs = alloc::vec::Vec::with_capacity(envp.len());
+        for s in envp.iter().rev() {
+            env_addrs.push(push_str(&mut writer, s)?);
+        }
+        let mut arg_addrs = alloc::vec::Vec::with_capacity(argv.len());
+        for s in argv.iter().rev() {
+            arg_addrs.push(push_str(&mut writer, s)?);
+        }
+
+        let words = 1 + (argv.len() + 1) + (envp.len() + 1) + 2 * (auxv.len() + 1);
+        let sp = cursor
+            .checked_sub(words as u64 * 8)
+            .ok_or(Error::StackOverflow)?
+            & !(STACK_ALIGN - 1);
+
+        let mut at = sp;
+        let mut push_word = |writer: &mut Writer, word: u64| -> Result<(), Error> {
+            writer.write(at, &word.to_le_bytes())?;
+            at += 8;
+            Ok(())
+        };
+        push_word(&mut writer, argv.len() as u64)?;
+        let argv_addr = sp + 8;
+        for addr in arg_addrs.iter().rev() {
+            push_word(&mut writer, *addr)?;
+        }
+        push_word(&mut writer, 0)?;
+        let envp_addr = argv_addr + (argv.len() as u64 + 1) * 8;
+        for addr in env_addrs.iter().rev() {
+            push_word(&mut writer, *addr)?;
+        }
+        push_word(&mut writer, 0)?;
+        for &(kind, value) in auxv.iter().chain(&[(auxv::AT_NULL, 0)]) {
+            push_word(&mut writer, kind)?;
+            push_word(&mut writer, value)?;
+        }
+
+        Ok(InitialStack {
+            sp,
+            argc: argv.len() as u64,
+            argv: argv_addr,
+            envp: envp_addr,
+        })
+    }
+}
+
+/// Writes to `stack` by address.
+struct Writer<'a> {
+    stack: &'a mut [u8],
+    bottom: u64,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, addr: u64, bytes: &[u8]) -> Result<(), Error> {
+        let at = addr.checked_sub(self.bottom).ok_or(Error::StackOverflow)? as usize;
+        self.stack
+            .get_mut(at..at + bytes.len())
+            .ok_or(Error::StackOverflow)?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+mod parse {
+    use crate::elf::{Elf, Error, Flags};
+
+    /// Builds a position-independent executable with a text segment holding
+    /// the headers and a relocation table, and a data segment with `.bss`.
+    pub fn image() -> Vec<u8> {
+        let mut image = vec![0u8; 0x220];
+        let mut put = |at: usize, bytes: &[u8]| image[at..at + bytes.len()].copy_from_slice(bytes);
+
+        put(0, &[0x7f, b'E', b'L', b'F', 2, 1, 1]);
+        put(16, &3u16.to_le_bytes()); // ET_DYN
+        put(18, &183u16.to_le_bytes()); // EM_AARCH64
+        put(24, &0x80u64.to_le_bytes()); // entry
+        put(32, &64u64.to_le_bytes()); // phoff
+        put(54, &56u16.to_le_bytes());
+        put(56, &3u16.to_le_bytes());
+
+        let headers: [(u32, u32, u64, u64, u64, u64); 3] = [
+            (1, 0b101, 0, 0, 0x200, 0x200),
+            (1, 0b110, 0x200, 0x1200, 0x20, 0x100),
+            (2, 0b110, 0x180, 0x180, 0x40, 0x40),
+        ];
+        for (i, (kind, flags, offset, vaddr, file_size, mem_size)) in headers.iter().enumerate() {
+            let at = 64 + i * 56;
+            put(at, &kind.to_le_bytes());
+            put(at + 4, &flags.to_le_bytes());
+            put(at + 8, &offset.to_le_bytes());
+            put(at + 16, &vaddr.to_le_bytes());
+            put(at + 32, &file_size.to_le_bytes());
+            put(at + 40, &mem_size.to_le_bytes());
+            put(at + 48, &0x1000u64.to_le_bytes());
+        }
+
+        // One R_AARCH64_RELATIVE relocation of the first data word.
+        put(0x100, &0x1200u64.to_le_bytes());
+        put(0x108, &1027u64.to_le_bytes());
+        put(0x110, &0x150u64.to_le_bytes());
+
+        // DT_RELA, DT_RELASZ, DT_RELAENT, DT_NULL.
+        for (i, (tag, value)) in [(7u64, 0x100u64), (8, 24), (9, 24), (0, 0)].iter().enumerate() {
+            put(0x180 + i * 16, &tag.to_le_bytes());
+            put(0x188 + i * 16, &value.to_le_bytes());
+        }
+
+        put(0x200, &[0xaa; 0x20]);
+        image
+    }
+
+    #[test]
+    fn parses_headers() {
+        let image = image();
+        let elf = Elf::parse(&image).expect("valid image");
+        let loadable: Vec<_> = elf.loadable().collect();
+        assert_eq!(loadable.len(), 2);
+        assert_eq!(loadable[0].flags, Flags::READ | Flags::EXECUTE);
+        assert_eq!(loadable[1].flags, Flags::READ | Flags::WRITE);
+        assert_eq!(loadable[1].vaddr, 0x1200);
+        assert_eq!(elf.mem_size(), 0x2000);
+        assert_eq!(elf.align(), 0x1000);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert_eq!(Elf::parse(b"\x7fELF").unwrap_err(), Error::BadMagic);
+        assert_eq!(Elf::parse(b"MZ\x90\x00").unwrap_err(), Error::BadMagic);
+
+        let mut image = image();
+        image[4] = 1;
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::Unsupported);
+
+        let mut image = self::image();
+        image[18] = 62;
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::WrongMachine(62));
+
+        let mut image = self::image();
+        image[16] = 2;
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::NotPositionIndependent(2));
+
+        let image = self::image();
+        assert_eq!(Elf::parse(&image[..0x100]).unwrap_err(), Error::Truncated);
+    }
+
+    #[test]
+    fn rejects_bad_segments() {
+        // File size larger than memory size.
+        let mut image = image();
+        image[64 + 56 + 40..64 + 56 + 48].copy_from_slice(&0x10u64.to_le_bytes());
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::BadSegment);
+
+        // No PT_LOAD segments.
+        let mut image = self::image();
+        image[64] = 6;
+        image[64 + 56] = 6;
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::NoSegments);
+    }
+
+    #[test]
+    fn rejects_bad_dynamic() {
+        const DYNAMIC: usize = 64 + 2 * 56;
+
+        // Offset past the end of the address space.
+        let mut image = image();
+        image[DYNAMIC + 8..DYNAMIC + 16].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::Truncated);
+
+        // File size past the end of the file.
+        let mut image = self::image();
+        image[DYNAMIC + 32..DYNAMIC + 40].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(Elf::parse(&image).unwrap_err(), Error::Truncated);
+    }
+
+    #[test]
+    fn stack_size() {
+        let mut image = image();
+        assert_eq!(Elf::parse(&image).unwrap().stack_size(), None);
+
+        // Turn the PT_DYNAMIC header into a PT_GNU_STACK one.
+        image[64 + 2 * 56..64 + 2 * 56 + 4].copy_from_slice(&0x6474_e551u32.to_le_bytes());
+        assert_eq!(Elf::parse(&image).unwrap().stack_size(), Some(0x40));
+    }
+
+    #[test]
+    fn displays_flags() {
+        assert_eq!(format!("{}", Flags::READ | Flags::EXECUTE), "r-x");
+        assert_eq!(format!("{}", Flags::READ | Flags::WRITE), "rw-");
+    }
+}
+
+mod load {
+    use super::parse::image;
+    use crate::elf::{auxv, Elf, Error, Flags, Segment};
+
+    const BASE: u64 = 0x40_0000;
+
+    #[test]
+    fn loads_segments() {
+        let image = image();
+        let elf = Elf::parse(&image).unwrap();
+        let mut dest = vec![0xffu8; elf.mem_size() as usize];
+        let loaded = elf.load(&mut dest, BASE).expect("loads");
+
+        assert_eq!(loaded.entry, BASE + 0x80);
+        assert_eq!(
+            loaded.segments,
+            [
+                Segment {
+                    start: BASE,
+                    size: 0x200,
+                    flags: Flags::READ | Flags::EXECUTE,
+                },
+                Segment {
+                    start: BASE + 0x1200,
+                    size: 0x100,
+                    flags: Flags::READ | Flags::WRITE,
+                },
+            ]
+        );
+        assert_eq!(dest[..0x200], image[..0x200]);
+        assert!(dest[0x200..0x1200].iter().all(|&byte| byte == 0));
+        assert!(dest[0x1208..0x1220].iter().all(|&byte| byte == 0xaa));
+        assert!(dest[0x1220..].iter().all(|&byte| byte == 0), ".bss is zeroed");
+
+        assert!(loaded.auxv.contains(&(auxv::AT_PHDR, BASE + 64)));
+        assert!(loaded.auxv.contains(&(auxv::AT_PHNUM, 3)));
+        assert!(loaded.auxv.contains(&(auxv::AT_ENTRY, BASE + 0x80)));
+    }
+
+    #[test]
+    fn applies_relative_relocations() {
+        let image = image();
+        let elf = Elf::parse(&image).unwrap();
+        let mut dest = vec![0u8; elf.mem_size() as usize];
+        elf.load(&mut dest, BASE).unwrap();
+        assert_eq!(dest[0x1200..0x1208], (BASE + 0x150).to_le_bytes());
+    }
+
+    #[test]
+    fn rejects_unsupported_relocations() {
+        let mut image = image();
+        image[0x108..0x110].copy_from_slice(&257u64.to_le_bytes()); // R_AARCH64_ABS64
+        let elf = Elf::parse(&image).unwrap();
+        let mut dest = vec![0u8; elf.mem_size() as usize];
+        assert_eq!(
+            elf.load(&mut dest, BASE).unwrap_err(),
+            Error::UnsupportedRelocation(257)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_relocation_table() {
+        let mut image = image();
+        image[0x188..0x190].copy_from_slice(&u64::MAX.to_le_bytes()); // DT_RELA
+        let elf = Elf::parse(&image).unwrap();
+        let mut dest = vec![0u8; elf.mem_size() as usize];
+        assert_eq!(elf.load(&mut dest, BASE).unwrap_err(), Error::BadRelocation);
+
+        let mut image = self::image();
+        image[0x100..0x108].copy_from_slice(&u64::MAX.to_le_bytes()); // r_offset
+        let elf = Elf::parse(&image).unwrap();
+        assert_eq!(elf.load(&mut dest, BASE).unwrap_err(), Error::BadRelocation);
+    }
+
+    #[test]
+    fn rejects_bad_destination() {
+        let image = image();
+        let elf = Elf::parse(&image).unwrap();
+        let mut dest = vec![0u8; elf.mem_size() as usize];
+        assert_eq!(elf.load(&mut dest, BASE + 8).unwrap_err(), Error::BadDestination);
+        assert_eq!(elf.load(&mut dest[1..], BASE).unwrap_err(), Error::BadDestination);
+    }
+}
+
+mod stack {
+    use crate::elf::{auxv, Error, InitialStack};
+
+    const TOP: u64 = 0x10_0000;
+
+    fn word(stack: &[u8], addr: u64) -> u64 {
+        let at = (addr - (TOP - stack.len() as u64)) as usize;
+        u64::from_le_bytes(stack[at..at + 8].try_into().unwrap())
+    }
+
+    fn string(stack: &[u8], addr: u64) -> &str {
+        let at = (addr - (TOP - stack.len() as u64)) as usize;
+        let len = stack[at..].iter().position(|&byte| byte == 0).unwrap();
+        core::str::from_utf8(&stack[at..at + len]).unwrap()
+    }
+
+    #[test]
+    fn lays_out_arguments() {
+        let mut stack = vec![0u8; 0x200];
+        let auxv = [(auxv::AT_PAGESZ, 0x1000)];
+        let initial =
+            InitialStack::build(&mut stack, TOP, &["init", "-v"], &["HOME=/"], &auxv).unwrap();
+
+        assert_eq!(initial.sp % 16, 0);
+        assert_eq!(initial.argc, 2);
+        assert_eq!(word(&stack, initial.sp), 2);
+        assert_eq!(initial.argv, initial.sp + 8);
+        assert_eq!(string(&stack, word(&stack, initial.argv)), "init");
+        assert_eq!(string(&stack, word(&stack, initial.argv + 8)), "-v");
+        assert_eq!(word(&stack, initial.argv + 16), 0);
+
+        assert_eq!(initial.envp, initial.argv + 24);
+        assert_eq!(string(&stack, word(&stack, initial.envp)), "HOME=/");
+        assert_eq!(word(&stack, initial.envp + 8), 0);
+
+        let auxv = initial.envp + 16;
+        assert_eq!(word(&stack, auxv), auxv::AT_PAGESZ);
+        assert_eq!(word(&stack, auxv + 8), 0x1000);
+        assert_eq!(word(&stack, auxv + 16), auxv::AT_NULL);
+    }
+
+    #[test]
+    fn overflows() {
+        let mut stack = vec![0u8; 32];
+        assert_eq!(
+            InitialStack::build(&mut stack, TOP, &["a long argument"], &[], &[]).unwrap_err(),
+            Error::StackOverflow
+        );
+    }
+}
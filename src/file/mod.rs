@@ -0,0 +1,64 @@
+//! Open files and the file descriptors processes refer to them by.
+//!
+//! A `File` is anything a process can read from or write to. Each process has
+//! a `FileTable` that maps its file descriptors to shared `Arc<dyn File>`s:
+//! `dup2` and `fork` share a file between descriptors, and it is closed when
+//! the last of them is.
+//!
+//! Besides pipes, IPC port handles (`ipc::PortHandle`) and shared memory
+//! handles (`vm::shm::SharedMemory`) are files.
+//!
+//! Like the primitives in `sync`, files never block. An operation that cannot
+//! proceed returns `Error::WouldBlock` with a `Waiter`, and the system call
+//! sleeps on it and tries again.
+
+mod pipe;
+mod table;
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+
+use crate::sync::Waiter;
+
+pub use self::pipe::pipe;
+#[cfg_attr(test, allow(unused_imports))]
+pub use self::table::{Fd, FileTable};
+
+/// Why a file operation did not complete.
+#[derive(Debug)]
+pub enum Error {
+    /// The operation cannot proceed yet. Try again once the waiter is woken.
+    WouldBlock(Waiter),
+    /// The file is a pipe that nobody can read from anymore.
+    BrokenPipe,
+    /// The file does not support the operation.
+    Unsupported,
+}
+
+/// An open file.
+pub trait File: fmt::Debug + Send + Sync {
+    /// Reads into `buf` and returns the number of bytes read, which is `0` at
+    /// the end of the file. Reads as much as is available, at least one byte
+    /// unless `buf` is empty.
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Writes from `buf` and returns the number of bytes written, at least
+    /// one unless `buf` is empty.
+    fn write(&self, _buf: &[u8]) -> Result<usize, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Returns the file as an IPC port handle, if it is one.
+    fn as_port(&self) -> Option<&crate::ipc::PortHandle> {
+        None
+    }
+
+    /// Returns the file as a shared memory handle, if it is one.
+    fn as_shared_memory(&self) -> Option<&crate::vm::shm::SharedMemory> {
+        None
+    }
+}
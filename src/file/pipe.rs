@@ -0,0 +1,136 @@
+use alloc::sync::Arc;
+
+use super::{Error, File};
+use crate::mutex::Mutex;
+use crate::sync::WaitQueue;
+
+/// The most bytes a pipe buffers before writers block.
+pub const CAPACITY: usize = 4096;
+
+/// The bytes in a pipe and which of its ends are still open.
+#[derive(Debug)]
+struct Buffer {
+    bytes: [u8; CAPACITY],
+    /// The index of the oldest byte.
+    start: usize,
+    /// The number of bytes in the buffer.
+    len: usize,
+    reader_closed: bool,
+    writer_closed: bool,
+}
+
+impl Buffer {
+    /// Copies the oldest bytes into `buf`, removing them, and returns how
+    /// many were copied.
+    fn pop(&mut self, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len);
+        for byte in &mut buf[..count] {
+            *byte = self.bytes[self.start];
+            self.start = (self.start + 1) % CAPACITY;
+        }
+        self.len -= count;
+        count
+    }
+
+    /// Appends as much of `buf` as fits and returns how many bytes were
+    /// appended.
+    fn push(&mut self, buf: &[u8]) -> usize {
+        let count = buf.len().min(CAPACITY - self.len);
+        for &byte in &buf[..count] {
+            self.bytes[(self.start + self.len) % CAPACITY] = byte;
+            self.len += 1;
+        }
+        count
+    }
+}
+
+/// A pipe: a bounded buffer that one end writes and the other reads.
+#[derive(Debug)]
+struct Pipe {
+    buffer: Mutex<Buffer>,
+    /// Woken when bytes are written or the write end is closed.
+    readable: WaitQueue,
+    /// Woken when bytes are read or the read end is closed.
+    writable: WaitQueue,
+}
+
+/// The end of a pipe that is read from.
+#[derive(Debug)]
+struct ReadEnd(Arc<Pipe>);
+
+/// The end of a pipe that is written to.
+#[derive(Debug)]
+struct WriteEnd(Arc<Pipe>);
+
+/// Returns the read and write ends of a new, empty pipe.
+///
+/// Reading an empty pipe blocks until bytes are written, or returns `0` once
+/// the write end is closed. Writing to a full pipe blocks until bytes are
+/// read, and fails with `Error::BrokenPipe` once the read end is closed.
+pub fn pipe() -> (Arc<dyn File>, Arc<dyn File>) {
+    let pipe = Arc::new(Pipe {
+        buffer: Mutex::new(Buffer {
+            bytes: [0; CAPACITY],
+            start: 0,
+            len: 0,
+            reader_closed: false,
+            writer_closed: false,
+        }),
+        readable: WaitQueue::new(),
+        writable: WaitQueue::new(),
+    });
+    (Arc::new(ReadEnd(pipe.clone())), Arc::new(WriteEnd(pipe)))
+}
+
+impl File for ReadEnd {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut buffer = self.0.buffer.lock();
+        if buf.is_empty() || (buffer.len == 0 && buffer.writer_closed) {
+            return Ok(0);
+        }
+        if buffer.len == 0 {
+            // Writers wake the queue after taking the lock, so the wake-up
+            // cannot be missed.
+            return Err(Error::WouldBlock(self.0.readable.register()));
+        }
+
+        let count = buffer.pop(buf);
+        drop(buffer);
+        self.0.writable.wake_all();
+        Ok(count)
+    }
+}
+
+impl File for WriteEnd {
+    fn write(&self, buf: &[u8]) -> Result<usize, Error> {
+        let mut buffer = self.0.buffer.lock();
+        if buffer.reader_closed {
+            return Err(Error::BrokenPipe);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if buffer.len == CAPACITY {
+            return Err(Error::WouldBlock(self.0.writable.register()));
+        }
+
+        let count = buffer.push(buf);
+        drop(buffer);
+        self.0.readable.wake_all();
+        Ok(count)
+    }
+}
+
+impl Drop for ReadEnd {
+    fn drop(&mut self) {
+        self.0.buffer.lock().reader_closed = true;
+        self.0.writable.wake_all();
+    }
+}
+
+impl Drop for WriteEnd {
+    fn drop(&mut self) {
+        self.0.buffer.lock().writer_closed = true;
+        self.0.readable.wake_all();
+    }
+}
@@ -0,0 +1,75 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use super::File;
+
+/// A file descriptor: an index into a process's `FileTable`.
+pub type Fd = usize;
+
+/// The most files a process can have open at once.
+pub const MAX_FILES: usize = 64;
+
+/// The open files of a process, indexed by file descriptor.
+#[derive(Debug, Clone, Default)]
+pub struct FileTable {
+    files: Vec<Option<Arc<dyn File>>>,
+}
+
+impl FileTable {
+    /// Returns the file open at `fd`, if any.
+    pub fn get(&self, fd: Fd) -> Option<Arc<dyn File>> {
+        self.files.get(fd)?.clone()
+    }
+
+    /// Opens `file` at the lowest free file descriptor and returns it.
+    /// Returns `None` if `MAX_FILES` files are open.
+    pub fn insert(&mut self, file: Arc<dyn File>) -> Option<Fd> {
+        let fd = match self.files.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None if self.files.len() < MAX_FILES => {
+                self.files.push(None);
+                self.files.len() - 1
+            }
+            None => return None,
+        };
+        self.files[fd] = Some(file);
+        Some(fd)
+    }
+
+    /// Returns `true` if no more files can be opened.
+    pub fn is_full(&self) -> bool {
+        self.files.len() == MAX_FILES && self.files.iter().all(Option::is_some)
+    }
+
+    /// Closes `fd`. Returns `false` if no file was open at it.
+    pub fn close(&mut self, fd: Fd) -> bool {
+        let closed = self.files.get_mut(fd).and_then(Option::take).is_some();
+        while self.files.last().is_some_and(Option::is_none) {
+            self.files.pop();
+        }
+        closed
+    }
+
+    /// Makes `new` refer to the file open at `old`, closing whatever `new`
+    /// referred to. Returns `false` if no file is open at `old` or `new` is
+    /// out of range.
+    pub fn dup2(&mut self, old: Fd, new: Fd) -> bool {
+        let Some(file) = self.get(old) else {
+            return false;
+        };
+        if new >= MAX_FILES {
+            return false;
+        }
+
+        if self.files.len() <= new {
+            self.files.resize(new + 1, None);
+        }
+        self.files[new] = Some(file);
+        true
+    }
+
+    /// Closes every file.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
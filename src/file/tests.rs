@@ -0,0 +1,195 @@
+mod pipe {
+    use crate::file::pipe::CAPACITY as PIPE_CAPACITY;
+    use crate::file::{pipe, Error};
+    use std::thread;
+
+    #[test]
+    fn bytes_come_out_in_order() {
+        let (reader, writer) = pipe();
+        assert_eq!(writer.write(b"hello, ").unwrap(), 7);
+        assert_eq!(writer.write(b"pipe").unwrap(), 4);
+
+        let mut buf = [0; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"hello, p");
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"ipe");
+    }
+
+    #[test]
+    fn empty_pipes_block_readers() {
+        let (reader, writer) = pipe();
+        let mut buf = [0; 4];
+        let Err(Error::WouldBlock(waiter)) = reader.read(&mut buf) else {
+            panic!("read from an empty pipe");
+        };
+        assert!(!waiter.is_woken());
+
+        writer.write(b"x").unwrap();
+        assert!(waiter.is_woken());
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(reader.read(&mut []).unwrap(), 0);
+    }
+
+    #[test]
+    fn full_pipes_block_writers() {
+        let (reader, writer) = pipe();
+        let bytes = vec![7; PIPE_CAPACITY + 10];
+        assert_eq!(writer.write(&bytes).unwrap(), PIPE_CAPACITY, "writes are partial");
+        let Err(Error::WouldBlock(waiter)) = writer.write(&bytes) else {
+            panic!("wrote to a full pipe");
+        };
+
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).unwrap(), 16);
+        assert!(waiter.is_woken());
+        assert_eq!(writer.write(&bytes).unwrap(), 16);
+    }
+
+    #[test]
+    fn closed_write_end_is_end_of_file() {
+        let (reader, writer) = pipe();
+        writer.write(b"last").unwrap();
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"last");
+        let Err(Error::WouldBlock(waiter)) = reader.read(&mut buf) else {
+            panic!("read past the written bytes");
+        };
+
+        drop(writer);
+        assert!(waiter.is_woken());
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn ends_go_one_way() {
+        let (reader, writer) = pipe();
+        assert!(matches!(writer.read(&mut [0; 4]), Err(Error::Unsupported)));
+        assert!(matches!(reader.write(b"x"), Err(Error::Unsupported)));
+    }
+
+    #[test]
+    fn closed_read_end_breaks_the_pipe() {
+        let (reader, writer) = pipe();
+        writer.write(&vec![0; PIPE_CAPACITY]).unwrap();
+        let Err(Error::WouldBlock(waiter)) = writer.write(b"x") else {
+            panic!("wrote to a full pipe");
+        };
+
+        drop(reader);
+        assert!(waiter.is_woken());
+        assert!(matches!(writer.write(b"x"), Err(Error::BrokenPipe)));
+        assert!(matches!(writer.write(b""), Err(Error::BrokenPipe)));
+    }
+
+    #[test]
+    fn streams_between_threads() {
+        let (reader, writer) = pipe();
+        let bytes: Vec<u8> = (0..3 * PIPE_CAPACITY).map(|i| i as u8).collect();
+        let expected = bytes.clone();
+        let producer = thread::spawn(move || {
+            let mut written = 0;
+            while written < bytes.len() {
+                match writer.write(&bytes[written..]) {
+                    Ok(count) => written += count,
+                    Err(Error::WouldBlock(waiter)) => {
+                        while !waiter.is_woken() {
+                            thread::yield_now();
+                        }
+                    }
+                    Err(error) => panic!("{error:?}"),
+                }
+            }
+        });
+
+        let mut received = Vec::new();
+        let mut buf = [0; 1000];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(count) => received.extend_from_slice(&buf[..count]),
+                Err(Error::WouldBlock(waiter)) => {
+                    while !waiter.is_woken() {
+                        thread::yield_now();
+                    }
+                }
+                Err(error) => panic!("{error:?}"),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(received, expected);
+    }
+}
+
+mod table {
+    use crate::file::table::MAX_FILES;
+    use crate::file::{pipe, FileTable};
+    use std::sync::Arc;
+
+    #[test]
+    fn lowest_free_descriptor() {
+        let (reader, writer) = pipe();
+        let mut files = FileTable::default();
+        assert_eq!(files.insert(reader.clone()), Some(0));
+        assert_eq!(files.insert(writer.clone()), Some(1));
+        assert_eq!(files.insert(reader.clone()), Some(2));
+
+        assert!(files.close(0));
+        assert!(!files.close(0));
+        assert!(!files.close(MAX_FILES + 1));
+        assert!(files.get(0).is_none());
+        assert_eq!(files.insert(writer.clone()), Some(0));
+        assert!(Arc::ptr_eq(&files.get(0).unwrap(), &writer));
+    }
+
+    #[test]
+    fn table_is_bounded() {
+        let (reader, _) = pipe();
+        let mut files = FileTable::default();
+        for fd in 0..MAX_FILES {
+            assert!(!files.is_full());
+            assert_eq!(files.insert(reader.clone()), Some(fd));
+        }
+        assert!(files.is_full());
+        assert_eq!(files.insert(reader.clone()), None);
+        files.close(3);
+        assert!(!files.is_full());
+    }
+
+    #[test]
+    fn dup2_shares_the_file() {
+        let (reader, writer) = pipe();
+        let mut files = FileTable::default();
+        files.insert(reader.clone());
+        files.insert(writer);
+        assert!(files.dup2(0, 1), "replaces the write end");
+        assert!(files.dup2(0, 9));
+        assert!(Arc::ptr_eq(&files.get(1).unwrap(), &reader));
+        assert!(Arc::ptr_eq(&files.get(9).unwrap(), &reader));
+        assert!(!files.dup2(5, 6), "nothing open at 5");
+        assert!(!files.dup2(0, MAX_FILES));
+        assert_eq!(files.insert(reader.clone()), Some(2));
+
+        // Closing the write end ends the pipe.
+        assert_eq!(files.get(0).unwrap().read(&mut []).unwrap(), 0);
+        assert_eq!(files.get(0).unwrap().read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn forked_tables_share_files() {
+        let (reader, writer) = pipe();
+        let mut parent = FileTable::default();
+        parent.insert(reader);
+        parent.insert(writer);
+        let mut child = parent.clone();
+
+        child.close(1);
+        parent.get(1).unwrap().write(b"x").unwrap();
+        let mut buf = [0; 2];
+        assert_eq!(child.get(0).unwrap().read(&mut buf).unwrap(), 1);
+
+        parent.clear();
+        assert_eq!(child.get(0).unwrap().read(&mut buf).unwrap(), 0, "last writer closed");
+    }
+}
@@ -0,0 +1,149 @@
+//! The Cortex-A53 generic timer.
+//!
+//! Every core has its own set of generic timers clocked by a shared system
+//! counter. Tavern uses the EL1 physical timer (CNTP), whose interrupt is
+//! routed to its core by the local interrupt controller.
+//!
+//! The system counter and the BCM2835 system timer differ in frequency and
+//! epoch. When core 0's software timers are driven by the system timer, the
+//! other cores use `SystemTimeGenericTimer`, which keeps the system timer's
+//! time, so that every core agrees on `now()`.
+
+/// CNTP_CTL_EL0 bit 0: the timer is enabled.
+const CTL_ENABLE: u64 = 1 << 0;
+/// CNTP_CTL_EL0 bit 1: the timer interrupt is masked.
+const CTL_IMASK: u64 = 1 << 1;
+/// CNTP_CTL_EL0 bit 2: the timer condition is met. Read only.
+#[allow(dead_code)] // not used yet.
+const CTL_ISTATUS: u64 = 1 << 2;
+
+const MICROS_PER_SECOND: u128 = 1_000_000;
+
+/// The EL1 physical timer of the current core.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct GenericTimer;
+
+#[cfg_attr(test, allow(dead_code))]
+impl GenericTimer {
+    /// Returns the frequency of the system counter in Hz (CNTFRQ_EL0).
+    pub fn frequency(&self) -> u64 {
+        let frequency: u64;
+        unsafe {
+            core::arch::asm!("mrs {0}, CNTFRQ_EL0", out(reg) frequency);
+        }
+        frequency
+    }
+
+    /// Returns the current value of the system counter (CNTPCT_EL0).
+    pub fn count(&self) -> u64 {
+        let count: u64;
+        unsafe {
+            // The ISB keeps the read from being executed out of order.
+            core::arch::asm!("isb", "mrs {0}, CNTPCT_EL0", out(reg) count);
+        }
+        count
+    }
+
+    /// Sets up the timer to fire `ticks` counter ticks from now
+    /// (CNTP_TVAL_EL0).
+    #[allow(dead_code)] // not used yet.
+    pub fn set_timer_value(&mut self, ticks: u32) {
+        unsafe {
+            core::arch::asm!("msr CNTP_TVAL_EL0, {0:x}", in(reg) ticks as u64);
+        }
+    }
+
+    /// Sets up the timer to fire when the counter reaches `ticks`
+    /// (CNTP_CVAL_EL0).
+    pub fn set_compare_value(&mut self, ticks: u64) {
+        unsafe {
+            core::arch::asm!("msr CNTP_CVAL_EL0, {0}", in(reg) ticks);
+        }
+    }
+
+    /// Reads the timer control register (CNTP_CTL_EL0).
+    #[allow(dead_code)] // not used yet.
+    fn control(&self) -> u64 {
+        let control: u64;
+        unsafe {
+            core::arch::asm!("mrs {0}, CNTP_CTL_EL0", out(reg) control);
+        }
+        control
+    }
+
+    /// Writes the timer control register (CNTP_CTL_EL0).
+    fn set_control(&mut self, control: u64) {
+        unsafe {
+            core::arch::asm!("msr CNTP_CTL_EL0, {0}", "isb", in(reg) control);
+        }
+    }
+
+    /// Enables the timer and unmasks its interrupt.
+    pub fn enable(&mut self) {
+        self.set_control(CTL_ENABLE);
+    }
+
+    /// Masks the timer interrupt while leaving the timer enabled.
+    pub fn mask(&mut self) {
+        self.set_control(CTL_ENABLE | CTL_IMASK);
+    }
+
+    /// Returns `true` if the timer condition is met.
+    #[allow(dead_code)] // not used yet.
+    pub fn is_pending(&self) -> bool {
+        self.control() & CTL_ISTATUS != 0
+    }
+
+    /// Converts counter ticks to microseconds.
+    fn ticks_to_us(&self, ticks: u64) -> u64 {
+        (ticks as u128 * MICROS_PER_SECOND / self.frequency() as u128) as u64
+    }
+
+    /// Converts microseconds to counter ticks, saturating at `u64::MAX`.
+    fn us_to_ticks(&self, us: u64) -> u64 {
+        let ticks = us as u128 * self.frequency() as u128 / MICROS_PER_SECOND;
+        ticks.min(u64::MAX as u128) as u64
+    }
+}
+
+/// The generic timer drives the kernel's software timers on its core.
+impl crate::timer::Clock for GenericTimer {
+    fn now(&self) -> u64 {
+        self.ticks_to_us(self.count())
+    }
+
+    fn arm(&mut self, deadline: u64) {
+        let ticks = self.us_to_ticks(deadline);
+        self.set_compare_value(ticks);
+        self.enable();
+    }
+
+    /// The timer condition stays met until the timer is armed again, so the
+    /// interrupt is masked instead.
+    fn acknowledge(&mut self) {
+        self.mask();
+    }
+}
+
+/// The generic timer of the current core, keeping the time of the BCM2835
+/// system timer: deadlines are converted into a delay on the system counter.
+#[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct SystemTimeGenericTimer;
+
+impl crate::timer::Clock for SystemTimeGenericTimer {
+    fn now(&self) -> u64 {
+        super::timer::current_time()
+    }
+
+    fn arm(&mut self, deadline: u64) {
+        let mut timer = GenericTimer;
+        let delay = timer.us_to_ticks(deadline.saturating_sub(self.now()));
+        timer.set_compare_value(timer.count().saturating_add(delay));
+        timer.enable();
+    }
+
+    fn acknowledge(&mut self) {
+        GenericTimer.mask();
+    }
+}
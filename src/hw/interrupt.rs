@@ -17,6 +17,32 @@ pub enum Interrupt {
     Uart = 57,
 }
 
+impl Interrupt {
+    /// Every interrupt that can be enabled.
+    #[cfg_attr(test, allow(dead_code))]
+    pub const ALL: [Interrupt; 8] = [
+        Interrupt::Timer1,
+        Interrupt::Timer3,
+        Interrupt::Usb,
+        Interrupt::Gpio0,
+        Interrupt::Gpio1,
+        Interrupt::Gpio2,
+        Interrupt::Gpio3,
+        Interrupt::Uart,
+    ];
+
+    /// Returns the bit mask of this interrupt within its 32-bit register bank.
+    fn mask(self) -> u32 {
+        1 << (self as u32 % 32)
+    }
+
+    /// Returns `true` if this interrupt is in the second register bank
+    /// (interrupts 32..63).
+    fn is_high(self) -> bool {
+        self as u32 >= 32
+    }
+}
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -70,18 +96,27 @@ impl Controller {
 
     /// Enables the interrupt `int`.
     pub fn enable(&mut self, int: Interrupt) {
-        self.registers.ENABLE_IRQS_1.or_mask(1 << (int as u8))
+        match int.is_high() {
+            false => self.registers.ENABLE_IRQS_1.or_mask(int.mask()),
+            true => self.registers.ENABLE_IRQS_2.or_mask(int.mask()),
+        }
     }
 
     /// Disables the interrupt `int`.
     #[allow(dead_code)] // not currently used.
     pub fn disable(&mut self, int: Interrupt) {
-        self.registers.DISABLE_IRQS_1.and_mask(1 << (int as u32))
+        match int.is_high() {
+            false => self.registers.DISABLE_IRQS_1.and_mask(int.mask()),
+            true => self.registers.DISABLE_IRQS_2.and_mask(int.mask()),
+        }
     }
 
     /// Returns `true` if `int` is pending. Otherwise, returns `false`.
-    #[allow(dead_code)] // not currently used.
+    #[cfg_attr(test, allow(dead_code))]
     pub fn is_pending(&self, int: Interrupt) -> bool {
-        self.registers.IRQ_PENDING_1.has_mask(1 << (int as u8))
+        match int.is_high() {
+            false => self.registers.IRQ_PENDING_1.has_mask(int.mask()),
+            true => self.registers.IRQ_PENDING_2.has_mask(int.mask()),
+        }
     }
 }
@@ -0,0 +1,153 @@
+//! The BCM2836 local interrupt controller.
+//!
+//! Unlike the BCM2835 interrupt controller, which is shared by all cores, the
+//! local controller routes the per-core interrupts (the generic timers and the
+//! mailboxes) to each core and reports, per core, which source raised an IRQ.
+
+use crate::cpu::CORES;
+use crate::volatile::prelude::*;
+use crate::volatile::{ReadVolatile, Reserved, Volatile, WriteVolatile};
+
+/// The physical address of the local peripherals. They are outside of
+/// `IO_BASE`.
+const LOCAL_BASE: usize = 0x4000_0000;
+
+/// The number of mailboxes per core.
+pub const MAILBOXES: usize = 4;
+
+/// A per-core interrupt source, numbered by its bit in the core's IRQ source
+/// register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code)] // not all variants are used yet.
+pub enum LocalInterrupt {
+    /// Secure physical timer (CNTPS).
+    CntPs = 0,
+    /// Non-secure physical timer (CNTPNS). This is the EL1 physical timer.
+    CntPns = 1,
+    /// Hypervisor physical timer (CNTHP).
+    CntHp = 2,
+    /// Virtual timer (CNTV).
+    CntV = 3,
+    Mailbox0 = 4,
+    Mailbox1 = 5,
+    Mailbox2 = 6,
+    Mailbox3 = 7,
+    /// Any interrupt from the BCM2835 interrupt controller.
+    Gpu = 8,
+    /// Performance monitors.
+    Pmu = 9,
+    AxiOutstanding = 10,
+    /// The local timer.
+    LocalTimer = 11,
+}
+
+impl LocalInterrupt {
+    /// Returns the interrupt for mailbox `mailbox`.
+    #[allow(dead_code)] // not used yet.
+    pub fn mailbox(mailbox: usize) -> LocalInterrupt {
+        [
+            LocalInterrupt::Mailbox0,
+            LocalInterrupt::Mailbox1,
+            LocalInterrupt::Mailbox2,
+            LocalInterrupt::Mailbox3,
+        ][mailbox]
+    }
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    /// Selects the clock source and increment of the core timers.
+    CONTROL: Volatile<u32>,
+    _reserved0: Reserved<u32>,
+    /// Core timer prescaler.
+    CORE_TIMER_PRESCALER: Volatile<u32>,
+    /// Selects the core that GPU IRQs and FIQs are routed to.
+    GPU_INTERRUPT_ROUTING: Volatile<u32>,
+    _reserved1: [Reserved<u32>; 12],
+    /// Per-core generic timer interrupt control. Bits 0..3 route CNTPS,
+    /// CNTPNS, CNTHP and CNTV to the core's IRQ. Bits 4..7 route them to FIQ.
+    CORE_TIMER_INTERRUPT_CONTROL: [Volatile<u32>; CORES],
+    /// Per-core mailbox interrupt control. Bits 0..3 enable the IRQ for each
+    /// mailbox. Bits 4..7 enable the FIQ.
+    CORE_MAILBOX_INTERRUPT_CONTROL: [Volatile<u32>; CORES],
+    /// Per-core IRQ source. A bit is set for each `LocalInterrupt` pending.
+    CORE_IRQ_SOURCE: [ReadVolatile<u32>; CORES],
+    /// Per-core FIQ source.
+    CORE_FIQ_SOURCE: [ReadVolatile<u32>; CORES],
+    /// Per-core mailbox write-set. Writing a 1 sets the bit in the mailbox.
+    CORE_MAILBOX_SET: [[WriteVolatile<u32>; MAILBOXES]; CORES],
+    /// Per-core mailbox read and write-clear. Writing a 1 clears the bit.
+    CORE_MAILBOX_CLEAR: [[Volatile<u32>; MAILBOXES]; CORES],
+}
+
+/// The local interrupt controller.
+pub struct LocalController {
+    registers: &'static mut Registers,
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl LocalController {
+    /// Returns a new handle to the local interrupt controller.
+    pub fn new() -> LocalController {
+        LocalController {
+            registers: unsafe { &mut *(LOCAL_BASE as *mut Registers) },
+        }
+    }
+
+    /// Routes `int` to the IRQ of `core`. Only the generic timers and the
+    /// mailboxes can be routed this way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `int` is not a timer or mailbox interrupt.
+    pub fn enable(&mut self, core: usize, int: LocalInterrupt) {
+        match int as u32 {
+            bit @ 0..=3 => self.registers.CORE_TIMER_INTERRUPT_CONTROL[core].or_mask(1 << bit),
+            bit @ 4..=7 => {
+                self.registers.CORE_MAILBOX_INTERRUPT_CONTROL[core].or_mask(1 << (bit - 4))
+            }
+            _ => panic!("{int:?} cannot be routed to a core"),
+        }
+    }
+
+    /// Stops routing `int` to the IRQ of `core`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `int` is not a timer or mailbox interrupt.
+    #[allow(dead_code)] // not used yet.
+    pub fn disable(&mut self, core: usize, int: LocalInterrupt) {
+        match int as u32 {
+            bit @ 0..=3 => self.registers.CORE_TIMER_INTERRUPT_CONTROL[core].and_mask(!(1 << bit)),
+            bit @ 4..=7 => {
+                self.registers.CORE_MAILBOX_INTERRUPT_CONTROL[core].and_mask(!(1 << (bit - 4)))
+            }
+            _ => panic!("{int:?} cannot be routed to a core"),
+        }
+    }
+
+    /// Returns `true` if `int` is pending on the IRQ of `core`.
+    pub fn is_pending(&self, core: usize, int: LocalInterrupt) -> bool {
+        self.registers.CORE_IRQ_SOURCE[core].has_mask(1 << (int as u32))
+    }
+
+    /// Sets the bits of `value` in mailbox `mailbox` of `core`, raising its
+    /// interrupt if enabled.
+    #[allow(dead_code)] // not used yet.
+    pub fn send(&mut self, core: usize, mailbox: usize, value: u32) {
+        self.registers.CORE_MAILBOX_SET[core][mailbox].write(value);
+    }
+
+    /// Returns the value of mailbox `mailbox` of `core`.
+    #[allow(dead_code)] // not used yet.
+    pub fn read(&self, core: usize, mailbox: usize) -> u32 {
+        self.registers.CORE_MAILBOX_CLEAR[core][mailbox].read()
+    }
+
+    /// Clears the bits of `value` in mailbox `mailbox` of `core`.
+    #[allow(dead_code)] // not used yet.
+    pub fn clear(&mut self, core: usize, mailbox: usize, value: u32) {
+        self.registers.CORE_MAILBOX_CLEAR[core][mailbox].write(value);
+    }
+}
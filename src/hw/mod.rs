@@ -1,7 +1,9 @@
 //! Hardware (hw) module.
 //!
 //! This contains code related to interfacing with the raspberry pi 3 hardware.
+pub(crate) mod generic_timer;
 pub(crate) mod interrupt;
+pub(crate) mod local_interrupt;
 pub(crate) mod timer;
 pub(crate) mod uart;
 
@@ -1,9 +1,16 @@
 use crate::volatile::prelude::*;
 use crate::volatile::{ReadVolatile, Volatile};
 
+use super::interrupt::Interrupt;
+
 /// The base address for the ARM system timer registers.
 const TIMER_REG_BASE: usize = super::IO_BASE + 0x3000;
 
+/// The shortest delay, in microseconds, that a compare channel is armed with.
+/// Shorter delays risk writing a compare value the counter has already passed,
+/// which would not match again until the lower 32 bits wrap around.
+const MIN_DELAY: u64 = 10;
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -23,6 +30,25 @@ struct Registers {
     COMPARE: [Volatile<u32>; 4],
 }
 
+/// A system timer compare channel that is free for the ARM to use.
+/// Channels 0 and 2 are used by the GPU.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code)] // not all variants are used yet.
+pub enum Channel {
+    One = 1,
+    Three = 3,
+}
+
+impl Channel {
+    /// Returns the interrupt raised when this channel matches.
+    pub fn interrupt(self) -> Interrupt {
+        match self {
+            Channel::One => Interrupt::Timer1,
+            Channel::Three => Interrupt::Timer3,
+        }
+    }
+}
+
 /// The Raspberry Pi ARM system timer.
 pub struct Timer {
     registers: &'static mut Registers,
@@ -39,20 +65,60 @@ impl Timer {
 
     /// Reads the system timer's counter and returns the 64-bit counter value.
     /// The returned value is the number of elapsed microseconds.
+    ///
+    /// The two halves are read separately, so the upper half is read again to
+    /// detect the lower half wrapping around in between.
     pub fn read(&self) -> u64 {
-        let mut counter: u64 = self.registers.CHI.read() as u64;
-        counter <<= 32;
-        counter += self.registers.CLO.read() as u64;
-        counter
+        loop {
+            let high = self.registers.CHI.read();
+            let low = self.registers.CLO.read();
+            if self.registers.CHI.read() == high {
+                return ((high as u64) << 32) | low as u64;
+            }
+        }
     }
 
-    /// Sets up a match in timer 1 to occur `us` microseconds from now. If
-    /// interrupts for timer 1 are enabled and IRQs are unmasked, then a timer
-    /// interrupt will be issued in `us` microseconds.
-    pub fn tick_in(&mut self, us: u32) {
-        self.registers.CS.or_mask(0b10);
+    /// Clears any pending match on `channel` and sets up a new match to occur
+    /// `us` microseconds from now. If interrupts for the channel are enabled
+    /// and IRQs are unmasked, then a timer interrupt will be issued in `us`
+    /// microseconds.
+    pub fn arm(&mut self, channel: Channel, us: u32) {
+        self.clear(channel);
         let current_lower = self.registers.CLO.read();
-        self.registers.COMPARE[1].write(current_lower + us);
+        self.registers.COMPARE[channel as usize].write(current_lower.wrapping_add(us));
+    }
+
+    /// Sets up a match on `channel` at the 64-bit time `deadline`.
+    ///
+    /// Compare registers only hold 32 bits, so a deadline more than
+    /// `u32::MAX` microseconds away is approached in steps: the channel
+    /// matches early and the caller is expected to arm it again. Deadlines in
+    /// the past match almost immediately.
+    pub fn arm_at(&mut self, channel: Channel, deadline: u64) {
+        let delay = deadline
+            .saturating_sub(self.read())
+            .clamp(MIN_DELAY, u32::MAX as u64);
+        self.arm(channel, delay as u32);
+    }
+
+    /// Clears a match on `channel` and its interrupt request line.
+    pub fn clear(&mut self, channel: Channel) {
+        self.registers.CS.write(1 << (channel as u32));
+    }
+}
+
+/// A compare channel drives the kernel's software timers.
+impl crate::timer::Clock for Channel {
+    fn now(&self) -> u64 {
+        current_time()
+    }
+
+    fn arm(&mut self, deadline: u64) {
+        Timer::new().arm_at(*self, deadline);
+    }
+
+    fn acknowledge(&mut self) {
+        Timer::new().clear(*self);
     }
 }
 
@@ -63,7 +129,7 @@ pub fn current_time() -> u64 {
 }
 
 /// Spins until `us` microseconds have passed.
-#[cfg_attr(test, allow(dead_code))]
+#[allow(dead_code)] // not used yet.
 pub fn spin_sleep_us(us: u64) {
     let start = current_time();
     loop {
@@ -80,7 +146,7 @@ pub fn spin_sleep_us(us: u64) {
 }
 
 /// Spins until `ms` milliseconds have passed.
-#[cfg_attr(test, allow(dead_code))]
+#[allow(dead_code)] // not used yet.
 pub fn spin_sleep_ms(ms: u64) {
     spin_sleep_us(ms * 1000);
 }
@@ -1,5 +1,7 @@
+#[cfg_attr(test, allow(dead_code))]
 pub(crate) struct Uart0;
 
+#[cfg_attr(test, allow(dead_code))]
 pub(crate) static mut UART0: Uart0 = Uart0;
 
 impl core::fmt::Write for Uart0 {
@@ -17,6 +19,7 @@ pub extern "C" fn uart0_write_char(c: u8) {
     }
 }
 
+#[cfg_attr(test, allow(dead_code))]
 pub(crate) fn uart0_write_str(str: &str) {
     for c in str.bytes() {
         uart0_write_char(c)
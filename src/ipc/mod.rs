@@ -0,0 +1,85 @@
+//! Message-passing IPC between processes.
+//!
+//! A server creates a named port with `Registry::create()`, which returns the
+//! port's receive handle, and clients look the port up by name with
+//! `Registry::open()`, which returns a send handle. Handles are `File`s: they
+//! live in a process's file table, are shared by `fork` and `dup2`, and can be
+//! passed along in messages. A port closes when its receive handle does.
+//!
+//! Messages are `MESSAGE_SIZE` bytes and may carry a file, such as a port
+//! handle, that the receiver gets a file descriptor for. Communication is
+//! synchronous: a sender waits until its message is received, and a caller
+//! until the receiver replies. Each send or call is a `Transaction` that the
+//! waiting side polls.
+//!
+//! Like files, ports never block. An operation that cannot proceed returns
+//! `Error::WouldBlock` with a `Waiter`, and the system call sleeps on it.
+
+mod port;
+mod registry;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::sync::Arc;
+
+use crate::file::File;
+use crate::process::Id;
+use crate::sync::Waiter;
+
+pub use self::port::{PortHandle, Transaction};
+#[cfg_attr(test, allow(unused_imports))]
+pub use self::registry::{Registry, PORTS};
+
+/// The size of a message in bytes.
+pub const MESSAGE_SIZE: usize = 64;
+
+/// The fixed-size payload of a message. The layout is part of the system call
+/// interface.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message(pub [u8; MESSAGE_SIZE]);
+
+impl Default for Message {
+    fn default() -> Self {
+        Message([0; MESSAGE_SIZE])
+    }
+}
+
+/// A message and the file it carries, if any.
+#[derive(Debug, Clone, Default)]
+pub struct Parcel {
+    pub message: Message,
+    pub handle: Option<Arc<dyn File>>,
+}
+
+/// Identifies a received call to its receiver, which replies with it.
+pub type Token = u64;
+
+/// A message taken from a port.
+#[derive(Debug)]
+pub struct Received {
+    pub parcel: Parcel,
+    /// The process that sent the message.
+    pub sender: Id,
+    /// The call's token if the sender waits for a reply, `None` for a message
+    /// that was only sent.
+    pub token: Option<Token>,
+}
+
+/// Why an IPC operation did not complete.
+#[derive(Debug)]
+pub enum Error {
+    /// The operation cannot proceed yet. Try again once the waiter is woken.
+    WouldBlock(Waiter),
+    /// The port was closed: its receive handle is gone.
+    Closed,
+    /// A port with that name already exists.
+    NameTaken,
+    /// There is no port with that name.
+    NoSuchPort,
+    /// Only the port's receive handle may receive and reply.
+    NotReceiver,
+    /// The token names no call waiting for a reply.
+    NoSuchCall,
+}
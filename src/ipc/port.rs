@@ -0,0 +1,266 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use super::{Error, Parcel, Received, Registry, Token};
+use crate::file::File;
+use crate::mutex::Mutex;
+use crate::process::Id;
+use crate::sync::WaitQueue;
+
+/// How far a transaction got.
+#[derive(Debug)]
+enum Progress {
+    /// The message waits in the port's queue.
+    Queued,
+    /// The receiver took the message of a call and has not replied yet.
+    Received,
+    /// The receiver took the message of a send, or replied to a call with
+    /// the parcel, until the sender takes it.
+    Done(Option<Parcel>),
+    /// The port closed before the transaction completed.
+    Failed,
+    /// The sender stopped waiting.
+    Cancelled,
+}
+
+/// A message sent to a port, as seen by its sender.
+#[derive(Debug)]
+pub struct Transaction {
+    progress: Mutex<Progress>,
+    is_call: bool,
+    /// Woken when the transaction completes or fails.
+    done: WaitQueue,
+}
+
+impl Transaction {
+    fn new(is_call: bool) -> Arc<Transaction> {
+        Arc::new(Transaction {
+            progress: Mutex::new(Progress::Queued),
+            is_call,
+            done: WaitQueue::new(),
+        })
+    }
+
+    /// Returns the outcome of the transaction once it completed: the reply
+    /// to a call, or `None` once a sent message was received. Fails with
+    /// `Error::Closed` if the port closed first.
+    pub fn poll(&self) -> Result<Option<Parcel>, Error> {
+        let mut progress = self.progress.lock();
+        match &mut *progress {
+            Progress::Queued | Progress::Received => Err(Error::WouldBlock(self.done.register())),
+            Progress::Done(reply) => Ok(reply.take()),
+            Progress::Failed | Progress::Cancelled => Err(Error::Closed),
+        }
+    }
+
+    /// Stops waiting for the transaction. A message that was not received
+    /// yet is dropped, and a reply to a call is discarded. Returns `false` if
+    /// the transaction has already completed or failed, in which case
+    /// `poll()` still returns its outcome.
+    pub fn cancel(&self) -> bool {
+        let mut progress = self.progress.lock();
+        match *progress {
+            Progress::Queued | Progress::Received => {
+                *progress = Progress::Cancelled;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the transaction on to `next`, waking the sender if that ends
+    /// it. Returns `false` if the sender stopped waiting.
+    fn advance(&self, next: Progress) -> bool {
+        let mut progress = self.progress.lock();
+        if let Progress::Cancelled = *progress {
+            return false;
+        }
+
+        let finished = !matches!(next, Progress::Received);
+        *progress = next;
+        drop(progress);
+        if finished {
+            self.done.wake_all();
+        }
+        true
+    }
+}
+
+/// A message waiting in a port's queue.
+#[derive(Debug)]
+struct Envelope {
+    parcel: Parcel,
+    sender: Id,
+    transaction: Arc<Transaction>,
+}
+
+/// The messages of a port and the calls waiting for a reply.
+#[derive(Debug, Default)]
+struct Queue {
+    envelopes: VecDeque<Envelope>,
+    calls: BTreeMap<Token, Arc<Transaction>>,
+    /// The token of the most recently received call. Tokens start at `1`.
+    last_token: Token,
+    closed: bool,
+}
+
+/// A named port that processes send messages to.
+#[derive(Debug)]
+pub(super) struct Port {
+    name: String,
+    registry: &'static Registry,
+    queue: Mutex<Queue>,
+    /// Woken when a message is queued.
+    incoming: WaitQueue,
+}
+
+impl Port {
+    /// Returns a new port called `name`, registered in `registry`.
+    pub(super) fn new(name: String, registry: &'static Registry) -> Port {
+        Port {
+            name,
+            registry,
+            queue: Mutex::new(Queue::default()),
+            incoming: WaitQueue::new(),
+        }
+    }
+
+    /// Queues `parcel` from process `sender` and returns the transaction
+    /// tracking it.
+    fn post(&self, parcel: Parcel, sender: Id, is_call: bool) -> Result<Arc<Transaction>, Error> {
+        let transaction = Transaction::new(is_call);
+        let mut queue = self.queue.lock();
+        if queue.closed {
+            return Err(Error::Closed);
+        }
+
+        queue.envelopes.push_back(Envelope {
+            parcel,
+            sender,
+            transaction: transaction.clone(),
+        });
+        drop(queue);
+        self.incoming.wake_all();
+        Ok(transaction)
+    }
+
+    /// Takes the oldest message whose sender still waits.
+    fn receive(&self) -> Result<Received, Error> {
+        loop {
+            let mut queue = self.queue.lock();
+            let Some(envelope) = queue.envelopes.pop_front() else {
+                return Err(Error::WouldBlock(self.incoming.register()));
+            };
+            let token = envelope.transaction.is_call.then(|| {
+                queue.last_token += 1;
+                let token = queue.last_token;
+                queue.calls.insert(token, envelope.transaction.clone());
+                token
+            });
+            drop(queue);
+
+            let next = match token {
+                Some(_) => Progress::Received,
+                None => Progress::Done(None),
+            };
+            if envelope.transaction.advance(next) {
+                return Ok(Received {
+                    parcel: envelope.parcel,
+                    sender: envelope.sender,
+                    token,
+                });
+            }
+            if let Some(token) = token {
+                self.queue.lock().calls.remove(&token);
+            }
+        }
+    }
+
+    /// Answers the call `token` with `parcel`. A reply to a caller that
+    /// stopped waiting is dropped.
+    fn reply(&self, token: Token, parcel: Parcel) -> Result<(), Error> {
+        let transaction = self.queue.lock().calls.remove(&token);
+        let transaction = transaction.ok_or(Error::NoSuchCall)?;
+        transaction.advance(Progress::Done(Some(parcel)));
+        Ok(())
+    }
+
+    /// Closes the port: its name is forgotten, and queued messages and calls
+    /// waiting for a reply fail. Their files are dropped outside the lock,
+    /// since one may be the receive handle of another port.
+    fn close(&self) {
+        self.registry.remove(&self.name);
+        let (envelopes, calls) = {
+            let mut queue = self.queue.lock();
+            queue.closed = true;
+            (
+                core::mem::take(&mut queue.envelopes),
+                core::mem::take(&mut queue.calls),
+            )
+        };
+
+        for envelope in envelopes {
+            envelope.transaction.advance(Progress::Failed);
+        }
+        for transaction in calls.values() {
+            transaction.advance(Progress::Failed);
+        }
+    }
+}
+
+/// A handle to a port: its receive handle, which can also send, or a send
+/// handle.
+#[derive(Debug)]
+pub struct PortHandle {
+    port: Arc<Port>,
+    is_receiver: bool,
+}
+
+impl PortHandle {
+    pub(super) fn new(port: Arc<Port>, is_receiver: bool) -> PortHandle {
+        PortHandle { port, is_receiver }
+    }
+
+    /// Sends `parcel` from process `sender`. The returned transaction
+    /// completes once the message is received.
+    pub fn send(&self, parcel: Parcel, sender: Id) -> Result<Arc<Transaction>, Error> {
+        self.port.post(parcel, sender, false)
+    }
+
+    /// Sends `parcel` from process `sender` as a call. The returned
+    /// transaction completes with the reply.
+    pub fn call(&self, parcel: Parcel, sender: Id) -> Result<Arc<Transaction>, Error> {
+        self.port.post(parcel, sender, true)
+    }
+
+    /// Takes the oldest message sent to the port.
+    pub fn receive(&self) -> Result<Received, Error> {
+        if !self.is_receiver {
+            return Err(Error::NotReceiver);
+        }
+        self.port.receive()
+    }
+
+    /// Answers the received call `token` with `parcel`.
+    pub fn reply(&self, token: Token, parcel: Parcel) -> Result<(), Error> {
+        if !self.is_receiver {
+            return Err(Error::NotReceiver);
+        }
+        self.port.reply(token, parcel)
+    }
+}
+
+impl File for PortHandle {
+    fn as_port(&self) -> Option<&PortHandle> {
+        Some(self)
+    }
+}
+
+impl Drop for PortHandle {
+    fn drop(&mut self) {
+        if self.is_receiver {
+            self.port.close();
+        }
+    }
+}
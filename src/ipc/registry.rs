@@ -0,0 +1,51 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use super::port::{Port, PortHandle};
+use super::Error;
+use crate::mutex::Mutex;
+
+/// The named ports of the system.
+#[cfg_attr(test, allow(dead_code))]
+pub static PORTS: Registry = Registry::new();
+
+/// A set of ports, keyed by name.
+#[derive(Debug)]
+pub struct Registry {
+    ports: Mutex<BTreeMap<String, Arc<Port>>>,
+}
+
+impl Registry {
+    /// Returns an empty registry.
+    pub const fn new() -> Registry {
+        Registry {
+            ports: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Creates a port called `name` and returns its receive handle. The name
+    /// is taken until the handle is dropped.
+    pub fn create(&'static self, name: &str) -> Result<PortHandle, Error> {
+        let mut ports = self.ports.lock();
+        if ports.contains_key(name) {
+            return Err(Error::NameTaken);
+        }
+
+        let port = Arc::new(Port::new(name.into(), self));
+        ports.insert(name.into(), port.clone());
+        Ok(PortHandle::new(port, true))
+    }
+
+    /// Returns a send handle to the port called `name`.
+    pub fn open(&self, name: &str) -> Result<PortHandle, Error> {
+        let port = self.ports.lock().get(name).cloned();
+        port.map(|port| PortHandle::new(port, false))
+            .ok_or(Error::NoSuchPort)
+    }
+
+    /// Forgets the port called `name`, whose receive handle was dropped.
+    pub(super) fn remove(&self, name: &str) {
+        self.ports.lock().remove(name);
+    }
+}
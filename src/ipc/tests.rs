@@ -0,0 +1,203 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use std::thread;
+
+use super::{Error, Message, Parcel, PortHandle, Registry};
+use crate::file::{pipe, File};
+
+/// Returns a registry of its own for a test.
+fn registry() -> &'static Registry {
+    Box::leak(Box::new(Registry::new()))
+}
+
+/// Returns a parcel holding `word` in its first word.
+fn parcel(word: u64) -> Parcel {
+    let mut message = Message::default();
+    message.0[..8].copy_from_slice(&word.to_le_bytes());
+    Parcel {
+        message,
+        handle: None,
+    }
+}
+
+/// Returns the first word of `parcel`.
+fn word(parcel: &Parcel) -> u64 {
+    u64::from_le_bytes(parcel.message.0[..8].try_into().unwrap())
+}
+
+#[test]
+fn ports_are_named() {
+    let registry = registry();
+    let receiver = registry.create("echo").unwrap();
+    assert!(matches!(registry.create("echo"), Err(Error::NameTaken)));
+    assert!(matches!(registry.open("other"), Err(Error::NoSuchPort)));
+    let sender = registry.open("echo").unwrap();
+
+    drop(receiver);
+    assert!(matches!(registry.open("echo"), Err(Error::NoSuchPort)));
+    assert!(matches!(sender.send(parcel(1), 1), Err(Error::Closed)));
+    registry.create("echo").expect("the name is free again");
+}
+
+#[test]
+fn send_completes_when_received() {
+    let registry = registry();
+    let receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    let Err(Error::WouldBlock(incoming)) = receiver.receive() else {
+        panic!("received from an empty port");
+    };
+
+    let sent = sender.send(parcel(7), 3).unwrap();
+    assert!(incoming.is_woken());
+    let Err(Error::WouldBlock(delivered)) = sent.poll() else {
+        panic!("completed before it was received");
+    };
+
+    let received = receiver.receive().unwrap();
+    assert_eq!((word(&received.parcel), received.sender, received.token), (7, 3, None));
+    assert!(delivered.is_woken());
+    assert!(matches!(sent.poll(), Ok(None)));
+    assert!(!sent.cancel(), "already completed");
+}
+
+#[test]
+fn calls_complete_with_the_reply() {
+    let registry = registry();
+    let receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    let first = sender.call(parcel(1), 5).unwrap();
+    let second = sender.call(parcel(2), 6).unwrap();
+
+    let a = receiver.receive().unwrap();
+    let b = receiver.receive().unwrap();
+    assert_eq!((word(&a.parcel), word(&b.parcel)), (1, 2), "in order");
+    let (Some(a), Some(b)) = (a.token, b.token) else {
+        panic!("calls have tokens");
+    };
+    assert_ne!(a, b);
+    assert!(matches!(first.poll(), Err(Error::WouldBlock(_))), "received, not replied");
+
+    receiver.reply(b, parcel(20)).unwrap();
+    receiver.reply(a, parcel(10)).unwrap();
+    assert_eq!(word(&first.poll().unwrap().unwrap()), 10);
+    assert_eq!(word(&second.poll().unwrap().unwrap()), 20);
+    assert!(matches!(receiver.reply(a, parcel(0)), Err(Error::NoSuchCall)));
+}
+
+#[test]
+fn only_the_receiver_receives() {
+    let registry = registry();
+    let _receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    assert!(matches!(sender.receive(), Err(Error::NotReceiver)));
+    assert!(matches!(sender.reply(1, parcel(0)), Err(Error::NotReceiver)));
+}
+
+#[test]
+fn handles_travel_with_messages() {
+    let registry = registry();
+    let receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    let (reader, writer) = pipe();
+    let reply_port: Arc<dyn File> = Arc::new(registry.create("reply").unwrap());
+
+    let mut message = parcel(0);
+    message.handle = Some(writer);
+    let call = sender.call(message, 1).unwrap();
+    let received = receiver.receive().unwrap();
+    received.parcel.handle.unwrap().write(b"hi").unwrap();
+    let mut buf = [0; 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+
+    let mut reply = parcel(0);
+    reply.handle = Some(reply_port.clone());
+    receiver.reply(received.token.unwrap(), reply).unwrap();
+    let handle = call.poll().unwrap().unwrap().handle.unwrap();
+    assert!(handle.as_port().is_some());
+    assert!(Arc::ptr_eq(&handle, &reply_port));
+}
+
+#[test]
+fn closing_fails_waiting_senders() {
+    let registry = registry();
+    let receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    let queued = sender.send(parcel(1), 1).unwrap();
+    let call = sender.call(parcel(2), 1).unwrap();
+    let unreplied = sender.call(parcel(3), 1).unwrap();
+    receiver.receive().unwrap();
+    receiver.receive().unwrap();
+    let Err(Error::WouldBlock(waiter)) = unreplied.poll() else {
+        panic!("completed before it was received");
+    };
+
+    drop(receiver);
+    assert!(waiter.is_woken());
+    assert!(matches!(queued.poll(), Ok(None)), "the send was received");
+    assert!(matches!(call.poll(), Err(Error::Closed)));
+    assert!(matches!(unreplied.poll(), Err(Error::Closed)));
+}
+
+#[test]
+fn cancelled_messages_are_skipped() {
+    let registry = registry();
+    let receiver = registry.create("port").unwrap();
+    let sender = registry.open("port").unwrap();
+    let cancelled = sender.send(parcel(1), 1).unwrap();
+    let call = sender.call(parcel(2), 1).unwrap();
+    sender.send(parcel(3), 1).unwrap();
+    assert!(cancelled.cancel());
+
+    let received = receiver.receive().unwrap();
+    assert_eq!(word(&received.parcel), 2);
+    assert!(call.cancel(), "not replied yet");
+    receiver.reply(received.token.unwrap(), parcel(0)).unwrap();
+    assert!(matches!(call.poll(), Err(Error::Closed)), "the reply was dropped");
+    assert_eq!(word(&receiver.receive().unwrap().parcel), 3);
+}
+
+/// Receives calls on `receiver` and answers each with its word plus one,
+/// until the port has no senders.
+fn serve(receiver: PortHandle, calls: usize) {
+    for _ in 0..calls {
+        let received = loop {
+            match receiver.receive() {
+                Ok(received) => break received,
+                Err(Error::WouldBlock(waiter)) => {
+                    while !waiter.is_woken() {
+                        thread::yield_now();
+                    }
+                }
+                Err(error) => panic!("{error:?}"),
+            }
+        };
+        let reply = parcel(word(&received.parcel) + 1);
+        receiver.reply(received.token.unwrap(), reply).unwrap();
+    }
+}
+
+#[test]
+fn rpc_between_threads() {
+    let registry = registry();
+    let receiver = registry.create("adder").unwrap();
+    let server = thread::spawn(move || serve(receiver, 100));
+
+    let sender = registry.open("adder").unwrap();
+    for n in 0..100 {
+        let call = sender.call(parcel(n), 2).unwrap();
+        let reply = loop {
+            match call.poll() {
+                Ok(reply) => break reply.unwrap(),
+                Err(Error::WouldBlock(waiter)) => {
+                    while !waiter.is_woken() {
+                        thread::yield_now();
+                    }
+                }
+                Err(error) => panic!("{error:?}"),
+            }
+        };
+        assert_eq!(word(&reply), n + 1);
+    }
+    server.join().unwrap();
+}
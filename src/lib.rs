@@ -5,27 +5,40 @@
 #![cfg_attr(not(test), feature(lang_items))]
 // The lang_items feature creates a build warning for internal_features.
 #![cfg_attr(not(test), allow(internal_features))]
-// ptr_internals is needed for core::ptr::Unique.
-#![cfg_attr(not(test), feature(ptr_internals))]
 
 extern crate alloc;
 
 mod allocator;
 mod atags;
+mod cpu;
+mod elf;
+mod file;
 mod hw;
+mod ipc;
 mod lang_items;
+mod log;
 mod mutex;
-#[cfg(not(test))]
 mod process;
-#[cfg(not(test))]
+mod sync;
+mod timer;
 mod traps;
 mod vm;
 mod volatile;
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use atags::Atags;
-use hw::interrupt::{Controller as InterruptController, Interrupt};
+#[cfg(not(test))]
+use cpu::PerCore;
+#[cfg(not(test))]
+use hw::generic_timer::{GenericTimer, SystemTimeGenericTimer};
+use hw::interrupt::Controller as InterruptController;
+use hw::local_interrupt::{LocalController, LocalInterrupt};
+use hw::timer::Channel;
 #[cfg(not(test))]
 use process::GlobalScheduler;
+#[cfg(not(test))]
+use timer::GlobalTimers;
 
 #[allow(unused_macros)]
 #[macro_export]
@@ -49,8 +62,27 @@ macro_rules! kprint {
     };
 }
 
-/// The `tick` time.
-const TICK: u32 = 2 * 1_000 * 1_000;
+/// The kernel command line argument that drives the kernel's timers from the
+/// BCM2835 system timer instead of the generic timer. The system timer is
+/// global, so only core 0 receives its interrupts; the other cores keep its
+/// time with `SystemTimeGenericTimer`.
+const SYSTEM_TIMER_ARG: &str = "clock=system";
+
+/// Set by core 0 if the kernel command line holds `SYSTEM_TIMER_ARG`.
+#[cfg_attr(test, allow(dead_code))]
+static USE_SYSTEM_TIMER: AtomicBool = AtomicBool::new(false);
+
+/// The kernel command line argument prefix that selects the scheduling
+/// policy, e.g. `sched=fair`. See `process::policy::from_name()`.
+const SCHED_ARG_PREFIX: &str = "sched=";
+
+/// The scheduling policy used when the kernel command line does not select
+/// one.
+const DEFAULT_SCHED_POLICY: &str = "rr";
+
+/// The scheduler's base time slice in microseconds.
+#[cfg_attr(test, allow(dead_code))]
+const TICK: u64 = 2 * 1_000 * 1_000;
 
 #[cfg(not(test))]
 #[global_allocator]
@@ -59,31 +91,85 @@ static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
 #[cfg(not(test))]
 static SCHEDULER: GlobalScheduler = GlobalScheduler::uninitialized();
 
+/// The kernel's software timers. Each core has its own, driven by its own
+/// generic timer, or by a system timer channel on core 0 with
+/// `SYSTEM_TIMER_ARG`. Every core's timers keep the same time.
+#[cfg(not(test))]
+static TIMERS: PerCore<GlobalTimers> =
+    PerCore::new([const { GlobalTimers::uninitialized() }; cpu::CORES]);
+
 #[no_mangle]
 pub extern "C" fn kmain() {
-    kprintln!("kmain enter");
+    info!("kmain enter");
     #[cfg(not(test))]
     ALLOCATOR.initialize();
 
+    let mut use_system_timer = false;
+    let mut sched_policy = None;
     for atag in Atags::get() {
         if let Some(cmdline) = atag.cmd() {
-            kprintln!("Atags cmdline: {cmdline}");
+            log::configure(cmdline);
+            use_system_timer = cmdline.split_whitespace().any(|arg| arg == SYSTEM_TIMER_ARG);
+            sched_policy = cmdline
+                .split_whitespace()
+                .filter_map(|arg| arg.strip_prefix(SCHED_ARG_PREFIX))
+                .next_back();
+            info!("Atags cmdline: {cmdline}");
         }
 
         if let Some(mem) = atag.mem() {
-            kprintln!("Atags mem start: {}, size: {}", mem.start, mem.size);
+            info!("Atags mem start: {}, size: {}", mem.start, mem.size);
         }
     }
 
-    let mut interrupt_controller = InterruptController::new();
-    interrupt_controller.enable(Interrupt::Timer1);
+    #[cfg_attr(test, allow(unused_variables))]
+    let name = sched_policy.unwrap_or(DEFAULT_SCHED_POLICY);
+
+    USE_SYSTEM_TIMER.store(use_system_timer, Ordering::Relaxed);
+    if use_system_timer {
+        InterruptController::new().enable(Channel::One.interrupt());
+        #[cfg(not(test))]
+        TIMERS.get().initialize(Channel::One);
+    } else {
+        LocalController::new().enable(0, LocalInterrupt::CntPns);
+        #[cfg(not(test))]
+        TIMERS.get().initialize(GenericTimer);
+    }
+
+    #[cfg(not(test))]
+    {
+        let policy = process::policy::from_name(name, TICK).unwrap_or_else(|| {
+            warn!("unknown scheduling policy {name}, using {DEFAULT_SCHED_POLICY}");
+            process::policy::from_name(DEFAULT_SCHED_POLICY, TICK).expect("default policy")
+        });
+        info!("scheduling policy: {policy:?}");
+
+        cpu::start_secondary_cores();
+        SCHEDULER.start(policy);
+    }
+
+    info!("kmain exit");
+}
 
-    let mut timer = crate::hw::timer::Timer::new();
+/// The Rust entry point of the secondary cores, called from `kernel.S` once
+/// the core is in EL1 with its own stack. Core 0 has already initialized the
+/// allocator when the secondary cores are released.
+#[no_mangle]
+pub extern "C" fn kmain_secondary() {
+    let core = cpu::cpu_id();
+    info!("kmain_secondary enter core {core}");
 
-    timer.tick_in(TICK);
+    LocalController::new().enable(core, LocalInterrupt::CntPns);
 
     #[cfg(not(test))]
-    SCHEDULER.start();
+    {
+        if USE_SYSTEM_TIMER.load(Ordering::Relaxed) {
+            TIMERS.get().initialize(SystemTimeGenericTimer);
+        } else {
+            TIMERS.get().initialize(GenericTimer);
+        }
+        SCHEDULER.start_secondary();
+    }
 
-    kprintln!("kmain exit");
+    info!("kmain_secondary exit core {core}");
 }
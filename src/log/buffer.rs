@@ -0,0 +1,117 @@
+use core::fmt;
+
+/// A fixed-size ring buffer of newline-terminated log records.
+///
+/// When a new record does not fit, the oldest whole records are evicted to
+/// make room, so the buffer always starts at the beginning of a record.
+pub struct RingBuffer<const N: usize> {
+    bytes: [u8; N],
+    /// The index of the oldest byte.
+    start: usize,
+    /// The number of bytes in use.
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Returns a new, empty ring buffer.
+    pub const fn new() -> RingBuffer<N> {
+        RingBuffer {
+            bytes: [0; N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes in the buffer.
+    #[allow(dead_code)] // only used by tests.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the byte `offset` bytes after the oldest byte.
+    fn get(&self, offset: usize) -> u8 {
+        self.bytes[(self.start + offset) % N]
+    }
+
+    /// Evicts the oldest record.
+    fn evict(&mut self) {
+        let record_len = (0..self.len)
+            .position(|offset| self.get(offset) == b'\n')
+            .map_or(self.len, |newline| newline + 1);
+        self.start = (self.start + record_len) % N;
+        self.len -= record_len;
+    }
+
+    /// Appends `record` to the buffer, evicting old records as needed. If
+    /// `record` is larger than the buffer, only its tail is kept.
+    pub fn push(&mut self, record: &[u8]) {
+        let record = &record[record.len().saturating_sub(N)..];
+        while N - self.len < record.len() {
+            self.evict();
+        }
+
+        for &byte in record {
+            self.bytes[(self.start + self.len) % N] = byte;
+            self.len += 1;
+        }
+    }
+
+    /// Copies the most recent whole records that fit into `buf`, oldest first,
+    /// and returns the number of bytes copied.
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        let mut skip = self.len.saturating_sub(buf.len());
+        if skip > 0 {
+            // Skip forward to the start of the next record.
+            while skip < self.len && self.get(skip - 1) != b'\n' {
+                skip += 1;
+            }
+        }
+
+        let count = self.len - skip;
+        for (i, byte) in buf.iter_mut().take(count).enumerate() {
+            *byte = self.get(skip + i);
+        }
+        count
+    }
+}
+
+/// A fixed-size buffer that a single record is formatted into. Output that
+/// does not fit is silently truncated.
+pub struct LineBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> LineBuffer<N> {
+    /// Returns a new, empty line buffer.
+    pub const fn new() -> LineBuffer<N> {
+        LineBuffer {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Ensures the line ends with a newline, overwriting the last byte if the
+    /// buffer is full.
+    pub fn terminate(&mut self) {
+        if self.len == N {
+            self.len -= 1;
+        }
+        self.bytes[self.len] = b'\n';
+        self.len += 1;
+    }
+
+    /// Returns the formatted bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl<const N: usize> fmt::Write for LineBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = s.len().min(N - self.len);
+        self.bytes[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
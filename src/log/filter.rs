@@ -0,0 +1,141 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// The importance of a log record. Lower levels are more important.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Returns the level named `name`, ignoring case, if there is one.
+    pub fn from_name(name: &str) -> Option<Level> {
+        [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ]
+        .into_iter()
+        .find(|level| level.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the upper case name of the level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+/// The most verbose level a filter lets through. `None` means "off".
+type MaxLevel = Option<Level>;
+
+/// Parses `off` or a level name into a `MaxLevel`.
+fn parse_max_level(name: &str) -> Option<MaxLevel> {
+    if name.eq_ignore_ascii_case("off") {
+        Some(None)
+    } else {
+        Level::from_name(name).map(Some)
+    }
+}
+
+/// Strips the leading crate name from a module path, so that
+/// `tavern::process::scheduler` becomes `process::scheduler`.
+pub fn strip_crate(module: &str) -> &str {
+    match module.split_once("::") {
+        Some((_, path)) => path,
+        None => "",
+    }
+}
+
+/// Returns `true` if `module` is `prefix` or one of its submodules.
+fn is_within(module: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || module
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// A module specific maximum level.
+#[derive(Debug)]
+struct Directive {
+    module: String,
+    max_level: MaxLevel,
+}
+
+/// Decides which records are logged based on their level and module.
+#[derive(Debug)]
+pub struct Filter {
+    default: MaxLevel,
+    directives: Vec<Directive>,
+}
+
+impl Filter {
+    /// The level used when no directive matches.
+    const DEFAULT: MaxLevel = Some(Level::Info);
+
+    /// Returns a filter that logs records at `Level::Info` and above.
+    pub const fn new() -> Filter {
+        Filter {
+            default: Self::DEFAULT,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Parses a comma separated list of directives such as
+    /// `warn,process=debug,traps::syscall=trace`. Malformed directives are
+    /// ignored.
+    pub fn parse(spec: &str) -> Filter {
+        let mut filter = Filter::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                None => {
+                    if let Some(max_level) = parse_max_level(directive) {
+                        filter.default = max_level;
+                    }
+                }
+                Some((module, name)) => {
+                    if let Some(max_level) = parse_max_level(name.trim()) {
+                        filter.directives.push(Directive {
+                            module: module.trim().to_string(),
+                            max_level,
+                        });
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    /// Returns `true` if a record at `level` from the module path `module`
+    /// should be logged.
+    pub fn enabled(&self, level: Level, module: &str) -> bool {
+        let module = strip_crate(module);
+        let max_level = self
+            .directives
+            .iter()
+            .filter(|directive| is_within(module, &directive.module))
+            .max_by_key(|directive| directive.module.len())
+            .map_or(self.default, |directive| directive.max_level);
+
+        max_level.is_some_and(|max_level| level <= max_level)
+    }
+
+    /// Returns the most verbose level the filter lets through for any module.
+    pub fn max_level(&self) -> MaxLevel {
+        self.directives
+            .iter()
+            .map(|directive| directive.max_level)
+            .fold(self.default, Ord::max)
+    }
+}
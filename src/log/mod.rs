@@ -0,0 +1,195 @@
+//! Kernel logging.
+//!
+//! Records are written with the `error!`, `warn!`, `info!`, `debug!` and
+//! `trace!` macros. Each record is stamped with the time of the kernel's
+//! timers, filtered by its level and the module that emitted it, written to
+//! UART0 and appended to an in-memory ring buffer. The ring buffer is read back with `read()`, which
+//! backs the `dmesg` system call.
+//!
+//! Filters are configured from the kernel command line with a `log=` argument
+//! holding a comma separated list of directives. A directive is either a bare
+//! level, which sets the default, or `module=level`. For example,
+//! `log=info,process::scheduler=trace,traps=off`. The most specific module
+//! wins.
+//!
+//! Records are logged from exception handlers, so the logger is locked with
+//! interrupts masked. That only covers formatting a record into the ring
+//! buffer; it is written to UART0 after the lock is released. Records more
+//! verbose than every directive are dropped before it is locked at all.
+
+mod buffer;
+mod filter;
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+pub(crate) use self::buffer::{LineBuffer, RingBuffer};
+pub(crate) use self::filter::{Filter, Level};
+use crate::mutex::Mutex;
+
+/// The size, in bytes, of the kernel log ring buffer.
+pub(crate) const BUFFER_SIZE: usize = 16 * 1024;
+
+/// The maximum length, in bytes, of a single formatted record. Longer records
+/// are truncated.
+const LINE_SIZE: usize = 256;
+
+/// The kernel command line argument that holds the filter directives.
+const CMDLINE_KEY: &str = "log=";
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger::new());
+
+/// The most verbose level the filter lets through for any module, as a `u8`,
+/// or `0` if it lets nothing through. See `Filter::max_level()`.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// The global logger state: the active filter and the record history.
+struct Logger {
+    filter: Filter,
+    buffer: RingBuffer<BUFFER_SIZE>,
+}
+
+impl Logger {
+    const fn new() -> Logger {
+        Logger {
+            filter: Filter::new(),
+            buffer: RingBuffer::new(),
+        }
+    }
+}
+
+/// Configures the log filter from the kernel command line `cmdline`.
+///
+/// Only the last `log=` argument is used. If there is none, the filter is left
+/// unchanged.
+pub fn configure(cmdline: &str) {
+    let spec = cmdline
+        .split_whitespace()
+        .filter_map(|arg| arg.strip_prefix(CMDLINE_KEY))
+        .next_back();
+
+    if let Some(spec) = spec {
+        let filter = Filter::parse(spec);
+        let mut logger = LOGGER.lock_irqsave();
+        MAX_LEVEL.store(filter.max_level().map_or(0, |level| level as u8), Ordering::Relaxed);
+        logger.filter = filter;
+    }
+}
+
+/// Logs a record at `level` from `module`. This is the implementation behind
+/// the logging macros; use those instead of calling this directly.
+#[doc(hidden)]
+pub fn log(level: Level, module: &'static str, args: fmt::Arguments) {
+    if level as u8 > MAX_LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // Read before the logger is locked, so that it never waits for the timers.
+    let timestamp = timestamp();
+    let mut line = LineBuffer::<LINE_SIZE>::new();
+    {
+        let mut logger = LOGGER.lock_irqsave();
+        if !logger.filter.enabled(level, module) {
+            return;
+        }
+
+        let _ = format(&mut line, timestamp, level, module, args);
+        line.terminate();
+        logger.buffer.push(line.as_bytes());
+    }
+
+    // The UART is slow: write to it with interrupts unmasked.
+    for &byte in line.as_bytes() {
+        crate::hw::uart::uart0_write_char(byte);
+    }
+}
+
+/// Returns the time records are stamped with: the time of this core's
+/// `TIMERS`, which every core agrees on, or `0` before they are initialized.
+#[cfg(not(test))]
+fn timestamp() -> u64 {
+    crate::TIMERS.get().try_now().unwrap_or(0)
+}
+
+/// Host builds have no timers.
+#[cfg(test)]
+fn timestamp() -> u64 {
+    0
+}
+
+/// Formats a record as `[seconds.micros] LEVEL module: message`, where
+/// `timestamp` is in microseconds and `module` has its crate name stripped.
+fn format(
+    w: &mut impl Write,
+    timestamp: u64,
+    level: Level,
+    module: &str,
+    args: fmt::Arguments,
+) -> fmt::Result {
+    let seconds = timestamp / 1_000_000;
+    let micros = timestamp % 1_000_000;
+    let module = filter::strip_crate(module);
+    write!(w, "[{seconds:5}.{micros:06}] {:<5} ", level.as_str())?;
+    if !module.is_empty() {
+        write!(w, "{module}: ")?;
+    }
+    w.write_fmt(args)
+}
+
+/// Copies the most recent log records into `buf`, oldest first, and returns
+/// the number of bytes copied. Only whole records are copied.
+#[cfg_attr(test, allow(dead_code))]
+pub fn read(buf: &mut [u8]) -> usize {
+    LOGGER.lock_irqsave().buffer.read(buf)
+}
+
+/// Logs a record at the given `Level`.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log::log($level, core::module_path!(), core::format_args!($($arg)+))
+    };
+}
+
+/// Logs a record at `Level::Error`.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+/// Logs a record at `Level::Warn`.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs a record at `Level::Info`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+/// Logs a record at `Level::Debug`.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+/// Logs a record at `Level::Trace`.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
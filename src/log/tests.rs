@@ -0,0 +1,161 @@
+mod ring_buffer {
+    use crate::log::RingBuffer;
+
+    fn read_all<const N: usize>(buffer: &RingBuffer<N>) -> Vec<u8> {
+        let mut buf = vec![0; N];
+        let count = buffer.read(&mut buf);
+        buf.truncate(count);
+        buf
+    }
+
+    #[test]
+    fn push_and_read() {
+        let mut buffer = RingBuffer::<32>::new();
+        buffer.push(b"one\n");
+        buffer.push(b"two\n");
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(read_all(&buffer), b"one\ntwo\n");
+    }
+
+    #[test]
+    fn evicts_whole_records() {
+        let mut buffer = RingBuffer::<16>::new();
+        buffer.push(b"first\n");
+        buffer.push(b"second\n");
+        buffer.push(b"third\n");
+        assert_eq!(read_all(&buffer), b"second\nthird\n");
+
+        buffer.push(b"4th\n");
+        assert_eq!(read_all(&buffer), b"third\n4th\n");
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut buffer = RingBuffer::<10>::new();
+        for _ in 0..7 {
+            buffer.push(b"abc\n");
+        }
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(read_all(&buffer), b"abc\nabc\n");
+    }
+
+    #[test]
+    fn short_read_keeps_newest_whole_records() {
+        let mut buffer = RingBuffer::<32>::new();
+        buffer.push(b"aaaa\n");
+        buffer.push(b"bb\n");
+        buffer.push(b"c\n");
+
+        let mut buf = [0; 6];
+        let count = buffer.read(&mut buf);
+        assert_eq!(&buf[..count], b"bb\nc\n");
+
+        let mut buf = [0; 1];
+        assert_eq!(buffer.read(&mut buf), 0);
+    }
+
+    #[test]
+    fn oversized_record_keeps_tail() {
+        let mut buffer = RingBuffer::<4>::new();
+        buffer.push(b"abcdefg\n");
+        assert_eq!(read_all(&buffer), b"efg\n");
+    }
+}
+
+mod line_buffer {
+    use core::fmt::Write;
+
+    use crate::log::LineBuffer;
+
+    #[test]
+    fn terminates() {
+        let mut line = LineBuffer::<8>::new();
+        write!(line, "hi").unwrap();
+        line.terminate();
+        assert_eq!(line.as_bytes(), b"hi\n");
+    }
+
+    #[test]
+    fn truncates() {
+        let mut line = LineBuffer::<4>::new();
+        write!(line, "hello world").unwrap();
+        line.terminate();
+        assert_eq!(line.as_bytes(), b"hel\n");
+    }
+}
+
+mod filter {
+    use crate::log::{Filter, Level};
+
+    #[test]
+    fn level_names() {
+        assert_eq!(Level::from_name("warn"), Some(Level::Warn));
+        assert_eq!(Level::from_name("TRACE"), Some(Level::Trace));
+        assert_eq!(Level::from_name("verbose"), None);
+        assert!(Level::Error < Level::Trace);
+    }
+
+    #[test]
+    fn default_is_info() {
+        let filter = Filter::new();
+        assert!(filter.enabled(Level::Info, "tavern::traps"));
+        assert!(!filter.enabled(Level::Debug, "tavern::traps"));
+    }
+
+    #[test]
+    fn default_level() {
+        let filter = Filter::parse("warn");
+        assert!(filter.enabled(Level::Error, "tavern"));
+        assert!(filter.enabled(Level::Warn, "tavern::traps"));
+        assert!(!filter.enabled(Level::Info, "tavern::traps"));
+
+        let filter = Filter::parse("off");
+        assert!(!filter.enabled(Level::Error, "tavern"));
+    }
+
+    #[test]
+    fn module_directives() {
+        let filter = Filter::parse("info,process=debug,process::scheduler=trace,traps=off");
+        assert!(filter.enabled(Level::Debug, "tavern::process"));
+        assert!(filter.enabled(Level::Debug, "tavern::process::stack"));
+        assert!(!filter.enabled(Level::Trace, "tavern::process::stack"));
+        assert!(filter.enabled(Level::Trace, "tavern::process::scheduler"));
+        assert!(!filter.enabled(Level::Error, "tavern::traps::syscall"));
+        assert!(!filter.enabled(Level::Debug, "tavern::processes"));
+        assert!(filter.enabled(Level::Info, "tavern::hw"));
+    }
+
+    #[test]
+    fn max_level() {
+        assert_eq!(Filter::new().max_level(), Some(Level::Info));
+        assert_eq!(Filter::parse("warn,hw=trace").max_level(), Some(Level::Trace));
+        assert_eq!(Filter::parse("debug,traps=off").max_level(), Some(Level::Debug));
+        assert_eq!(Filter::parse("off").max_level(), None);
+    }
+
+    #[test]
+    fn ignores_malformed_directives() {
+        let filter = Filter::parse(",loud,hw=verbose, hw::timer = debug ,");
+        assert!(filter.enabled(Level::Info, "tavern::hw"));
+        assert!(!filter.enabled(Level::Debug, "tavern::hw"));
+        assert!(filter.enabled(Level::Debug, "tavern::hw::timer"));
+    }
+}
+
+mod format {
+    use crate::log::{format, Level, LineBuffer};
+
+    #[test]
+    fn record_layout() {
+        let mut line = LineBuffer::<64>::new();
+        format(
+            &mut line,
+            12_345_678,
+            Level::Warn,
+            "tavern::traps",
+            format_args!("x = {}", 7),
+        )
+        .unwrap();
+        assert_eq!(line.as_bytes(), b"[   12.345678] WARN  traps: x = 7");
+    }
+}
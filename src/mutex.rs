@@ -1,12 +1,44 @@
 use core::cell::UnsafeCell;
 use core::fmt;
 use core::ops::{Deref, DerefMut, Drop};
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use crate::cpu::{self, PerCore, CORES};
+
+/// The `owner` of a `Mutex` that is not locked.
+const NO_OWNER: usize = usize::MAX;
+
+/// The number of locks each core holds.
+static HELD: PerCore<AtomicUsize> = PerCore::new([const { AtomicUsize::new(0) }; CORES]);
+
+/// Returns `true` if this core holds a lock.
+#[cfg_attr(test, allow(dead_code))]
+pub fn holds_locks() -> bool {
+    HELD.get().load(Ordering::Relaxed) != 0
+}
+
+/// A spinlock.
+///
+/// Waiting cores sleep with `wfe` until the holder unlocks with `sev`. The
+/// core holding the lock is recorded so that a core trying to take a lock it
+/// already holds panics instead of spinning forever.
+///
+/// A core that holds a lock is not preempted (see
+/// `process::take_preemption_request()`), so the holder is never moved to
+/// another core. Interrupts stay enabled unless the lock is taken with
+/// `lock_irqsave`, which masks them while it is held. Locks that interrupt
+/// handlers take must always be taken that way, so that a handler never spins
+/// on a lock its core holds.
+///
+/// On real hardware the exclusive accesses behind `compare_exchange` only work
+/// on cacheable memory, so the MMU and data cache must be enabled before a
+/// second core is started. QEMU does not have this restriction.
 #[repr(align(32))]
 pub struct Mutex<T> {
     data: UnsafeCell<T>,
     lock: AtomicBool,
+    /// The core holding the lock, or `NO_OWNER`. Only used for diagnostics.
+    owner: AtomicUsize,
 }
 
 unsafe impl<T: Send> Send for Mutex<T> {}
@@ -14,6 +46,9 @@ unsafe impl<T: Send> Sync for Mutex<T> {}
 
 pub struct MutexGuard<'a, T: 'a> {
     lock: &'a Mutex<T>,
+    /// The interrupt mask to restore on unlock, if the lock was taken with
+    /// `lock_irqsave`.
+    daif: Option<u64>,
 }
 
 unsafe impl<'a, T: Sync> Sync for MutexGuard<'a, T> {}
@@ -22,38 +57,72 @@ impl<T> Mutex<T> {
     pub const fn new(val: T) -> Mutex<T> {
         Mutex {
             lock: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_OWNER),
             data: UnsafeCell::new(val),
         }
     }
 }
 
 impl<T> Mutex<T> {
-    // Once MMU/cache is enabled, do the right thing here. For now, we don't
-    // need any real synchronization.
+    /// Takes the lock if it is free. Returns `None` if it is held.
     pub fn try_lock(&self) -> Option<MutexGuard<T>> {
-        if !self.lock.load(Ordering::Relaxed) {
-            self.lock.store(true, Ordering::Relaxed);
-            Some(MutexGuard { lock: self })
-        } else {
-            None
-        }
+        self.lock
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        self.owner.store(cpu::cpu_id(), Ordering::Relaxed);
+        HELD.get().fetch_add(1, Ordering::Relaxed);
+        Some(MutexGuard {
+            lock: self,
+            daif: None,
+        })
     }
 
-    // Once MMU/cache is enabled, do the right thing here. For now, we don't
-    // need any real synchronization.
+    /// Takes the lock, waiting for it to become free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is already held by this core, which would otherwise
+    /// deadlock. Since the holder cannot be switched out, this is a bug such
+    /// as a recursive lock.
     #[inline(never)]
     pub fn lock(&self) -> MutexGuard<T> {
-        // Wait until we can "aquire" the lock, then "acquire" it.
         loop {
-            match self.try_lock() {
-                Some(guard) => return guard,
-                None => continue,
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            let owner = self.owner.load(Ordering::Relaxed);
+            if owner == cpu::cpu_id() {
+                panic!("deadlock: core {owner} tried to take a lock it holds");
+            }
+
+            // Only read while waiting, so the holder's cache line is not
+            // stolen by failed exchanges.
+            while self.lock.load(Ordering::Relaxed) {
+                cpu::wait_for_event();
             }
         }
     }
 
+    /// Masks all interrupts on this core (DAIF) and takes the lock. The mask
+    /// is restored when the lock is released, so an interrupt handler on this
+    /// core can never spin on a lock this core holds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is already held by this core.
+    pub fn lock_irqsave(&self) -> MutexGuard<'_, T> {
+        let daif = cpu::mask_interrupts();
+        let mut guard = self.lock();
+        guard.daif = Some(daif);
+        guard
+    }
+
     fn unlock(&self) {
-        self.lock.store(false, Ordering::Relaxed);
+        self.owner.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.store(false, Ordering::Release);
+        cpu::send_event();
+        HELD.get().fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -73,7 +142,10 @@ impl<'a, T: 'a> DerefMut for MutexGuard<'a, T> {
 
 impl<'a, T: 'a> Drop for MutexGuard<'a, T> {
     fn drop(&mut self) {
-        self.lock.unlock()
+        self.lock.unlock();
+        if let Some(daif) = self.daif {
+            cpu::restore_interrupts(daif);
+        }
     }
 }
 
@@ -81,7 +153,14 @@ impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.try_lock() {
             Some(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
-            None => f.debug_struct("Mutex").field("data", &"<locked>").finish(),
+            None => f
+                .debug_struct("Mutex")
+                .field("data", &"<locked>")
+                .field("owner", &self.owner.load(Ordering::Relaxed))
+                .finish(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,34 @@
+use super::Mutex;
+
+#[test]
+fn try_lock_is_exclusive() {
+    let mutex = Mutex::new(1);
+    let mut guard = mutex.try_lock().expect("unlocked");
+    *guard += 1;
+    assert!(mutex.try_lock().is_none());
+
+    drop(guard);
+    assert_eq!(*mutex.try_lock().expect("unlocked"), 2);
+}
+
+#[test]
+fn lock_unlocks_on_drop() {
+    let mutex = Mutex::new(());
+    drop(mutex.lock());
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+#[should_panic(expected = "deadlock")]
+fn relock_on_same_core_panics() {
+    let mutex = Mutex::new(());
+    let _guard = mutex.lock();
+    let _deadlock = mutex.lock();
+}
+
+#[test]
+fn lock_irqsave_unlocks_on_drop() {
+    let mutex = Mutex::new(());
+    drop(mutex.lock_irqsave());
+    assert!(mutex.try_lock().is_some());
+}
@@ -0,0 +1,202 @@
+//! Lazy FP/SIMD context switching.
+//!
+//! The kernel is built for `aarch64-unknown-none-softfloat`, so it never uses
+//! the FP/SIMD registers: they only ever hold user state, and trap frames
+//! leave them out. A core lets EL0 use them only while they hold the running
+//! process's state. Until then CPACR_EL1 traps the process's first FP/SIMD
+//! instruction, EC 0x07 (`Syndrome::SimdFp`, not `Syndrome::TrappedFpu`,
+//! which reports floating-point exceptions), and `FpContext::load()` restores
+//! its state.
+//!
+//! A process that used the registers has them saved when it is switched out.
+//! Each core remembers whose state its registers hold, so a process that runs
+//! on the same core again, with no other process using FP/SIMD in between,
+//! gets access back without a trap or a restore. The state of processes that
+//! never use FP/SIMD is never saved or restored.
+
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::Id;
+use crate::cpu::{self, PerCore, CORES};
+
+/// The process whose FP/SIMD state the registers of each core hold, or `0`
+/// for none.
+static OWNER: PerCore<AtomicU64> = PerCore::new([const { AtomicU64::new(0) }; CORES]);
+
+/// The FP/SIMD registers of a process. The layout is shared with `fp_save`
+/// and `fp_restore` in `kernel.S`.
+#[repr(C, align(16))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FpState {
+    /// The 128-bit registers q0..q31.
+    pub q: [u128; 32],
+    /// Floating-point Status Register.
+    pub fpsr: u64,
+    /// Floating-point Control Register.
+    pub fpcr: u64,
+}
+
+/// The FP/SIMD context of a process.
+#[derive(Debug, Default, Clone)]
+pub struct FpContext {
+    /// The process's registers as of when it last used them.
+    state: Box<FpState>,
+    /// The core whose registers also hold `state`, if any.
+    loaded_on: Option<usize>,
+}
+
+impl FpContext {
+    /// Returns the saved registers.
+    pub fn state(&self) -> &FpState {
+        &self.state
+    }
+
+    /// Returns a copy of the context for a forked child. The registers must
+    /// have been saved with `FpContext::sync()`.
+    pub fn fork(&self) -> FpContext {
+        FpContext {
+            state: self.state.clone(),
+            loaded_on: None,
+        }
+    }
+
+    /// Returns `true` if the registers of core `core`, which hold the state of
+    /// process `owner`, hold this context of process `id`.
+    fn is_loaded(&self, id: Id, core: usize, owner: Id) -> bool {
+        owner == id && self.loaded_on == Some(core)
+    }
+
+    /// Called when process `id` is scheduled on this core. Gives it access to
+    /// the registers if they still hold its state, and otherwise arms the
+    /// trap on its first FP/SIMD instruction.
+    pub fn switch_in(&self, id: Id) {
+        let core = cpu::cpu_id();
+        if self.is_loaded(id, core, OWNER.get().load(Ordering::Relaxed)) {
+            enable();
+        } else {
+            disable();
+        }
+    }
+
+    /// Called when the process is switched out on this core. Saves its
+    /// registers if it used them since it was scheduled.
+    pub fn switch_out(&mut self) {
+        if is_enabled() {
+            unsafe { fp_save(&mut *self.state) };
+            self.loaded_on = Some(cpu::cpu_id());
+            disable();
+        }
+    }
+
+    /// Called when process `id` traps on its first FP/SIMD instruction since
+    /// it was scheduled on this core. Loads its state into the registers and
+    /// gives it access. Whoever held the registers before was saved when it
+    /// was switched out.
+    #[cfg_attr(test, allow(dead_code))]
+    pub fn load(&mut self, id: Id) {
+        unsafe { fp_restore(&*self.state) };
+        OWNER.get().store(id, Ordering::Relaxed);
+        self.loaded_on = Some(cpu::cpu_id());
+        enable();
+    }
+
+    /// Saves the registers if the process, which runs on this core, is using
+    /// them, so that its context can be copied.
+    pub fn sync(&mut self) {
+        if is_enabled() {
+            unsafe { fp_save(&mut *self.state) };
+        }
+    }
+
+    /// Replaces the registers of the process, which runs on this core, with
+    /// `state`, as when it returns from a signal handler. Registers it is
+    /// using are loaded right away; otherwise its next FP/SIMD instruction
+    /// traps and loads them.
+    pub fn restore(&mut self, state: &FpState) {
+        *self.state = *state;
+        if is_enabled() {
+            unsafe { fp_restore(&*self.state) };
+        } else {
+            self.loaded_on = None;
+        }
+    }
+}
+
+/// CPACR_EL1.FPEN: the FP/SIMD access control bits.
+#[cfg(target_arch = "aarch64")]
+const CPACR_FPEN: u64 = 0b11 << 20;
+
+/// CPACR_EL1.FPEN value that traps FP/SIMD instructions at EL0 only.
+#[cfg(target_arch = "aarch64")]
+const CPACR_FPEN_TRAP_EL0: u64 = 0b01 << 20;
+
+#[cfg(target_arch = "aarch64")]
+fn cpacr() -> u64 {
+    let cpacr: u64;
+    unsafe {
+        core::arch::asm!("mrs {0}, CPACR_EL1", out(reg) cpacr);
+    }
+    cpacr
+}
+
+#[cfg(target_arch = "aarch64")]
+fn set_fpen(fpen: u64) {
+    let cpacr = cpacr();
+    if cpacr & CPACR_FPEN != fpen {
+        unsafe {
+            core::arch::asm!("msr CPACR_EL1, {0}", "isb", in(reg) (cpacr & !CPACR_FPEN) | fpen);
+        }
+    }
+}
+
+/// Returns `true` if EL0 may use the FP/SIMD registers on this core.
+#[cfg(target_arch = "aarch64")]
+fn is_enabled() -> bool {
+    cpacr() & CPACR_FPEN == CPACR_FPEN
+}
+
+/// Lets EL0 use the FP/SIMD registers on this core.
+#[cfg(target_arch = "aarch64")]
+fn enable() {
+    set_fpen(CPACR_FPEN);
+}
+
+/// Traps EL0's FP/SIMD instructions on this core.
+#[cfg(target_arch = "aarch64")]
+fn disable() {
+    set_fpen(CPACR_FPEN_TRAP_EL0);
+}
+
+#[cfg(target_arch = "aarch64")]
+extern "C" {
+    /// Saves this core's FP/SIMD registers into `state`. See `kernel.S`.
+    fn fp_save(state: *mut FpState);
+    /// Loads this core's FP/SIMD registers from `state`. See `kernel.S`.
+    fn fp_restore(state: *const FpState);
+}
+
+/// Host builds have no FP/SIMD registers to switch.
+#[cfg(not(target_arch = "aarch64"))]
+fn is_enabled() -> bool {
+    false
+}
+
+/// Host builds have no FP/SIMD registers to switch.
+#[cfg(not(target_arch = "aarch64"))]
+fn enable() {}
+
+/// Host builds have no FP/SIMD registers to switch.
+#[cfg(not(target_arch = "aarch64"))]
+fn disable() {}
+
+/// Host builds have no FP/SIMD registers to switch.
+#[cfg(not(target_arch = "aarch64"))]
+unsafe fn fp_save(_state: *mut FpState) {}
+
+/// Host builds have no FP/SIMD registers to switch.
+#[cfg(not(target_arch = "aarch64"))]
+unsafe fn fp_restore(_state: *const FpState) {}
+
+#[cfg(test)]
+mod tests;
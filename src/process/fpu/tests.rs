@@ -0,0 +1,49 @@
+use core::mem::{offset_of, size_of};
+
+use super::{FpContext, FpState};
+
+#[test]
+fn state_layout_matches_kernel_s() {
+    assert_eq!(offset_of!(FpState, fpsr), 0x200);
+    assert_eq!(offset_of!(FpState, fpcr), 0x208);
+    assert_eq!(size_of::<FpState>(), 0x210);
+}
+
+#[test]
+fn registers_hold_the_owner_loaded_on_the_core() {
+    let mut context = FpContext::default();
+    assert!(!context.is_loaded(1, 0, 0), "never loaded");
+
+    context.loaded_on = Some(2);
+    assert!(context.is_loaded(1, 2, 1));
+    assert!(!context.is_loaded(1, 0, 1), "loaded on another core");
+    assert!(!context.is_loaded(1, 2, 3), "another process used them since");
+}
+
+#[test]
+fn forked_context_is_not_loaded() {
+    let mut context = FpContext::default();
+    context.state.q[5] = 42;
+    context.state.fpcr = 1 << 24;
+    context.loaded_on = Some(0);
+
+    let child = context.fork();
+    assert_eq!(child.state(), context.state());
+    assert_eq!(child.loaded_on, None);
+}
+
+#[test]
+fn restored_context_is_reloaded() {
+    let mut context = FpContext {
+        loaded_on: Some(0),
+        ..FpContext::default()
+    };
+
+    let state = FpState {
+        fpcr: 1 << 24,
+        ..FpState::default()
+    };
+    context.restore(&state);
+    assert_eq!(context.state(), &state);
+    assert_eq!(context.loaded_on, None, "the registers hold the old state");
+}
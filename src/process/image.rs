@@ -0,0 +1,72 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::elf::{Elf, Error, Loaded, Segment};
+use alloc::alloc::Layout;
+
+/// The memory holding a process's program, loaded from an ELF executable.
+pub struct Image {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    /// The program's segments. Their permissions are not enforced until the
+    /// kernel has page tables.
+    pub segments: Vec<Segment>,
+}
+
+// The image is owned by its processes, which may run on any core. It is only
+// written while loading.
+unsafe impl Send for Image {}
+unsafe impl Sync for Image {}
+
+#[cfg_attr(test, allow(dead_code))]
+impl Image {
+    /// Allocates memory for `elf` and loads it there. Returns the image and
+    /// what the loader found out about it.
+    pub fn load(elf: &Elf) -> Result<(Image, Loaded), Error> {
+        let size = usize::try_from(elf.mem_size()).map_err(|_| Error::OutOfMemory)?;
+        let align = usize::try_from(elf.align()).map_err(|_| Error::OutOfMemory)?;
+        if size == 0 {
+            return Err(Error::NoSegments);
+        }
+        let layout = Layout::from_size_align(size, align).map_err(|_| Error::OutOfMemory)?;
+        let raw_ptr = unsafe { alloc::alloc::alloc(layout) };
+        let ptr = NonNull::new(raw_ptr).ok_or(Error::OutOfMemory)?;
+        let mut image = Image {
+            ptr,
+            layout,
+            segments: Vec::new(),
+        };
+
+        let base = image.base();
+        let loaded = elf.load(image.as_mut_slice(), base)?;
+        crate::cpu::sync_instruction_cache(base as usize, size);
+        image.segments = loaded.segments.clone();
+        Ok((image, loaded))
+    }
+
+    /// Returns the address the image is loaded at.
+    pub fn base(&self) -> u64 {
+        self.ptr.as_ptr() as u64
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("base", &format_args!("{:#x}", self.base()))
+            .field("size", &self.layout.size())
+            .field("segments", &self.segments)
+            .finish()
+    }
+}
@@ -0,0 +1,69 @@
+//! Kernel threads: processes that run kernel code at EL1h on their own stack.
+//!
+//! A kernel thread is scheduled like any user process and preempted by the
+//! same timer interrupt. Exceptions it takes are handled on its own stack, and
+//! the trap frame it is switched out with holds its SP_EL1. It exits when its
+//! function returns.
+//!
+//! Kernel threads run with interrupts unmasked and take the same locks as
+//! interrupt handlers, such as the allocator's and the logger's. This is safe
+//! because those locks are always taken with `Mutex::lock_irqsave()`, so a
+//! handler never interrupts their holder, and because a core is not preempted
+//! while it holds any lock. In turn, a kernel thread may not block while it
+//! holds a lock; `process::block()` panics if it tries.
+
+#[cfg(not(test))]
+use super::Id;
+use super::Process;
+
+/// The stack size of kernel threads.
+pub const STACK_SIZE: usize = 64 * 1024;
+
+/// Returns a new kernel thread named `name` that runs `entry`, to be added to
+/// the scheduler. Returns `None` if its stack could not be allocated.
+pub fn new(name: &str, entry: fn()) -> Option<Process> {
+    let mut process = Process::kernel_thread(STACK_SIZE)?;
+    process.name = name.into();
+    let top = process.stack.top().as_u64();
+    process
+        .trap_frame
+        .set_kernel_entry(start as *const () as u64, top);
+    process.trap_frame.x0 = entry as usize as u64;
+    Some(process)
+}
+
+/// Starts a kernel thread named `name` that runs `entry`, and returns its ID.
+/// Returns `None` if it could not be created or scheduled.
+#[cfg(not(test))]
+#[allow(dead_code)] // not used yet.
+pub fn spawn(name: &str, entry: fn()) -> Option<Id> {
+    crate::SCHEDULER.add(new(name, entry)?)
+}
+
+/// The entry point of kernel threads. Runs the thread's function, passed in
+/// `x0`, and exits the thread when it returns.
+extern "C" fn start(entry: usize) -> ! {
+    let entry: fn() = unsafe { core::mem::transmute(entry) };
+    entry();
+    exit()
+}
+
+/// Exits the calling kernel thread with the `exit` system call.
+#[cfg(all(not(test), target_arch = "aarch64"))]
+fn exit() -> ! {
+    unsafe {
+        core::arch::asm!(
+        "svc #{num}",
+        num = const crate::traps::syscall::SYS_EXIT,
+        in("x0") 0,
+        options(noreturn)
+        );
+    }
+}
+
+/// Host builds do not run kernel threads.
+#[cfg(any(test, not(target_arch = "aarch64")))]
+fn exit() -> ! {
+    unreachable!("kernel threads only run on the target")
+}
+
@@ -1,10 +1,27 @@
+pub mod fpu;
+mod image;
+pub mod kthread;
+pub mod policy;
 #[allow(clippy::module_inception)]
 mod process;
-mod scheduler;
+#[cfg(not(test))]
+pub mod programs;
+pub(super) mod scheduler;
 mod stack;
+pub mod signal;
 pub mod state;
+pub mod stats;
+mod table;
 
+pub use self::image::Image;
 pub use self::process::{Id, Process};
-pub use self::scheduler::GlobalScheduler;
-pub use self::stack::Stack;
-pub use self::state::State;
+#[cfg_attr(test, allow(unused_imports))]
+pub use self::scheduler::{
+    kernel_stack_top, request_preemption, take_preemption_request, wake_idle_cores,
+    GlobalScheduler, Wait,
+};
+#[cfg(not(test))]
+pub use self::scheduler::block;
+pub use self::stack::{Stack, StackOverflow};
+pub use self::state::{ExitStatus, Interrupted, State};
+pub use self::table::ProcessTable;
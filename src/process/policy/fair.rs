@@ -0,0 +1,112 @@
+use alloc::collections::BTreeMap;
+
+use super::{Nice, SchedPolicy, NICE_MIN};
+use crate::process::Id;
+
+/// The weight of each niceness from `NICE_MIN` to `NICE_MAX`, as in Linux's
+/// CFS. Each step is about 1.25 times the next, so a process gets about 10%
+/// more CPU time than one with a niceness one higher.
+const WEIGHTS: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110,
+    87, 70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// The weight of niceness 0.
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// Returns the weight of `nice`.
+fn weight(nice: Nice) -> u64 {
+    WEIGHTS[(nice - NICE_MIN) as usize]
+}
+
+/// A fair policy modelled on Linux's CFS. Each process accumulates virtual
+/// runtime: its CPU time scaled down by its weight. The ready process with
+/// the least virtual runtime runs next, so each gets CPU time in proportion
+/// to its weight.
+///
+/// The base time slice is the period in which every ready process should run
+/// once. Each process runs for its weight's share of the period, but no less
+/// than `1/MIN_SLICE_DIVISOR` of it.
+#[derive(Debug)]
+pub struct Fair {
+    period: u64,
+    processes: BTreeMap<Id, Entry>,
+    /// The least virtual runtime of any ready process, which only grows. New
+    /// and waking processes start from it so that they cannot claim the CPU
+    /// time they did not use.
+    min_vruntime: u64,
+    /// The time slice of the process picked last.
+    slice: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    nice: Nice,
+    vruntime: u64,
+}
+
+impl Fair {
+    /// See the type documentation.
+    const MIN_SLICE_DIVISOR: u64 = 8;
+
+    pub fn new(period: u64) -> Fair {
+        Fair {
+            period,
+            processes: BTreeMap::new(),
+            min_vruntime: 0,
+            slice: period,
+        }
+    }
+
+    fn entry(&mut self, id: Id) -> &mut Entry {
+        let min_vruntime = self.min_vruntime;
+        self.processes.entry(id).or_insert(Entry {
+            nice: 0,
+            vruntime: min_vruntime,
+        })
+    }
+}
+
+impl SchedPolicy for Fair {
+    fn add(&mut self, id: Id, nice: Nice) {
+        let vruntime = self.min_vruntime;
+        self.processes.insert(id, Entry { nice, vruntime });
+    }
+
+    fn remove(&mut self, id: Id) {
+        self.processes.remove(&id);
+    }
+
+    fn set_nice(&mut self, id: Id, nice: Nice) {
+        self.entry(id).nice = nice;
+    }
+
+    fn charge(&mut self, id: Id, runtime: u64) {
+        let entry = self.entry(id);
+        entry.vruntime += runtime * NICE_0_WEIGHT / weight(entry.nice);
+    }
+
+    fn pick(&mut self, ready: &[Id]) -> Id {
+        // A process that slept has fallen behind; it may run ahead of the
+        // others by at most one period's worth of virtual runtime.
+        let floor = self.min_vruntime.saturating_sub(self.period);
+        for id in ready {
+            let entry = self.entry(*id);
+            entry.vruntime = entry.vruntime.max(floor);
+        }
+
+        let vruntime = |id: &Id| self.processes[id].vruntime;
+        let next = *ready.iter().min_by_key(|id| vruntime(id)).expect("ready");
+        self.min_vruntime = self.min_vruntime.max(vruntime(&next));
+
+        let total: u64 = ready.iter().map(|id| weight(self.processes[id].nice)).sum();
+        let share = self.period * weight(self.processes[&next].nice) / total;
+        self.slice = share.max(self.period / Self::MIN_SLICE_DIVISOR);
+        next
+    }
+
+    fn time_slice(&self, _id: Id) -> u64 {
+        self.slice
+    }
+}
@@ -0,0 +1,66 @@
+//! Scheduling policies.
+//!
+//! The `Scheduler` keeps the processes and decides which are ready to run. A
+//! `SchedPolicy` decides which of the ready processes runs next, and for how
+//! long. The kernel command line selects the policy with `sched=rr`,
+//! `sched=priority` or `sched=fair`.
+
+mod fair;
+mod priority;
+mod round_robin;
+
+#[cfg(test)]
+mod sim;
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+use core::fmt;
+
+pub use self::fair::Fair;
+pub use self::priority::Priority;
+pub use self::round_robin::RoundRobin;
+
+use super::Id;
+
+/// A process's niceness: lower values ask for more CPU time.
+pub type Nice = i8;
+
+/// The least nice, i.e. highest priority, niceness.
+pub const NICE_MIN: Nice = -20;
+/// The nicest, i.e. lowest priority, niceness.
+pub const NICE_MAX: Nice = 19;
+
+/// Decides which ready process runs next.
+pub trait SchedPolicy: fmt::Debug + Send {
+    /// Starts tracking the new process `id` with niceness `nice`.
+    fn add(&mut self, id: Id, nice: Nice);
+
+    /// Stops tracking process `id`, which has been reaped.
+    fn remove(&mut self, id: Id);
+
+    /// Changes the niceness of process `id`.
+    fn set_nice(&mut self, id: Id, nice: Nice);
+
+    /// Charges `runtime` microseconds of CPU time to process `id`, which just
+    /// stopped running.
+    fn charge(&mut self, id: Id, runtime: u64);
+
+    /// Chooses the process to run next from `ready`, which lists the ready
+    /// processes from least to most recently run. `ready` is never empty.
+    fn pick(&mut self, ready: &[Id]) -> Id;
+
+    /// Returns how long process `id`, which was just picked, may run before
+    /// it is preempted, in microseconds.
+    fn time_slice(&self, id: Id) -> u64;
+}
+
+/// Returns the policy called `name` with the base time slice `slice`.
+pub fn from_name(name: &str, slice: u64) -> Option<Box<dyn SchedPolicy>> {
+    match name {
+        "rr" => Some(Box::new(RoundRobin::new(slice))),
+        "priority" => Some(Box::new(Priority::new(slice))),
+        "fair" => Some(Box::new(Fair::new(slice))),
+        _ => None,
+    }
+}
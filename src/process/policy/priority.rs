@@ -0,0 +1,74 @@
+use alloc::collections::BTreeMap;
+
+use super::{Nice, SchedPolicy};
+use crate::process::Id;
+
+/// Runs the ready process with the highest priority, i.e. the lowest
+/// niceness. To keep low priority processes from starving, every time a ready
+/// process is passed over it ages, raising its priority by one step until it
+/// runs.
+#[derive(Debug)]
+pub struct Priority {
+    slice: u64,
+    processes: BTreeMap<Id, Entry>,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    nice: Nice,
+    /// The number of times the process was passed over since it last ran.
+    age: u32,
+}
+
+impl Entry {
+    /// Returns the effective priority. Lower runs first.
+    fn priority(&self) -> i64 {
+        self.nice as i64 - self.age as i64
+    }
+}
+
+impl Priority {
+    pub fn new(slice: u64) -> Priority {
+        Priority {
+            slice,
+            processes: BTreeMap::new(),
+        }
+    }
+}
+
+impl SchedPolicy for Priority {
+    fn add(&mut self, id: Id, nice: Nice) {
+        self.processes.insert(id, Entry { nice, age: 0 });
+    }
+
+    fn remove(&mut self, id: Id) {
+        self.processes.remove(&id);
+    }
+
+    fn set_nice(&mut self, id: Id, nice: Nice) {
+        self.processes.entry(id).or_default().nice = nice;
+    }
+
+    fn charge(&mut self, _id: Id, _runtime: u64) {}
+
+    fn pick(&mut self, ready: &[Id]) -> Id {
+        let priority = |id: &Id| self.processes.get(id).map_or(0, Entry::priority);
+        // `min_by_key` keeps the first of equal priorities, i.e. the least
+        // recently run.
+        let next = *ready.iter().min_by_key(|id| priority(id)).expect("ready");
+
+        for id in ready {
+            let entry = self.processes.entry(*id).or_default();
+            if *id == next {
+                entry.age = 0;
+            } else {
+                entry.age += 1;
+            }
+        }
+        next
+    }
+
+    fn time_slice(&self, _id: Id) -> u64 {
+        self.slice
+    }
+}
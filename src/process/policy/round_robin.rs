@@ -0,0 +1,33 @@
+use super::{Nice, SchedPolicy};
+use crate::process::Id;
+
+/// Runs the ready processes in turn, each for the same time slice. Ignores
+/// niceness.
+#[derive(Debug)]
+pub struct RoundRobin {
+    slice: u64,
+}
+
+impl RoundRobin {
+    pub fn new(slice: u64) -> RoundRobin {
+        RoundRobin { slice }
+    }
+}
+
+impl SchedPolicy for RoundRobin {
+    fn add(&mut self, _id: Id, _nice: Nice) {}
+
+    fn remove(&mut self, _id: Id) {}
+
+    fn set_nice(&mut self, _id: Id, _nice: Nice) {}
+
+    fn charge(&mut self, _id: Id, _runtime: u64) {}
+
+    fn pick(&mut self, ready: &[Id]) -> Id {
+        ready[0]
+    }
+
+    fn time_slice(&self, _id: Id) -> u64 {
+        self.slice
+    }
+}
@@ -0,0 +1,127 @@
+//! A deterministic simulation of the scheduler on one core, for comparing
+//! scheduling policies on synthetic workloads.
+//!
+//! Time only advances when a simulated task runs or the core idles, so every
+//! run of a simulation gives the same result.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{Nice, SchedPolicy};
+use crate::process::scheduler::Scheduler;
+use crate::process::{Process, State};
+use crate::traps::TrapFrame;
+
+/// A synthetic task: it runs for `run` microseconds, sleeps for `sleep`
+/// microseconds, and repeats.
+#[derive(Debug, Clone, Copy)]
+pub struct Task {
+    pub nice: Nice,
+    pub run: u64,
+    pub sleep: u64,
+}
+
+impl Task {
+    /// Returns a task that never sleeps.
+    pub fn cpu_bound(nice: Nice) -> Task {
+        Task {
+            nice,
+            run: u64::MAX,
+            sleep: 0,
+        }
+    }
+
+    /// Returns a task that runs in short bursts of `run` microseconds,
+    /// sleeping for `sleep` microseconds in between.
+    pub fn interactive(nice: Nice, run: u64, sleep: u64) -> Task {
+        Task { nice, run, sleep }
+    }
+}
+
+/// What happened to one task during a simulation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// The CPU time the task got, in microseconds.
+    pub runtime: u64,
+    /// The longest the task was ready without running, in microseconds.
+    pub max_latency: u64,
+}
+
+/// The progress of a task through its current burst.
+#[derive(Debug)]
+struct Progress {
+    /// The CPU time left in the current burst.
+    left: u64,
+    /// When the task last became ready.
+    ready_since: u64,
+    /// When the task wakes up, if it is sleeping.
+    wake_at: Option<u64>,
+}
+
+/// Runs `tasks` for `duration` microseconds with `policy` and reports on each
+/// task, in the order of `tasks`.
+pub fn simulate(policy: Box<dyn SchedPolicy>, tasks: &[Task], duration: u64) -> Vec<Report> {
+    let clock = Arc::new(AtomicU64::new(0));
+    let mut scheduler = Scheduler::new(policy);
+    let ids: Vec<_> = tasks
+        .iter()
+        .map(|task| {
+            let mut process = Process::new().expect("process");
+            process.nice = task.nice;
+            scheduler.add(process).expect("process ID")
+        })
+        .collect();
+    let mut progress: Vec<_> = tasks
+        .iter()
+        .map(|task| Progress {
+            left: task.run,
+            ready_since: 0,
+            wake_at: None,
+        })
+        .collect();
+    let mut reports = vec![Report::default(); tasks.len()];
+
+    let mut now = 0;
+    while now < duration {
+        clock.store(now, Ordering::Relaxed);
+        let mut tf = TrapFrame::zeroed();
+        let Some((id, slice)) = scheduler.schedule_with_slice(&mut tf, now) else {
+            // Idle until the next task wakes up.
+            match progress.iter().filter_map(|task| task.wake_at).min() {
+                Some(wake_at) => now = wake_at.min(duration),
+                None => break,
+            }
+            continue;
+        };
+
+        let index = ids.iter().position(|&task_id| task_id == id).expect("task");
+        let (task, progress, report) = (&tasks[index], &mut progress[index], &mut reports[index]);
+        progress.wake_at = None;
+        report.max_latency = report.max_latency.max(now - progress.ready_since);
+
+        let ran = slice.min(progress.left).min(duration - now);
+        now += ran;
+        report.runtime += ran;
+        progress.left -= ran;
+
+        if progress.left > 0 || task.sleep == 0 {
+            scheduler.save(State::Ready, &tf, now, 0);
+            progress.ready_since = now;
+            continue;
+        }
+
+        let wake_at = now + task.sleep;
+        let clock = clock.clone();
+        let sleep = Box::new(move |_: &mut Process| clock.load(Ordering::Relaxed) >= wake_at);
+        scheduler.save(State::Waiting(sleep), &tf, now, 0);
+        *progress = Progress {
+            left: task.run,
+            ready_since: wake_at,
+            wake_at: Some(wake_at),
+        };
+    }
+
+    reports
+}
@@ -0,0 +1,141 @@
+mod policies {
+    use crate::process::policy::{from_name, Fair, Priority, RoundRobin, SchedPolicy};
+
+    #[test]
+    fn from_name_selects_policy() {
+        assert!(format!("{:?}", from_name("rr", 10).unwrap()).starts_with("RoundRobin"));
+        assert!(format!("{:?}", from_name("priority", 10).unwrap()).starts_with("Priority"));
+        assert!(format!("{:?}", from_name("fair", 10).unwrap()).starts_with("Fair"));
+        assert!(from_name("fifo", 10).is_none());
+    }
+
+    #[test]
+    fn round_robin_picks_least_recently_run() {
+        let mut policy = RoundRobin::new(10);
+        policy.add(1, -20);
+        policy.add(2, 19);
+        assert_eq!(policy.pick(&[2, 1]), 2);
+        assert_eq!(policy.time_slice(2), 10);
+    }
+
+    #[test]
+    fn priority_ages_waiting_processes() {
+        let mut policy = Priority::new(10);
+        policy.add(1, 0);
+        policy.add(2, 2);
+
+        // Process 2 is passed over twice, which brings it level with process
+        // 1, and it wins the tie as the least recently run.
+        assert_eq!(policy.pick(&[1, 2]), 1);
+        assert_eq!(policy.pick(&[2, 1]), 1);
+        assert_eq!(policy.pick(&[2, 1]), 2);
+        assert_eq!(policy.pick(&[1, 2]), 1);
+
+        policy.set_nice(2, -1);
+        assert_eq!(policy.pick(&[1, 2]), 2);
+    }
+
+    #[test]
+    fn fair_picks_least_vruntime() {
+        let mut policy = Fair::new(1_000);
+        policy.add(1, 0);
+        policy.add(2, 0);
+        policy.charge(1, 500);
+        assert_eq!(policy.pick(&[1, 2]), 2);
+        assert_eq!(policy.time_slice(2), 500, "an equal share of the period");
+
+        // A higher weight accumulates vruntime more slowly.
+        policy.set_nice(2, -5);
+        policy.charge(2, 1_000);
+        assert_eq!(policy.pick(&[1, 2]), 2);
+        assert!(policy.time_slice(2) > 500);
+    }
+
+    #[test]
+    fn fair_new_processes_start_at_min_vruntime() {
+        let mut policy = Fair::new(1_000);
+        policy.add(1, 0);
+        policy.charge(1, 50_000);
+        assert_eq!(policy.pick(&[1]), 1);
+
+        // Process 2 does not get 50ms of catching up to do.
+        policy.add(2, 0);
+        assert_eq!(policy.pick(&[1, 2]), 1, "ties go to the least recently run");
+        policy.charge(1, 1);
+        assert_eq!(policy.pick(&[1, 2]), 2);
+    }
+}
+
+mod simulation {
+    use crate::process::policy::sim::{simulate, Report, Task};
+    use crate::process::policy::{Fair, Priority, RoundRobin};
+
+    const SLICE: u64 = 10_000;
+    const DURATION: u64 = 3_000_000;
+
+    fn runtimes(reports: &[Report]) -> Vec<u64> {
+        reports.iter().map(|report| report.runtime).collect()
+    }
+
+    /// Returns `true` if `a / b` is within 5% of `ratio`.
+    fn ratio_near(a: u64, b: u64, ratio: f64) -> bool {
+        let actual = a as f64 / b as f64;
+        (actual / ratio - 1.0).abs() < 0.05
+    }
+
+    #[test]
+    fn deterministic() {
+        let tasks = [Task::cpu_bound(0), Task::interactive(0, 1_000, 5_000)];
+        let first = simulate(Box::new(Fair::new(SLICE)), &tasks, DURATION);
+        let second = simulate(Box::new(Fair::new(SLICE)), &tasks, DURATION);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn round_robin_shares_equally() {
+        let tasks = [Task::cpu_bound(-10), Task::cpu_bound(0), Task::cpu_bound(10)];
+        let reports = simulate(Box::new(RoundRobin::new(SLICE)), &tasks, DURATION);
+        assert_eq!(runtimes(&reports), [1_000_000; 3]);
+        assert!(reports.iter().all(|report| report.max_latency == 2 * SLICE));
+    }
+
+    #[test]
+    fn priority_favors_low_nice_without_starving() {
+        let tasks = [Task::cpu_bound(0), Task::cpu_bound(5)];
+        let reports = simulate(Box::new(Priority::new(SLICE)), &tasks, DURATION);
+        let [high, low] = [reports[0], reports[1]];
+        assert!(high.runtime > 4 * low.runtime, "{reports:?}");
+        assert!(low.runtime > 0, "aging lets the low priority task run");
+        assert!(low.max_latency <= 6 * SLICE, "{reports:?}");
+        assert_eq!(high.runtime + low.runtime, DURATION);
+    }
+
+    #[test]
+    fn fair_shares_by_weight() {
+        let tasks = [Task::cpu_bound(0), Task::cpu_bound(0), Task::cpu_bound(5)];
+        let reports = simulate(Box::new(Fair::new(SLICE)), &tasks, DURATION);
+        let runtimes = runtimes(&reports);
+        assert!(ratio_near(runtimes[0], runtimes[1], 1.0), "{runtimes:?}");
+        // Weights 1024 and 335.
+        assert!(ratio_near(runtimes[0], runtimes[2], 1024.0 / 335.0), "{runtimes:?}");
+    }
+
+    #[test]
+    fn fair_keeps_interactive_latency_low() {
+        // An interactive task competes with higher priority CPU hogs. Static
+        // priorities make it wait for aging; the fair policy runs it soon
+        // after it wakes because it used little CPU time.
+        let tasks = [
+            Task::cpu_bound(-5),
+            Task::cpu_bound(-5),
+            Task::interactive(0, 1_000, 20_000),
+        ];
+        let priority = simulate(Box::new(Priority::new(SLICE)), &tasks, DURATION);
+        let fair = simulate(Box::new(Fair::new(SLICE)), &tasks, DURATION);
+        assert!(
+            fair[2].max_latency < priority[2].max_latency,
+            "fair {fair:?} priority {priority:?}"
+        );
+        assert!(fair[2].max_latency <= SLICE, "{fair:?}");
+    }
+}
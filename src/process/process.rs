@@ -1,33 +1,235 @@
-use super::{Stack, State};
+use super::fpu::FpContext;
+use super::policy::Nice;
+use super::signal::Signals;
+use super::stats::CpuStats;
+use super::{Image, Interrupted, Stack, StackOverflow, State};
+use crate::elf::{self, Elf, InitialStack};
+use crate::file::FileTable;
+use crate::ipc::Transaction;
+use crate::sync::WaitQueue;
 use crate::traps::TrapFrame;
+use crate::vm::user::{self, UserMemory};
+use crate::vm::{Protection, VmaList};
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
 
 /// Type alias for the type of a process ID.
 pub type Id = u64;
 
 /// A structure that represents the complete state of a process.
 #[derive(Debug)]
+#[cfg_attr(test, allow(dead_code))]
 pub struct Process {
+    /// The process's ID, assigned when it is added to the scheduler's process
+    /// table. Kept here rather than in `TPIDR_EL0`, which user code may
+    /// overwrite.
+    pub id: Id,
+    /// The process's name: the first argument of the program it runs.
+    pub name: String,
     /// The saved trap frame of a process.
     pub trap_frame: Box<TrapFrame>,
     /// The memory allocation used for the process's stack.
     pub stack: Stack,
+    /// The stack the kernel handles the process's exceptions on. A process
+    /// that sleeps in a system call is switched out on it. Kernel threads have
+    /// none: they take exceptions on `stack`.
+    pub kernel_stack: Option<Stack>,
     /// The scheduling state of the process.
     pub state: State,
+    /// The program loaded into the process, if it runs one. Shared with
+    /// forked children.
+    pub image: Option<Arc<Image>>,
+    /// The process that is notified when this process exits, if any.
+    pub parent: Option<Id>,
+    /// Woken whenever one of the process's children exits.
+    pub child_exited: WaitQueue,
+    /// The process's niceness. See `policy::SchedPolicy`.
+    pub nice: Nice,
+    /// The CPU time the process used and how often it was scheduled.
+    pub stats: CpuStats,
+    /// The process's FP/SIMD registers. See `fpu`.
+    pub fp: FpContext,
+    /// The process's pending and blocked signals and their actions.
+    pub signals: Signals,
+    /// The process's open files. Shared with forked children and kept across
+    /// `exec`.
+    pub files: FileTable,
+    /// The IPC send or call the process waits to complete. Kept here rather
+    /// than on the kernel stack, so that it is dropped with the process.
+    pub transaction: Option<Arc<Transaction>>,
+    /// The memory regions the process has mapped and its heap. Shared with
+    /// forked children and unmapped by `exec`.
+    pub vmas: VmaList,
 }
 
 impl Process {
     /// Creates a new process with a zeroed `TrapFrame` (the default), a zeroed
-    /// stack of the default size, and a state of `Ready`.
-    ///
-    /// If enough memory could not be allocated to start the process, returns
-    /// `None`. Otherwise returns `Some` of the new `Process`.
-    pub fn new() -> Self {
+    /// stack of the default size, and a state of `Ready`. Returns `None` if
+    /// the stacks could not be allocated.
+    pub fn new() -> Option<Self> {
+        Self::with_stack_size(Stack::DEFAULT_SIZE)
+    }
+
+    /// Like `Process::new()`, but with a stack of `size` bytes. Returns `None`
+    /// if the stacks could not be allocated. See `Stack::with_size()`.
+    pub fn with_stack_size(size: usize) -> Option<Self> {
+        Some(Self::with_stacks(Stack::with_size(size)?, Some(Stack::kernel()?)))
+    }
+
+    /// Returns a new process for a kernel thread with a stack of `size` bytes
+    /// and no kernel stack. Returns `None` if the stack could not be
+    /// allocated. See `kthread::new()`.
+    pub fn kernel_thread(size: usize) -> Option<Self> {
+        Stack::with_size(size).map(|stack| Self::with_stacks(stack, None))
+    }
+
+    fn with_stacks(stack: Stack, kernel_stack: Option<Stack>) -> Self {
         Self {
+            id: 0,
+            name: String::new(),
             trap_frame: Box::new(TrapFrame::zeroed()),
-            stack: Stack::new(),
+            stack,
+            kernel_stack,
+            state: State::Ready,
+            image: None,
+            parent: None,
+            child_exited: WaitQueue::new(),
+            nice: 0,
+            stats: CpuStats::default(),
+            fp: FpContext::default(),
+            signals: Signals::default(),
+            files: FileTable::default(),
+            transaction: None,
+            vmas: VmaList::default(),
+        }
+    }
+
+    /// Creates a new process running the ELF executable `bytes` with the
+    /// arguments `argv` and environment `envp`. See `Process::exec()`.
+    #[cfg_attr(test, allow(dead_code))]
+    pub fn load(bytes: &[u8], argv: &[&str], envp: &[&str]) -> Result<Self, elf::Error> {
+        let mut process = Self::new().ok_or(elf::Error::OutOfMemory)?;
+        process.exec(bytes, argv, envp)?;
+        Ok(process)
+    }
+
+    /// Replaces the process's program with the ELF executable `bytes`, run
+    /// with the arguments `argv` and environment `envp`. The process gets a
+    /// fresh stack, trap frame and FP/SIMD registers, keeping only its ID,
+    /// accounting, signals and open files, and is named after `argv[0]`.
+    /// Mapped memory and the heap are freed, and signal handlers are reset.
+    /// On error, the process is left unchanged.
+    ///
+    /// The program starts at its entry point with `sp` pointing at `argc`,
+    /// followed by `argv`, `envp` and the auxiliary vector. For programs that
+    /// do not read their stack, `x0`, `x1` and `x2` also hold `argc`, `argv`
+    /// and `envp`.
+    ///
+    /// The stack has the size the program asks for with `PT_GNU_STACK`, or
+    /// the default size.
+    #[cfg_attr(test, allow(dead_code))]
+    pub fn exec(&mut self, bytes: &[u8], argv: &[&str], envp: &[&str]) -> Result<(), elf::Error> {
+        let elf = Elf::parse(bytes)?;
+        let (image, loaded) = Image::load(&elf)?;
+
+        let stack_size = elf.stack_size().map_or(Stack::DEFAULT_SIZE, |size| size as usize);
+        let mut stack = Stack::with_size(stack_size).ok_or(elf::Error::OutOfMemory)?;
+        let top = stack.top().as_u64();
+        let initial = InitialStack::build(stack.as_mut_slice(), top, argv, envp, &loaded.auxv)?;
+
+        let mut tf = TrapFrame::zeroed();
+        tf.set_user_entry(loaded.entry, initial.sp);
+        tf.x0 = initial.argc;
+        tf.x1 = initial.argv;
+        tf.x2 = initial.envp;
+
+        *self.trap_frame = tf;
+        self.name = argv.first().map_or_else(String::new, |&name| String::from(name));
+        self.stack = stack;
+        self.image = Some(Arc::new(image));
+        self.fp = FpContext::default();
+        self.signals.exec();
+        self.vmas.clear();
+        Ok(())
+    }
+
+    /// Returns a copy of this process, running with the trap frame `tf`, to
+    /// be added to the scheduler as its child, or `None` if there is no
+    /// memory for its stacks. The child returns `0` from `fork`.
+    ///
+    /// The child gets a copy of the stack, with `sp` and the frame pointer
+    /// `x29` moved to the copy, of the FP/SIMD registers as last saved, and of
+    /// the signal actions and mask. It shares the parent's open files. Until
+    /// the kernel has virtual memory, the program image, mapped memory and
+    /// heap are shared with the parent, and pointers saved in the stack still
+    /// point into the parent's stack.
+    pub fn fork(&self, tf: &TrapFrame) -> Option<Self> {
+        let kernel_stack = Stack::kernel()?;
+        let mut stack = Stack::with_size(self.stack.size())?;
+        stack.as_mut_slice().copy_from_slice(self.stack.as_slice());
+
+        let (bottom, top) = (self.stack.bottom().as_u64(), self.stack.top().as_u64());
+        let offset = stack.bottom().as_u64().wrapping_sub(bottom);
+        let rebase = |addr: u64| {
+            if (bottom..=top).contains(&addr) {
+                addr.wrapping_add(offset)
+            } else {
+                addr
+            }
+        };
+
+        let mut trap_frame = Box::new(*tf);
+        trap_frame.sp = rebase(tf.sp);
+        trap_frame.x29 = rebase(tf.x29);
+        trap_frame.x0 = 0;
+        trap_frame.x7 = 0;
+
+        Some(Self {
+            id: 0,
+            name: self.name.clone(),
+            trap_frame,
+            stack,
+            kernel_stack: Some(kernel_stack),
             state: State::Ready,
+            image: self.image.clone(),
+            parent: Some(self.id),
+            child_exited: WaitQueue::new(),
+            nice: self.nice,
+            stats: CpuStats::default(),
+            fp: self.fp.fork(),
+            signals: self.signals.fork(),
+            files: self.files.clone(),
+            transaction: None,
+            vmas: self.vmas.clone(),
+        })
+    }
+
+    /// Checks the process's stacks for overflow as it is switched out with
+    /// the trap frame `tf`. See `Stack::check()`.
+    ///
+    /// A process switched out at EL1h runs on its kernel stack, if it has one:
+    /// it sleeps in a system call.
+    pub fn check_stacks(&self, tf: &TrapFrame) -> Result<(), StackOverflow> {
+        let Some(kernel_stack) = &self.kernel_stack else {
+            return self.stack.check(self.id, tf.sp);
+        };
+        if tf.is_kernel() {
+            return kernel_stack.check(self.id, tf.sp);
         }
+        kernel_stack.check(self.id, kernel_stack.top().as_u64())?;
+        self.stack.check(self.id, tf.sp)
+    }
+
+    /// Returns `true` if this process has exited.
+    pub fn is_zombie(&self) -> bool {
+        matches!(self.state, State::Zombie(_))
+    }
+
+    /// Returns `true` if this process is a kernel thread, which has no
+    /// separate kernel stack.
+    pub fn is_kernel_thread(&self) -> bool {
+        self.kernel_stack.is_none()
     }
 
     /// Returns `true` if this process is ready to be scheduled.
@@ -43,15 +245,30 @@ impl Process {
     ///     occured. If it has, the state is switched to `Ready` and this
     ///     function returns `true`.
     ///
+    ///   * A signal arrived that the waiting process does not block. A process
+    ///     sleeping in the kernel is told it was `Interrupted`.
+    ///
     /// Returns `false` in all other cases.
     pub fn is_ready(&mut self) -> bool {
         let current_state = core::mem::replace(&mut self.state, State::Ready);
         let (is_ready, next_state) = match current_state {
             State::Ready => (true, State::Ready),
             State::Running => (false, State::Running),
+            State::Zombie(status) => (false, State::Zombie(status)),
             State::Waiting(mut poll_fn) => {
                 let is_ready = poll_fn(self);
-                (is_ready, State::Waiting(poll_fn))
+                let interrupted = !is_ready && self.signals.is_deliverable();
+                if interrupted && self.trap_frame.is_kernel() {
+                    self.trap_frame.x0 = Interrupted::BLOCK_RESULT;
+                }
+                let is_ready = is_ready || interrupted;
+                let next_state = if is_ready {
+                    self.stats.wakeups += 1;
+                    State::Ready
+                } else {
+                    State::Waiting(poll_fn)
+                };
+                (is_ready, next_state)
             }
         };
 
@@ -59,3 +276,24 @@ impl Process {
         is_ready
     }
 }
+
+/// A user process may access its stack, the segments of its program, its
+/// heap and the regions it mapped. Kernel threads may access all of memory.
+impl UserMemory for Process {
+    fn accessible_end(&self, addr: u64, prot: Protection) -> Option<u64> {
+        if self.is_kernel_thread() {
+            return Some(u64::MAX);
+        }
+
+        let stack = self.stack.bottom().as_u64()..self.stack.top().as_u64();
+        let segments = self.image.iter().flat_map(|image| &image.segments);
+        let regions = core::iter::once((stack, Protection::READ_WRITE))
+            .chain(segments.map(|segment| {
+                let end = segment.start + segment.size;
+                (segment.start..end, Protection::from(segment.flags))
+            }))
+            .chain(self.vmas.heap().map(|heap| (heap, Protection::READ_WRITE)))
+            .chain(self.vmas.iter().map(|vma| (vma.start()..vma.end(), vma.prot)));
+        user::accessible_end(regions, addr, prot)
+    }
+}
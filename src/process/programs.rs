@@ -0,0 +1,40 @@
+//! User programs embedded in the kernel image.
+//!
+//! `make` builds the programs in `user/` and `kernel.S` includes them with
+//! `.incbin` between a pair of start and end symbols.
+
+extern "C" {
+    static __user_init_start: u8;
+    static __user_init_end: u8;
+    static __user_sleeper_start: u8;
+    static __user_sleeper_end: u8;
+    static __user_rpc_server_start: u8;
+    static __user_rpc_server_end: u8;
+    static __user_rpc_client_start: u8;
+    static __user_rpc_client_end: u8;
+}
+
+/// Returns the bytes between the symbols `start` and `end`.
+fn embedded(start: &'static u8, end: &'static u8) -> &'static [u8] {
+    let start = start as *const u8;
+    let len = end as *const u8 as usize - start as usize;
+    unsafe { core::slice::from_raw_parts(start, len) }
+}
+
+/// Returns the ELF image of the `init` program.
+pub fn init() -> &'static [u8] {
+    unsafe { embedded(&__user_init_start, &__user_init_end) }
+}
+
+/// Returns the ELF image of the program called `name`, for `exec`.
+pub fn find(name: &str) -> Option<&'static [u8]> {
+    unsafe {
+        match name {
+            "init" => Some(init()),
+            "sleeper" => Some(embedded(&__user_sleeper_start, &__user_sleeper_end)),
+            "rpc_server" => Some(embedded(&__user_rpc_server_start, &__user_rpc_server_end)),
+            "rpc_client" => Some(embedded(&__user_rpc_client_start, &__user_rpc_client_end)),
+            _ => None,
+        }
+    }
+}
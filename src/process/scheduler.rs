@@ -1,36 +1,358 @@
+use alloc::boxed::Box;
 use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-use super::{Id, Process, State};
+use super::policy::{Nice, SchedPolicy, NICE_MAX, NICE_MIN};
+use super::signal::{self, Delivery, InvalidSignal, SigAction, SigSet, Signal, SIGCHLD, SIGKILL, SIGSEGV};
+use super::stats::{self, ProcessInfo};
+#[cfg(not(test))]
+use super::state::EventPollFn;
+#[cfg(not(test))]
+use super::Interrupted;
+use super::{ExitStatus, Id, Process, ProcessTable, StackOverflow, State};
+use crate::cpu::{self, PerCore, CORES};
+use crate::elf;
+use crate::file::FileTable;
 use crate::mutex::Mutex;
+use crate::sync::Waiter;
 use crate::traps::TrapFrame;
+use crate::vm::VmaList;
+
+/// Set when the process running on a core should be switched out before
+/// returning from the current exception.
+static PREEMPTION_REQUESTED: PerCore<AtomicBool> =
+    PerCore::new([const { AtomicBool::new(false) }; CORES]);
+
+/// Requests that the process running on this core be switched out before
+/// returning from the current exception. Used by timer callbacks, which cannot
+/// switch processes themselves.
+#[cfg_attr(test, allow(dead_code))]
+pub fn request_preemption() {
+    PREEMPTION_REQUESTED.get().store(true, Ordering::Relaxed);
+}
+
+/// How long a process that holds a spin lock when its preemption is requested
+/// keeps running before it is asked again, in microseconds.
+#[cfg_attr(test, allow(dead_code))]
+const LOCK_HOLD_SLICE: u64 = 1_000;
+
+/// Returns `true`, and clears the request, if preemption was requested on this
+/// core. A core that holds a spin lock is not preempted, so that the lock's
+/// holder never moves to another core: the request is cleared, and the time
+/// slice extended by `LOCK_HOLD_SLICE` instead.
+#[cfg_attr(test, allow(dead_code))]
+pub fn take_preemption_request() -> bool {
+    if !PREEMPTION_REQUESTED.get().swap(false, Ordering::Relaxed) {
+        return false;
+    }
+    if crate::mutex::holds_locks() {
+        start_time_slice(LOCK_HOLD_SLICE);
+        return false;
+    }
+    true
+}
+
+/// Marks a core without a time slice timer in `SLICE_TIMERS`.
+#[cfg(not(test))]
+const NO_TIMER: u64 = u64::MAX;
+
+/// The timer that ends the time slice of the process running on each core.
+#[cfg(not(test))]
+static SLICE_TIMERS: PerCore<AtomicU64> = PerCore::new([const { AtomicU64::new(NO_TIMER) }; CORES]);
+
+/// Arms this core's time slice timer to request preemption in `us`
+/// microseconds, replacing the previous time slice.
+#[cfg(not(test))]
+fn start_time_slice(us: u64) {
+    stop_time_slice();
+    let id = crate::TIMERS.get().after(us, Box::new(request_preemption));
+    SLICE_TIMERS.get().store(id, Ordering::Relaxed);
+}
+
+/// Cancels this core's time slice timer, if it is armed. An idle core has no
+/// time slice, so its clock only interrupts for the next software timer, such
+/// as the deadline of a sleeping process.
+#[cfg(not(test))]
+fn stop_time_slice() {
+    let previous = SLICE_TIMERS.get().swap(NO_TIMER, Ordering::Relaxed);
+    if previous != NO_TIMER {
+        crate::TIMERS.get().cancel(previous);
+    }
+}
+
+/// Host builds have no timers.
+#[cfg(test)]
+fn start_time_slice(_us: u64) {}
+
+/// Host builds have no timers.
+#[cfg(test)]
+fn stop_time_slice() {}
+
+/// The ID `switch()` returns when a core has no process to run and switches
+/// to its idle task. Never allocated to a process.
+pub const IDLE: Id = 0;
+
+/// Counts the changes that may have made a process ready. Idle cores wait for
+/// it to change.
+static WORK: AtomicU64 = AtomicU64::new(0);
+
+/// Wakes idle cores to look for a ready process. Called whenever a process
+/// may have become ready, such as after waking a `Waiter`.
+pub fn wake_idle_cores() {
+    WORK.fetch_add(1, Ordering::Release);
+    cpu::send_event();
+}
+
+/// The top of the kernel stack of the process running on each core, or `0` if
+/// it has none and exceptions from EL0 are taken on the core's own stack.
+static KERNEL_STACK_TOP: PerCore<AtomicU64> = PerCore::new([const { AtomicU64::new(0) }; CORES]);
+
+/// Returns the top of the kernel stack of the process running on this core,
+/// or `None` if it has none. Trap frames that return to EL0 are restored from
+/// there, so that the process's next exception is taken on its kernel stack.
+#[cfg_attr(test, allow(dead_code))]
+pub fn kernel_stack_top() -> Option<u64> {
+    match KERNEL_STACK_TOP.get().load(Ordering::Relaxed) {
+        0 => None,
+        top => Some(top),
+    }
+}
+
+/// Records the top of the kernel stack of the process scheduled on this core.
+fn set_kernel_stack_top(top: Option<u64>) {
+    KERNEL_STACK_TOP.get().store(top.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Blocks the calling process in the kernel until `poll` returns `true`,
+/// letting other processes run meanwhile. See `State::Waiting`.
+///
+/// The process is switched out in the middle of the system call or kernel
+/// thread calling this, on its kernel stack, and returns from here when it
+/// runs again. Must be called on behalf of a process, holding no spin locks.
+///
+/// # Panics
+///
+/// Panics if this core holds a lock, which would stay held while other
+/// processes run.
+///
+/// Returns `Err(Interrupted)` if the process was woken by a signal before
+/// the event occurred. The system call should then fail with `EINTR`, so
+/// that the signal is delivered on its way back to user space.
+#[cfg(all(not(test), target_arch = "aarch64"))]
+pub fn block(poll: EventPollFn) -> Result<(), Interrupted> {
+    let mut poll = Some(poll);
+    let result: u64;
+    unsafe {
+        core::arch::asm!(
+        "svc #{num}",
+        num = const crate::traps::syscall::SYS_BLOCK,
+        inout("x0") &mut poll as *mut Option<EventPollFn> => result,
+        );
+    }
+    match result {
+        Interrupted::BLOCK_RESULT => Err(Interrupted),
+        _ => Ok(()),
+    }
+}
+
+/// Host builds have no system calls.
+#[cfg(all(not(test), not(target_arch = "aarch64")))]
+pub fn block(_poll: EventPollFn) -> Result<(), Interrupted> {
+    unreachable!("host builds have no system calls")
+}
+
+/// How often each core logs its idle time, in microseconds. Reports are only
+/// made when a core leaves its idle task, so idle cores are not woken for them.
+#[cfg(not(test))]
+const IDLE_REPORT_INTERVAL: u64 = 10 * 1_000 * 1_000;
+
+/// When each core last logged its idle time.
+#[cfg(not(test))]
+static IDLE_REPORTED: PerCore<AtomicU64> = PerCore::new([const { AtomicU64::new(0) }; CORES]);
+
+/// Logs the idle time of this core if it has not done so in the last
+/// `IDLE_REPORT_INTERVAL`.
+#[cfg(not(test))]
+fn report_idle(stats: IdleStats, now: u64) {
+    let reported = IDLE_REPORTED.get();
+    if now - reported.load(Ordering::Relaxed) < IDLE_REPORT_INTERVAL {
+        return;
+    }
+
+    reported.store(now, Ordering::Relaxed);
+    crate::debug!(
+        "core {} idle for {} of {} ms ({}%), {} wakeups",
+        cpu::cpu_id(),
+        stats.total / 1_000,
+        now / 1_000,
+        stats.total * 100 / now.max(1),
+        stats.wakeups
+    );
+}
+
+#[cfg(not(test))]
+extern "C" {
+    /// The entry point of the idle task in `kernel.S`. Resets the stack to
+    /// the address in `x0` and calls `idle()`.
+    fn _idle();
+}
+
+/// Returns a trap frame that enters this core's idle task.
+#[cfg(not(test))]
+fn idle_frame() -> TrapFrame {
+    let mut tf = TrapFrame::zeroed();
+    let stack_end = cpu::stack_end(cpu::cpu_id()) as u64;
+    tf.set_kernel_entry(_idle as *const () as u64, stack_end);
+    tf.x0 = stack_end;
+    tf
+}
+
+/// The idle task of each core. Runs at EL1h with interrupts unmasked on a
+/// fresh stack, outside the scheduler lock, and waits in `wfe` until a
+/// process may be ready: another core changed the scheduler's processes or an
+/// interrupt arrived.
+///
+/// The task has no state worth keeping. Whenever the core switches to it, it
+/// starts over from `_idle`.
+#[cfg(not(test))]
+#[no_mangle]
+extern "C" fn idle() -> ! {
+    loop {
+        let work = WORK.load(Ordering::Acquire);
+        let mut tf = TrapFrame::zeroed();
+        if crate::SCHEDULER.leave_idle(&mut tf) {
+            unsafe { enter(&tf) }
+        }
+
+        while WORK.load(Ordering::Acquire) == work {
+            cpu::wait_for_event();
+        }
+    }
+}
+
+/// Restores `tf` and returns from the exception level into it, resetting this
+/// core's stack.
+///
+/// # Safety
+///
+/// Nothing on this core's stack may be in use.
+#[cfg(not(test))]
+unsafe fn enter(tf: &TrapFrame) -> ! {
+    // Copy the trap frame to where it is restored from, the top of this
+    // core's stack or below a kernel thread's stack pointer, and restore it
+    // from there like the exception vectors do.
+    let tf_dst = TrapFrame::relocate(tf);
+    core::arch::asm!(
+    "mov sp, {0}",
+    "bl context_restore",
+    "ldp lr, x0, [SP], #0x10",
+    "eret",
+    in(reg) tf_dst,
+    options(noreturn)
+    );
+}
+
+/// Returns the current time in microseconds.
+#[cfg(not(test))]
+fn now() -> u64 {
+    crate::TIMERS.get().now()
+}
+
+/// Host builds have no timers.
+#[cfg(test)]
+fn now() -> u64 {
+    0
+}
+
+/// The outcome of waiting for a child process.
+#[derive(Debug)]
+pub enum Wait {
+    /// The child with this ID exited with this status and has been reaped.
+    Exited(Id, ExitStatus),
+    /// The matching children are all still running. Holds a waiter that is
+    /// woken when one of them exits, if the caller asked to block.
+    Running(Option<Waiter>),
+    /// The caller has no matching children.
+    NoChildren,
+}
 
 /// Process scheduler for the entire machine.
 #[derive(Debug)]
+#[cfg_attr(test, allow(dead_code))]
 pub struct GlobalScheduler(Mutex<Option<Scheduler>>);
 
-fn thread_id() -> u64 {
-    let thread_id: u64;
+/// Returns the ID of the calling process with the `getpid` system call.
+#[cfg(all(not(test), target_arch = "aarch64"))]
+fn getpid() -> Id {
+    let pid: Id;
     unsafe {
         core::arch::asm!(
-        "mrs {0}, TPIDR_EL0",
-        out(reg) thread_id
+        "svc #{num}",
+        num = const crate::traps::syscall::SYS_GETPID,
+        out("x0") pid,
+        out("x7") _,
         );
     }
-    thread_id
+    pid
 }
 
-#[no_mangle]
-extern "C" fn init() {
-    let thread_id = self::thread_id();
-    crate::kprintln!("init enter tid {thread_id}");
+/// Host builds have no system calls.
+#[cfg(all(not(test), not(target_arch = "aarch64")))]
+fn getpid() -> Id {
+    0
+}
+
+/// Sleeps for `ms` milliseconds with the `sleep` system call, letting other
+/// processes run meanwhile.
+#[cfg(all(not(test), target_arch = "aarch64"))]
+fn sleep(ms: u32) {
+    unsafe {
+        core::arch::asm!(
+        "svc #{num}",
+        num = const crate::traps::syscall::SYS_SLEEP,
+        inout("x0") ms as u64 => _,
+        out("x7") _,
+        );
+    }
+}
+
+/// Host builds have no system calls.
+#[cfg(all(not(test), not(target_arch = "aarch64")))]
+fn sleep(_ms: u32) {}
 
+/// How many system calls `init()` makes to measure the trap round trip.
+#[cfg(not(test))]
+const TRAP_BENCH_CALLS: u64 = 10_000;
+
+/// The programs of the IPC example, started at boot: a server that answers
+/// calls to the port "adder", and a client that calls it. See `ipc`.
+#[cfg(not(test))]
+const IPC_EXAMPLE: [&str; 2] = ["rpc_server", "rpc_client"];
+
+/// The kernel's init thread. Like every kernel thread, it runs with interrupts
+/// unmasked; see `kthread`.
+#[cfg(not(test))]
+fn init() {
+    let pid = self::getpid();
+    crate::debug!("init enter pid {pid}");
+
+    // Measure the round trip of a system call: building, handling and
+    // restoring a trap frame.
+    let start = now();
+    for _ in 0..TRAP_BENCH_CALLS {
+        self::getpid();
+    }
+    let ns = (now() - start) * 1_000 / TRAP_BENCH_CALLS;
+    crate::info!("trap round trip: {ns} ns ({TRAP_BENCH_CALLS} system calls)");
+
+    crate::debug!("init pid {pid} idle");
     loop {
-        let thread_id = self::thread_id(); // keep getting thread_id in case it changed.
-        crate::kprintln!("init loop tid {thread_id}. Sleeping for 1 sec.");
-        crate::hw::timer::spin_sleep_ms(1_000);
+        self::sleep(1_000);
     }
 }
 
+#[cfg_attr(test, allow(dead_code))]
 impl GlobalScheduler {
     /// Returns an uninitialized wrapper around a local scheduler.
     pub const fn uninitialized() -> GlobalScheduler {
@@ -39,148 +361,717 @@ impl GlobalScheduler {
 
     /// Adds a process to the scheduler's queue and returns that process's ID.
     /// For more details, see the documentation on `Scheduler::add()`.
-    #[allow(dead_code)] // not used yet.
     pub fn add(&self, process: Process) -> Option<Id> {
-        self.0
-            .lock()
+        let id = self
+            .0
+            .lock_irqsave()
             .as_mut()
             .expect("scheduler uninitialized")
-            .add(process)
+            .add(process);
+        wake_idle_cores();
+        id
     }
 
     /// Performs a context switch using `tf` by setting the state of the current
     /// process to `new_state`, saving `tf` into the current process, and
-    /// restoring the next process's trap frame into `tf`. For more details, see
-    /// the documentation on `Scheduler::switch()`.
+    /// restoring the next process's trap frame into `tf`. Returns the ID of
+    /// the process switched to. For more details, see the documentation on
+    /// `Scheduler::save()` and `Scheduler::schedule()`.
+    ///
+    /// The process switched to runs for the time slice its scheduling policy
+    /// gives it. If no process is ready, `tf` is set to enter this core's idle
+    /// task instead, without a time slice, and `IDLE` is returned.
     #[must_use]
-    pub fn switch(&self, new_state: State, tf: &mut TrapFrame) -> Option<Id> {
+    pub fn switch(&self, new_state: State, tf: &mut TrapFrame) -> Id {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        let now = now();
+        if let Some(overflow) = scheduler.save(new_state, tf, now, stats::take_kernel_time(now)) {
+            crate::error!("{overflow}");
+        }
+        wake_idle_cores();
+
+        match scheduler.schedule_with_slice(tf, now) {
+            Some((id, time_slice)) => {
+                set_kernel_stack_top(scheduler.kernel_stack_top());
+                start_time_slice(time_slice);
+                id
+            }
+            None => {
+                scheduler.enter_idle(now);
+                drop(guard);
+                stop_time_slice();
+                #[cfg(not(test))]
+                {
+                    *tf = idle_frame();
+                }
+                IDLE
+            }
+        }
+    }
+
+    /// Called by this core's idle task: switches to a ready process by
+    /// restoring its trap frame into `tf`. Returns `false` if no process is
+    /// ready or the scheduler has not been started.
+    #[cfg(not(test))]
+    fn leave_idle(&self, tf: &mut TrapFrame) -> bool {
+        let mut guard = self.0.lock_irqsave();
+        let Some(scheduler) = guard.as_mut() else {
+            return false;
+        };
+        let now = now();
+        let mut scheduled = scheduler.schedule_with_slice(tf, now);
+        // Like returning from an exception, entering a process delivers its
+        // signals. One that terminates it leaves the core to the next.
+        while scheduled.is_some() {
+            let Some(sig) = scheduler.deliver_signals(tf) else {
+                break;
+            };
+            if let Some(overflow) = scheduler.save(State::Zombie(ExitStatus::Killed(sig)), tf, now, 0) {
+                crate::error!("{overflow}");
+            }
+            wake_idle_cores();
+            scheduled = scheduler.schedule_with_slice(tf, now);
+        }
+        let Some((_, time_slice)) = scheduled else {
+            scheduler.enter_idle(now);
+            return false;
+        };
+        set_kernel_stack_top(scheduler.kernel_stack_top());
+        let idle = scheduler.idle_stats();
+        drop(guard);
+
+        // Interrupts taken while idle are not charged to the process.
+        let _ = stats::take_kernel_time(now);
+        start_time_slice(time_slice);
+        report_idle(idle, now);
+        true
+    }
+
+    /// Loads the FP/SIMD registers of the process running on this core, which
+    /// trapped on its first FP/SIMD instruction. See `fpu`.
+    pub fn load_fp(&self) {
+        self.with_current(|process| process.fp.load(process.id));
+    }
+
+    /// Returns a snapshot of every process, ordered by ID.
+    pub fn snapshot(&self) -> Vec<ProcessInfo> {
+        self.0
+            .lock_irqsave()
+            .as_ref()
+            .map_or_else(Vec::new, Scheduler::snapshot)
+    }
+
+    /// Frees the processes this core reaped while handling its previous
+    /// exception. Called when an exception is taken. See
+    /// `Scheduler::reap_orphans()`.
+    pub fn release_retired(&self) {
+        if let Some(scheduler) = self.0.lock_irqsave().as_mut() {
+            scheduler.release_retired();
+        }
+    }
+
+    /// Returns the ID of the process running on this core, if any.
+    pub fn current(&self) -> Option<Id> {
         self.0
-            .lock()
+            .lock_irqsave()
+            .as_ref()
+            .and_then(|scheduler| scheduler.current[cpu::cpu_id()])
+    }
+
+    /// Sets the niceness of process `id`, or of the process running on this
+    /// core if `id` is `None`, to `nice`, clamped to the valid range. Returns
+    /// the previous niceness, or `None` if there is no such process.
+    pub fn set_nice(&self, id: Option<Id>, nice: i64) -> Option<Nice> {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        let id = id.or(scheduler.current[cpu::cpu_id()])?;
+        scheduler.set_nice(id, nice)
+    }
+
+    /// Ends the process running on this core with `status` and switches to
+    /// the next process using `tf`.
+    pub fn exit(&self, status: ExitStatus, tf: &mut TrapFrame) {
+        let _scheduled_pid = self.switch(State::Zombie(status), tf);
+    }
+
+    /// Kills the process `id`. If it is the process running on this core,
+    /// switches to the next process using `tf`. Returns `false` if there is
+    /// no such process.
+    pub fn kill(&self, id: Id, tf: &mut TrapFrame) -> bool {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        if scheduler.current[cpu::cpu_id()] == Some(id) {
+            drop(guard);
+            self.exit(ExitStatus::Killed(SIGKILL), tf);
+            return true;
+        }
+
+        let killed = scheduler.kill(id);
+        drop(guard);
+        // Killing a process may wake its parent.
+        wake_idle_cores();
+        killed
+    }
+
+    /// Returns `true` if there is a process `id`.
+    pub fn exists(&self, id: Id) -> bool {
+        self.0
+            .lock_irqsave()
+            .as_ref()
+            .is_some_and(|scheduler| scheduler.processes.get(id).is_some())
+    }
+
+    /// Sends signal `sig` to process `id`. `SIGKILL` kills it right away, as
+    /// described in `GlobalScheduler::kill()`. Other signals are delivered
+    /// when it next returns to user space, and wake it if it sleeps. Returns
+    /// `false` if there is no such process.
+    pub fn signal(&self, id: Id, sig: Signal, tf: &mut TrapFrame) -> bool {
+        if sig == SIGKILL {
+            return self.kill(id, tf);
+        }
+
+        let sent = self
+            .0
+            .lock_irqsave()
             .as_mut()
             .expect("scheduler uninitialized")
-            .switch(new_state, tf)
-    }
-
-    /// Initializes the scheduler and starts executing processes in user space
-    /// using timer interrupt based preemptive scheduling. This method should
-    /// not return under normal conditions.
-    pub fn start(&self) {
-        use core::ops::DerefMut;
-        let mut guard = self.0.lock();
-        let _old = core::mem::replace(guard.deref_mut(), Some(Scheduler::new()));
-        let mut process1 = Process::new();
-        unsafe {
-            // set the process trap frame's stack pointer
-            let process1_stack_pointer = process1.stack.top().as_mut_ptr() as *mut TrapFrame;
-            process1.trap_frame.sp = process1_stack_pointer as u64;
-
-            // set the link return to the init1 function
-            process1.trap_frame.elr = init as usize as u64;
-
-            // push the trap frame onto the processes stack
-            let process1_tf_dst = process1_stack_pointer.sub(1);
-            process1.trap_frame.tpidr = 1;
-            let process1_tf_src = (&mut *process1.trap_frame) as *mut TrapFrame;
-            core::ptr::copy(process1_tf_src, process1_tf_dst, 1);
-
-            process1.state = State::Running;
-            guard.as_mut().unwrap().add(process1);
-            guard.as_mut().unwrap().current = Some(1);
-
-            let mut process2 = Process::new();
-            process2.trap_frame.sp = process2.stack.top().as_ptr() as u64;
-            process2.trap_frame.elr = init as usize as u64;
-            guard.as_mut().unwrap().add(process2);
+            .signal(id, sig);
+        wake_idle_cores();
+        sent
+    }
 
-            drop(guard);
+    /// Sends signal `sig`, other than `SIGKILL`, to the process running on
+    /// this core. It is delivered on the way back to user space.
+    pub fn raise(&self, sig: Signal) {
+        self.with_current(|process| process.signals.send(sig));
+    }
+
+    /// Calls `f` with the open files of the process running on this core.
+    pub fn with_files<T>(&self, f: impl FnOnce(&mut FileTable) -> T) -> T {
+        self.with_current(|process| f(&mut process.files))
+    }
+
+    /// Calls `f` with the memory regions of the process running on this
+    /// core.
+    pub fn with_vmas<T>(&self, f: impl FnOnce(&mut VmaList) -> T) -> T {
+        self.with_current(|process| f(&mut process.vmas))
+    }
+
+    /// Makes signal `sig`, raised by a fault of the process running on this
+    /// core, pending. See `Signals::force()`.
+    pub fn force_signal(&self, sig: Signal) {
+        self.with_current(|process| process.signals.force(sig));
+    }
+
+    /// Sets the action of signal `sig` for the process running on this core,
+    /// and returns the previous one. See `Signals::set_action()`.
+    pub fn sigaction(&self, sig: Signal, action: Option<SigAction>) -> Result<SigAction, InvalidSignal> {
+        self.with_current(|process| match action {
+            Some(action) => process.signals.set_action(sig, action),
+            None => process.signals.action(sig),
+        })
+    }
+
+    /// Changes the blocked signals of the process running on this core, and
+    /// returns the previously blocked ones. See `Signals::update_blocked()`.
+    pub fn sigprocmask(&self, how: u64, set: SigSet) -> Result<SigSet, InvalidSignal> {
+        self.with_current(|process| process.signals.update_blocked(how, set))
+    }
 
-            // set the current executing SP to the new process's stack
-            // which contains the trap frame
-            core::arch::asm!(
-            "mov x0, {0}",
-            "mov sp, x0",
-            "bl context_restore",
-            "ldp     lr, x0, [SP], #0x10",
-            // reset SP back to the beginning
-            "adrp	x2, __cpu0_stack_end",
-            "add	x2, x2, #:lo12:__cpu0_stack_end",
-            "mov	sp, x2",
-            "eret",
-            in(reg) process1_tf_dst
-            );
+    /// Returns the process running on this core from its signal handler, by
+    /// restoring the trap frame saved on its stack into `tf`. See
+    /// `signal::sigreturn()`.
+    pub fn sigreturn(&self, tf: &mut TrapFrame) -> Result<(), InvalidSignal> {
+        self.with_current(|process| signal::sigreturn(process, tf))
+    }
+
+    /// Delivers the signals of the process returning to user space with the
+    /// trap frame `tf`. Handlers are entered by changing `tf`. A signal that
+    /// terminates the process switches to the next one, whose signals are
+    /// delivered in turn. See `Scheduler::deliver_signals()`.
+    pub fn deliver_signals(&self, tf: &mut TrapFrame) {
+        loop {
+            let Some(sig) = self
+                .0
+                .lock_irqsave()
+                .as_mut()
+                .and_then(|scheduler| scheduler.deliver_signals(tf))
+            else {
+                return;
+            };
+            self.exit(ExitStatus::Killed(sig), tf);
         }
     }
+
+    /// Calls `f` with the process running on this core.
+    pub fn with_current<T>(&self, f: impl FnOnce(&mut Process) -> T) -> T {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        let id = scheduler.current[cpu::cpu_id()].expect("no current process");
+        f(scheduler.processes.get_mut(id).expect("current process"))
+    }
+
+    /// Creates a child of the process running on this core, which is
+    /// executing with the trap frame `tf`. See `Process::fork()`. Returns the
+    /// child's ID, or `None` if no further processes can be scheduled.
+    pub fn fork(&self, tf: &TrapFrame) -> Option<Id> {
+        let id = self
+            .0
+            .lock_irqsave()
+            .as_mut()
+            .expect("scheduler uninitialized")
+            .fork(tf);
+        wake_idle_cores();
+        id
+    }
+
+    /// Replaces the program of the process running on this core, and restores
+    /// its new trap frame into `tf`. See `Process::exec()`.
+    pub fn exec(
+        &self,
+        bytes: &[u8],
+        argv: &[&str],
+        envp: &[&str],
+        tf: &mut TrapFrame,
+    ) -> Result<(), elf::Error> {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        let id = scheduler.current[cpu::cpu_id()].expect("no current process");
+        let process = scheduler.processes.get_mut(id).expect("current process");
+        process.exec(bytes, argv, envp)?;
+        *tf = *process.trap_frame;
+        // The registers hold the old program's FP/SIMD state.
+        process.fp.switch_in(id);
+        Ok(())
+    }
+
+    /// Reaps an exited child of the process running on this core: the child
+    /// `id`, or any child if `id` is `None`. For more details, see the
+    /// documentation on `Scheduler::wait()`.
+    ///
+    /// If `block` is set and the matching children are still running, the
+    /// result holds a waiter that is woken when one of them exits.
+    pub fn wait(&self, id: Option<Id>, block: bool) -> Wait {
+        let mut guard = self.0.lock_irqsave();
+        let scheduler = guard.as_mut().expect("scheduler uninitialized");
+        let parent = scheduler.current[cpu::cpu_id()].expect("no current process");
+        match scheduler.wait(parent, id) {
+            Wait::Running(None) if block => {
+                let parent = scheduler
+                    .processes
+                    .get_mut(parent)
+                    .expect("current process");
+                Wait::Running(Some(parent.child_exited.register()))
+            }
+            wait => wait,
+        }
+    }
+
+    /// Initializes the scheduler with the scheduling policy `policy`, adds the
+    /// first processes, and starts executing processes in user space using
+    /// timer interrupt based preemptive scheduling. This method should not
+    /// return under normal conditions.
+    #[cfg(not(test))]
+    pub fn start(&self, policy: Box<dyn SchedPolicy>) {
+        *self.0.lock_irqsave() = Some(Scheduler::new(policy));
+
+        match super::kthread::new("kinit", init) {
+            Some(init) => {
+                self.add(init);
+            }
+            None => crate::error!("failed to create kinit"),
+        }
+        match Process::load(super::programs::init(), &["init"], &[]) {
+            Ok(process) => {
+                crate::info!("loaded init: {:?}", process.image);
+                self.add(process);
+            }
+            Err(err) => crate::error!("failed to load init: {err}"),
+        }
+        for name in IPC_EXAMPLE {
+            let bytes = super::programs::find(name).expect("embedded program");
+            match Process::load(bytes, &[name], &[]) {
+                Ok(process) => {
+                    self.add(process);
+                }
+                Err(err) => crate::error!("failed to load {name}: {err}"),
+            }
+        }
+
+        self.run()
+    }
+
+    /// Starts executing processes in user space on a secondary core. Waits
+    /// until the scheduler has been started on core 0 and a process is ready.
+    /// This method should not return under normal conditions.
+    #[cfg(not(test))]
+    pub fn start_secondary(&self) {
+        self.run()
+    }
+
+    /// Enters this core's idle task, which switches to the first ready
+    /// process.
+    #[cfg(not(test))]
+    fn run(&self) -> ! {
+        if let Some(scheduler) = self.0.lock_irqsave().as_mut() {
+            scheduler.enter_idle(now());
+        }
+        unsafe { enter(&idle_frame()) }
+    }
 }
 
 #[derive(Debug)]
-struct Scheduler {
-    processes: VecDeque<Process>,
-    current: Option<Id>,
-    last_id: Option<Id>,
+pub(super) struct Scheduler {
+    /// Every process, including zombies that have not been reaped.
+    processes: ProcessTable,
+    /// The processes that are neither running nor zombies, from least to most
+    /// recently scheduled. Waiting processes are polled when picking the next
+    /// process.
+    run_queue: VecDeque<Id>,
+    /// The process running on each core.
+    current: [Option<Id>; CORES],
+    /// When the process running on each core was scheduled, in microseconds.
+    started: [u64; CORES],
+    /// The idle time of each core.
+    idle: [IdleStats; CORES],
+    /// Processes reaped on each core since it last took an exception. See
+    /// `Scheduler::reap_orphans()`.
+    retired: [Vec<Process>; CORES],
+    policy: Box<dyn SchedPolicy>,
+}
+
+/// How long a core has been idle: without a ready process to run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IdleStats {
+    /// When the core became idle, if it is idle.
+    pub since: Option<u64>,
+    /// The time the core spent idle, in microseconds, excluding the current
+    /// idle period.
+    pub total: u64,
+    /// How many times the core became idle and then left idle for a process.
+    pub wakeups: u64,
 }
 
 impl Scheduler {
-    /// Returns a new `Scheduler` with an empty queue.
-    fn new() -> Scheduler {
+    /// Returns a new `Scheduler` with no processes that picks processes with
+    /// `policy`.
+    pub(super) fn new(policy: Box<dyn SchedPolicy>) -> Scheduler {
         Self {
-            processes: alloc::collections::VecDeque::new(),
-            current: None,
-            last_id: None,
+            processes: ProcessTable::new(),
+            run_queue: VecDeque::new(),
+            current: [None; CORES],
+            started: [0; CORES],
+            idle: [IdleStats::default(); CORES],
+            retired: [const { Vec::new() }; CORES],
+            policy,
         }
     }
 
-    /// Adds a process to the scheduler's queue and returns that process's ID if
-    /// a new process can be scheduled. The process ID is newly allocated for
-    /// the process and saved in its `trap_frame`. If no further processes can
-    /// be scheduled, returns `None`.
+    /// Adds a process to the process table and the back of the run queue, and
+    /// returns that process's ID if a new process can be scheduled. The
+    /// process ID is newly allocated for the process and saved in its `id`.
+    /// If no further processes can be scheduled, returns `None`.
+    pub(super) fn add(&mut self, process: Process) -> Option<Id> {
+        let nice = process.nice;
+        let id = self.processes.insert(process)?;
+        self.policy.add(id, nice);
+        self.run_queue.push_back(id);
+        Some(id)
+    }
+
+    /// Returns `true` if process `id` is running on any core.
+    fn is_current(&self, id: Id) -> bool {
+        self.current.contains(&Some(id))
+    }
+
+    /// Sets the state of the process running on this core to `new_state`,
+    /// saves `tf` into it, and puts it at the back of the run queue. The time
+    /// since it was scheduled, up to `now`, is charged to it, `kernel`
+    /// microseconds of it as kernel time. The core no longer has a current
+    /// process. Does nothing if the core has no current process.
     ///
-    /// If this is the first process added, it is marked as the current process.
-    /// It is the caller's responsibility to ensure that the first time `switch`
-    /// is called, that process is executing on the CPU.
-    fn add(&mut self, mut process: Process) -> Option<Id> {
-        let next_id = self.last_id.unwrap_or(0) + 1;
-        process.trap_frame.tpidr = next_id;
-        self.processes.push_back(process);
-        self.last_id = Some(next_id);
-        self.last_id
-    }
-
-    /// Sets the current process's state to `new_state`, finds the next process
-    /// to switch to, and performs the context switch on `tf` by saving `tf`
-    /// into the current process and restoring the next process's trap frame
-    /// into `tf`. If there is no current process, returns `None`. Otherwise,
-    /// returns `Some` of the process ID that was context switched into `tf`.
+    /// The process's stack is checked for overflow. A process that overflowed
+    /// it is killed, and the overflow is returned.
     ///
-    /// This method blocks until there is a process to switch to, conserving
-    /// energy as much as possible in the interim.
-    fn switch(&mut self, new_state: State, tf: &mut TrapFrame) -> Option<Id> {
-        if self.processes.is_empty() {
-            return None;
-        }
+    /// If the process is now a zombie, either because `new_state` is
+    /// `State::Zombie` or because it was killed while it ran, its exit is
+    /// finished as described in `Scheduler::exited()`.
+    pub(super) fn save(
+        &mut self,
+        new_state: State,
+        tf: &TrapFrame,
+        now: u64,
+        kernel: u64,
+    ) -> Option<StackOverflow> {
+        let core = cpu::cpu_id();
+        let id = self.current[core].take()?;
+        let runtime = now.saturating_sub(self.started[core]);
+        self.policy.charge(id, runtime);
+        let current = self.processes.get_mut(id)?;
 
-        let mut current = self.processes.pop_front().unwrap();
-        current.state = new_state;
+        // Kernel threads run in the kernel all along.
+        let kernel = if current.is_kernel_thread() { runtime } else { kernel };
+        current.stats.charge(runtime, kernel);
+        current.fp.switch_out();
         *current.trap_frame = *tf;
+        let overflow = current.check_stacks(tf).err();
+        if overflow.is_some() {
+            current.state = State::Zombie(ExitStatus::Killed(SIGSEGV));
+        } else if !current.is_zombie() {
+            current.state = new_state;
+        }
 
-        self.processes.push_back(current);
+        if current.is_zombie() {
+            self.exited(id);
+        } else {
+            self.run_queue.push_back(id);
+        }
+        overflow
+    }
+
+    /// Adds a child of the process running on this core, which is executing
+    /// with the trap frame `tf`, and returns the child's ID.
+    fn fork(&mut self, tf: &TrapFrame) -> Option<Id> {
+        let id = self.current[cpu::cpu_id()]?;
+        let parent = self.processes.get_mut(id)?;
+        parent.fp.sync();
+        let child = parent.fork(tf)?;
+        self.add(child)
+    }
+
+    /// Kills the process `id`. If it is running on another core, its exit is
+    /// finished when that core switches it out. Returns `false` if there is
+    /// no such process.
+    fn kill(&mut self, id: Id) -> bool {
+        let Some(process) = self.processes.get_mut(id) else {
+            return false;
+        };
+        if process.is_zombie() {
+            return true;
+        }
+
+        process.state = State::Zombie(ExitStatus::Killed(SIGKILL));
+        if !self.is_current(id) {
+            self.run_queue.retain(|&queued| queued != id);
+            self.exited(id);
+        }
+        true
+    }
+
+    /// Sends signal `sig`, other than `SIGKILL`, to process `id`. Kernel
+    /// threads ignore signals. Returns `false` if there is no such process.
+    fn signal(&mut self, id: Id, sig: Signal) -> bool {
+        let Some(process) = self.processes.get_mut(id) else {
+            return false;
+        };
+        if !process.is_zombie() && !process.is_kernel_thread() {
+            process.signals.send(sig);
+        }
+        true
+    }
+
+    /// Takes the signals of the process running on this core, which returns
+    /// to user space with the trap frame `tf`, until one must be acted on:
+    /// handlers are entered by changing `tf`, and ignored signals are
+    /// discarded. Returns the signal that terminates the process, if any.
+    ///
+    /// A handler whose signal frame does not fit on the stack terminates the
+    /// process with `SIGSEGV`. Frames that return to EL1h take no signals.
+    fn deliver_signals(&mut self, tf: &mut TrapFrame) -> Option<Signal> {
+        if tf.is_kernel() {
+            return None;
+        }
 
+        let id = self.current[cpu::cpu_id()]?;
+        let process = self.processes.get_mut(id)?;
         loop {
-            for _ in 0..self.processes.len() {
-                let next = self.processes.front_mut().unwrap();
-                if next.is_ready() {
-                    next.state = State::Running;
-                    *tf = *next.trap_frame;
-                    let next_pid = Some(next.trap_frame.tpidr);
-                    self.current = next_pid;
-                    return next_pid;
-                } else {
-                    let next = self.processes.pop_front().unwrap();
-                    self.processes.push_back(next);
+            let blocked = process.signals.blocked();
+            match process.signals.take()? {
+                Delivery::Handle(sig, action) => {
+                    if signal::enter_handler(process, tf, sig, action, blocked).is_err() {
+                        return Some(SIGSEGV);
+                    }
                 }
+                Delivery::Terminate(sig) => return Some(sig),
+            }
+        }
+    }
+
+    /// Finishes the exit of the zombie process `id`, which is no longer
+    /// running: its files are closed, its memory is unmapped and the
+    /// IPC it waited for is abandoned, its children are orphaned, its parent
+    /// is woken and sent `SIGCHLD`, and it is reaped right away if it has no
+    /// parent to collect its exit status.
+    fn exited(&mut self, id: Id) {
+        if let Some(process) = self.processes.get_mut(id) {
+            process.files.clear();
+            process.transaction = None;
+            process.vmas.clear();
+        }
+        for process in self.processes.iter_mut() {
+            if process.parent == Some(id) {
+                process.parent = None;
+            }
+        }
+
+        let parent = self.processes.get(id).and_then(|process| process.parent);
+        if let Some(parent) = parent.and_then(|parent| self.processes.get_mut(parent)) {
+            parent.child_exited.wake_all();
+            if !parent.is_kernel_thread() {
+                parent.signals.send(SIGCHLD);
             }
-            unsafe {
-                core::arch::asm!("wfi");
+        }
+        self.reap_orphans();
+    }
+
+    /// Removes zombies that have no parent and are not running, freeing their
+    /// stacks, trap frames and images.
+    ///
+    /// The handler reaping a process may still be running on its stack:
+    /// exceptions from user code run on the process's kernel stack, and
+    /// exceptions from a kernel thread on the thread's own stack, and the
+    /// same handler may switch processes or reap more before it leaves that
+    /// stack. Reaped processes are kept until this core takes its next
+    /// exception, by which time it runs on another stack. See
+    /// `Scheduler::release_retired()`.
+    fn reap_orphans(&mut self) {
+        let orphans: Vec<Id> = self
+            .processes
+            .iter()
+            .filter(|process| {
+                process.is_zombie() && process.parent.is_none() && !self.is_current(process.id)
+            })
+            .map(|process| process.id)
+            .collect();
+
+        for id in orphans {
+            self.policy.remove(id);
+            if let Some(process) = self.processes.remove(id) {
+                self.retired[cpu::cpu_id()].push(process);
             }
         }
     }
+
+    /// Frees the processes this core reaped before it took the current
+    /// exception. The core runs on the stack of whatever it interrupted, which
+    /// is not a reaped process.
+    fn release_retired(&mut self) {
+        self.retired[cpu::cpu_id()].clear();
+    }
+
+    /// Reaps an exited child of process `parent`: the child `id`, or any child
+    /// if `id` is `None`. Returns `Wait::Running(None)` if the matching
+    /// children are all still running.
+    fn wait(&mut self, parent: Id, id: Option<Id>) -> Wait {
+        let is_match = |process: &Process| {
+            process.parent == Some(parent) && id.is_none_or(|id| process.id == id)
+        };
+        if !self.processes.iter().any(is_match) {
+            return Wait::NoChildren;
+        }
+
+        let exited = self
+            .processes
+            .iter()
+            .find(|process| {
+                is_match(process) && process.is_zombie() && !self.is_current(process.id)
+            })
+            .map(|process| process.id);
+        let Some(child) = exited.and_then(|id| self.processes.remove(id)) else {
+            return Wait::Running(None);
+        };
+        self.policy.remove(child.id);
+
+        match child.state {
+            State::Zombie(status) => Wait::Exited(child.id, status),
+            _ => unreachable!("reaped a live process"),
+        }
+    }
+
+    /// Sets the niceness of process `id` to `nice`, clamped to the valid
+    /// range. Returns the previous niceness, or `None` if there is no such
+    /// process.
+    fn set_nice(&mut self, id: Id, nice: i64) -> Option<Nice> {
+        let nice = nice.clamp(NICE_MIN as i64, NICE_MAX as i64) as Nice;
+        let process = self.processes.get_mut(id)?;
+        let previous = core::mem::replace(&mut process.nice, nice);
+        self.policy.set_nice(id, nice);
+        Some(previous)
+    }
+
+    /// Lets the policy pick the next ready process from the run queue, marks
+    /// it as running on this core since `now`, and restores its trap frame
+    /// into `tf`. The process leaves the run queue until it is saved. Returns
+    /// `Some` of the process ID that was context switched into `tf`, or `None`
+    /// if no process is ready.
+    fn schedule(&mut self, tf: &mut TrapFrame, now: u64) -> Option<Id> {
+        let processes = &mut self.processes;
+        let ready: Vec<Id> = self
+            .run_queue
+            .iter()
+            .copied()
+            .filter(|&id| processes.get_mut(id).is_some_and(Process::is_ready))
+            .collect();
+        if ready.is_empty() {
+            return None;
+        }
+
+        let next_id = self.policy.pick(&ready);
+        let index = self.run_queue.iter().position(|&id| id == next_id)?;
+        self.run_queue.remove(index);
+        let next = self.processes.get_mut(next_id)?;
+        next.state = State::Running;
+        next.stats.last_scheduled = now;
+        *tf = *next.trap_frame;
+        next.fp.switch_in(next_id);
+
+        let core = cpu::cpu_id();
+        self.current[core] = Some(next_id);
+        self.started[core] = now;
+        let idle = &mut self.idle[core];
+        if let Some(since) = idle.since.take() {
+            idle.total += now.saturating_sub(since);
+            idle.wakeups += 1;
+        }
+        Some(next_id)
+    }
+
+    /// Returns the top of the kernel stack of the process running on this
+    /// core, or `None` if it has none or the core is idle.
+    fn kernel_stack_top(&self) -> Option<u64> {
+        let id = self.current[cpu::cpu_id()]?;
+        let kernel_stack = self.processes.get(id)?.kernel_stack.as_ref()?;
+        Some(kernel_stack.top().as_u64())
+    }
+
+    /// Returns a snapshot of every process, ordered by ID.
+    fn snapshot(&self) -> Vec<ProcessInfo> {
+        let mut snapshot: Vec<_> = self.processes.iter().map(ProcessInfo::new).collect();
+        snapshot.sort_by_key(|info| info.id);
+        snapshot
+    }
+
+    /// Marks this core as idle since `now`, unless it already is.
+    fn enter_idle(&mut self, now: u64) {
+        self.idle[cpu::cpu_id()].since.get_or_insert(now);
+    }
+
+    /// Returns the idle time of this core.
+    fn idle_stats(&self) -> IdleStats {
+        self.idle[cpu::cpu_id()]
+    }
+
+    /// Like `schedule()`, but also returns how long the process may run.
+    pub(super) fn schedule_with_slice(
+        &mut self,
+        tf: &mut TrapFrame,
+        now: u64,
+    ) -> Option<(Id, u64)> {
+        let id = self.schedule(tf, now)?;
+        Some((id, self.policy.time_slice(id)))
+    }
 }
+
+#[cfg(test)]
+mod tests;
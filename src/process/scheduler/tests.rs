@@ -0,0 +1,597 @@
+use super::{IdleStats, Scheduler, Wait};
+use crate::process::policy::RoundRobin;
+use crate::process::signal::{self, SigAction, SIGCHLD, SIGKILL, SIGSEGV, SIGUSR1};
+use crate::process::stats::ProcessState;
+use crate::process::{kthread, ExitStatus, Id, Process, State};
+use crate::traps::TrapFrame;
+
+/// Returns a scheduler with a parent process and `children` children of it.
+fn family(children: usize) -> (Scheduler, Id, Vec<Id>) {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let parent = scheduler.add(Process::new().expect("process")).unwrap();
+    let children = (0..children)
+        .map(|_| {
+            let mut child = Process::new().expect("process");
+            child.parent = Some(parent);
+            scheduler.add(child).unwrap()
+        })
+        .collect();
+    (scheduler, parent, children)
+}
+
+/// Runs process `id` on this core and returns its trap frame.
+fn run(scheduler: &mut Scheduler, id: Id) -> TrapFrame {
+    let mut tf = TrapFrame::zeroed();
+    loop {
+        let scheduled = scheduler.schedule(&mut tf, 0).expect("a ready process");
+        if scheduled == id {
+            return tf;
+        }
+        scheduler.save(State::Ready, &tf, 0, 0);
+    }
+}
+
+/// Makes process `id` exit with `code`.
+fn exit(scheduler: &mut Scheduler, id: Id, code: i32) {
+    let tf = run(scheduler, id);
+    scheduler.save(State::Zombie(ExitStatus::Exited(code)), &tf, 0, 0);
+}
+
+fn ids(scheduler: &Scheduler) -> Vec<Id> {
+    let mut ids: Vec<_> = scheduler
+        .processes
+        .iter()
+        .map(|process| process.id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn state(scheduler: &mut Scheduler, id: Id) -> String {
+    format!(
+        "{:?}",
+        scheduler.processes.get_mut(id).expect("process").state
+    )
+}
+
+#[test]
+fn schedules_round_robin() {
+    let (mut scheduler, parent, children) = family(2);
+    let mut tf = TrapFrame::zeroed();
+    let mut order = Vec::new();
+    for _ in 0..6 {
+        order.push(scheduler.schedule(&mut tf, 0).unwrap());
+        scheduler.save(State::Ready, &tf, 0, 0);
+    }
+    let round = [parent, children[0], children[1]];
+    assert_eq!(order, [round, round].concat());
+}
+
+#[test]
+fn exit_makes_zombie_until_waited() {
+    let (mut scheduler, parent, children) = family(1);
+    exit(&mut scheduler, children[0], 3);
+    assert_eq!(
+        state(&mut scheduler, children[0]),
+        "State::Zombie(Exited(3))"
+    );
+    assert_eq!(ids(&scheduler), [parent, children[0]]);
+
+    match scheduler.wait(parent, None) {
+        Wait::Exited(id, status) => {
+            assert_eq!(id, children[0]);
+            assert_eq!(status, ExitStatus::Exited(3));
+            assert_eq!(status.to_wait_status(), 3 << 8);
+        }
+        wait => panic!("unexpected {wait:?}"),
+    }
+    assert_eq!(ids(&scheduler), [parent], "the child is reaped");
+    assert!(matches!(scheduler.wait(parent, None), Wait::NoChildren));
+}
+
+#[test]
+fn zombies_are_not_scheduled() {
+    let (mut scheduler, parent, children) = family(1);
+    exit(&mut scheduler, children[0], 0);
+
+    let mut tf = TrapFrame::zeroed();
+    for _ in 0..3 {
+        assert_eq!(scheduler.schedule(&mut tf, 0), Some(parent));
+        scheduler.save(State::Ready, &tf, 0, 0);
+    }
+}
+
+#[test]
+fn wait_for_running_children() {
+    let (mut scheduler, parent, children) = family(2);
+    assert!(matches!(scheduler.wait(parent, None), Wait::Running(None)));
+
+    exit(&mut scheduler, children[1], 0);
+    assert!(matches!(
+        scheduler.wait(parent, Some(children[0])),
+        Wait::Running(None)
+    ));
+    assert!(matches!(
+        scheduler.wait(parent, Some(children[1])),
+        Wait::Exited(id, _) if id == children[1]
+    ));
+    assert!(matches!(
+        scheduler.wait(parent, Some(children[1])),
+        Wait::NoChildren
+    ));
+    assert!(matches!(
+        scheduler.wait(children[0], None),
+        Wait::NoChildren
+    ));
+}
+
+#[test]
+fn exit_wakes_parent() {
+    let (mut scheduler, parent, children) = family(1);
+    let waiter = scheduler
+        .processes
+        .get_mut(parent)
+        .unwrap()
+        .child_exited
+        .register();
+    assert!(!waiter.is_woken());
+
+    exit(&mut scheduler, children[0], 0);
+    assert!(waiter.is_woken());
+}
+
+#[test]
+fn orphans_are_reaped() {
+    let (mut scheduler, parent, children) = family(2);
+    exit(&mut scheduler, children[0], 0);
+    exit(&mut scheduler, parent, 0);
+    assert_eq!(ids(&scheduler), [children[1]], "exited orphans are reaped");
+    assert_eq!(
+        scheduler.processes.get_mut(children[1]).unwrap().parent,
+        None
+    );
+
+    exit(&mut scheduler, children[1], 0);
+    assert!(scheduler.processes.is_empty());
+}
+
+#[test]
+fn kill() {
+    let (mut scheduler, parent, children) = family(1);
+    assert!(!scheduler.kill(100));
+    assert!(scheduler.kill(children[0]));
+    assert!(scheduler.kill(children[0]), "killing a zombie succeeds");
+    assert!(matches!(
+        scheduler.wait(parent, None),
+        Wait::Exited(id, ExitStatus::Killed(SIGKILL)) if id == children[0]
+    ));
+    assert_eq!(ExitStatus::Killed(SIGKILL).to_wait_status(), 9);
+}
+
+#[test]
+fn kill_running_process() {
+    let (mut scheduler, parent, children) = family(1);
+    let tf = run(&mut scheduler, children[0]);
+
+    // As if another core killed the child while it ran on this one.
+    assert!(scheduler.kill(children[0]));
+    assert!(
+        matches!(scheduler.wait(parent, None), Wait::Running(None)),
+        "a running zombie is not reaped"
+    );
+
+    scheduler.save(State::Ready, &tf, 0, 0);
+    assert_eq!(state(&mut scheduler, children[0]), "State::Zombie(Killed(9))");
+    assert!(matches!(
+        scheduler.wait(parent, None),
+        Wait::Exited(_, ExitStatus::Killed(SIGKILL))
+    ));
+}
+
+#[test]
+fn fork() {
+    let (mut scheduler, parent, _) = family(0);
+    let mut tf = run(&mut scheduler, parent);
+    let (bottom, top) = {
+        let process = scheduler.processes.get_mut(parent).unwrap();
+        process.stack.as_mut_slice()[..4].copy_from_slice(b"data");
+        (
+            process.stack.bottom().as_u64(),
+            process.stack.top().as_u64(),
+        )
+    };
+    tf.sp = top - 0x20;
+    tf.x0 = 1;
+    tf.x7 = 38;
+    tf.x19 = 0x1234;
+    tf.tpidr = 0x5678;
+
+    let child = scheduler.fork(&tf).expect("child");
+    let process = scheduler.processes.get_mut(child).unwrap();
+    assert_eq!(process.parent, Some(parent));
+    assert_eq!(process.id, child);
+    assert_eq!(
+        process.trap_frame.tpidr, 0x5678,
+        "the thread pointer is copied"
+    );
+    assert_eq!(process.trap_frame.x0, 0, "the child returns 0");
+    assert_eq!(process.trap_frame.x7, 0);
+    assert_eq!(process.trap_frame.x19, 0x1234);
+    assert_eq!(&process.stack.as_slice()[..4], b"data");
+    assert_ne!(process.stack.bottom().as_u64(), bottom);
+    assert_eq!(process.trap_frame.sp, process.stack.top().as_u64() - 0x20);
+
+    exit(&mut scheduler, child, 0);
+    assert!(matches!(
+        scheduler.wait(parent, None),
+        Wait::Exited(id, _) if id == child
+    ));
+}
+
+#[test]
+fn user_tpidr_does_not_change_identity() {
+    let (mut scheduler, parent, children) = family(1);
+    let mut tf = run(&mut scheduler, children[0]);
+    tf.tpidr = parent;
+    scheduler.save(State::Ready, &tf, 0, 0);
+
+    exit(&mut scheduler, children[0], 0);
+    assert!(matches!(
+        scheduler.wait(parent, Some(children[0])),
+        Wait::Exited(id, _) if id == children[0]
+    ));
+    assert_eq!(ids(&scheduler), [parent]);
+}
+
+#[test]
+fn run_queue_holds_processes_not_running() {
+    let (mut scheduler, parent, children) = family(2);
+    assert_eq!(scheduler.run_queue, [parent, children[0], children[1]]);
+
+    let tf = run(&mut scheduler, parent);
+    assert_eq!(scheduler.run_queue, [children[0], children[1]]);
+    scheduler.save(State::Ready, &tf, 0, 0);
+    assert_eq!(scheduler.run_queue, [children[0], children[1], parent]);
+
+    assert!(scheduler.kill(children[1]));
+    assert_eq!(scheduler.run_queue, [children[0], parent]);
+}
+
+#[test]
+fn idle_time_is_accounted() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let mut tf = TrapFrame::zeroed();
+    assert_eq!(scheduler.schedule(&mut tf, 0), None);
+    scheduler.enter_idle(10);
+    scheduler.enter_idle(20);
+    assert_eq!(scheduler.idle_stats().since, Some(10), "still the same idle period");
+
+    let id = scheduler.add(Process::new().expect("process")).unwrap();
+    assert_eq!(scheduler.schedule(&mut tf, 25), Some(id));
+    scheduler.save(State::Ready, &tf, 30, 0);
+    assert_eq!(scheduler.schedule(&mut tf, 40), Some(id), "not idle in between");
+    assert_eq!(
+        scheduler.idle_stats(),
+        IdleStats {
+            since: None,
+            total: 15,
+            wakeups: 1,
+        }
+    );
+}
+
+#[test]
+fn cpu_accounting() {
+    let (mut scheduler, parent, children) = family(1);
+    let mut tf = TrapFrame::zeroed();
+    assert_eq!(scheduler.schedule(&mut tf, 10), Some(parent));
+    scheduler.save(State::Waiting(Box::new(|_| true)), &tf, 40, 5);
+    assert_eq!(scheduler.schedule(&mut tf, 40), Some(children[0]));
+    scheduler.save(State::Ready, &tf, 50, 0);
+    assert_eq!(scheduler.schedule(&mut tf, 50), Some(parent));
+
+    let stats = scheduler.processes.get_mut(parent).unwrap().stats;
+    assert_eq!(stats.user_time, 25);
+    assert_eq!(stats.kernel_time, 5);
+    assert_eq!(stats.switches, 1);
+    assert_eq!(stats.wakeups, 1);
+    assert_eq!(stats.last_scheduled, 50);
+
+    let snapshot = scheduler.snapshot();
+    let ids: Vec<_> = snapshot.iter().map(|info| info.id).collect();
+    assert_eq!(ids, [parent, children[0]]);
+    assert_eq!(snapshot[0].state, ProcessState::Running);
+    assert_eq!(snapshot[1].parent, parent);
+    assert_eq!(snapshot[1].user_time, 10);
+}
+
+#[test]
+fn stack_overflow_kills() {
+    let (mut scheduler, parent, children) = family(1);
+    let mut tf = run(&mut scheduler, children[0]);
+    tf.sp = scheduler.processes.get_mut(children[0]).unwrap().stack.bottom().as_u64() - 8;
+
+    let overflow = scheduler.save(State::Ready, &tf, 0, 0).expect("overflow");
+    assert_eq!(overflow.id, children[0]);
+    assert_eq!(state(&mut scheduler, children[0]), "State::Zombie(Killed(11))");
+    assert!(matches!(
+        scheduler.wait(parent, None),
+        Wait::Exited(id, ExitStatus::Killed(SIGSEGV)) if id == children[0]
+    ));
+}
+
+fn kernel_work() {}
+
+#[test]
+fn kernel_and_user_threads_interleave() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let user = scheduler.add(Process::new().expect("process")).unwrap();
+    let kernel = scheduler.add(kthread::new("kworker", kernel_work).unwrap()).unwrap();
+
+    let mut tf = TrapFrame::zeroed();
+    let mut order = Vec::new();
+    for _ in 0..4 {
+        let id = scheduler.schedule(&mut tf, 0).unwrap();
+        assert_eq!(tf.is_kernel(), id == kernel, "process {id} runs at the wrong level");
+        order.push(id);
+        scheduler.save(State::Ready, &tf, 0, 0);
+    }
+    assert_eq!(order, [user, kernel, user, kernel]);
+
+    let stack = &scheduler.processes.get(kernel).unwrap().stack;
+    let sp = scheduler.processes.get(kernel).unwrap().trap_frame.sp;
+    assert_eq!(sp, stack.top().as_u64(), "kernel threads run on their own stack");
+}
+
+#[test]
+fn kernel_threads_run_in_the_kernel() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let kernel = scheduler.add(kthread::new("kworker", kernel_work).unwrap()).unwrap();
+    let mut tf = TrapFrame::zeroed();
+    assert_eq!(scheduler.schedule(&mut tf, 10), Some(kernel));
+    scheduler.save(State::Ready, &tf, 40, 5);
+
+    let stats = scheduler.processes.get(kernel).unwrap().stats;
+    assert_eq!(stats.user_time, 0);
+    assert_eq!(stats.kernel_time, 30);
+}
+
+#[test]
+fn exited_kernel_threads_outlive_the_switch() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let user = scheduler.add(Process::new().expect("process")).unwrap();
+    let kernel = scheduler.add(kthread::new("kworker", kernel_work).unwrap()).unwrap();
+
+    let tf = run(&mut scheduler, kernel);
+    scheduler.save(State::Zombie(ExitStatus::Exited(0)), &tf, 0, 0);
+    assert_eq!(ids(&scheduler), [user], "reaped");
+    assert_eq!(scheduler.retired[0].len(), 1, "the stack may still be in use");
+
+    // The next exception runs on another stack.
+    scheduler.release_retired();
+    assert!(scheduler.retired[0].is_empty());
+}
+
+#[test]
+fn exited_user_processes_outlive_a_preemption() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let orphan = scheduler.add(Process::new().expect("process")).unwrap();
+    let other = scheduler.add(Process::new().expect("process")).unwrap();
+
+    let tf = run(&mut scheduler, orphan);
+    scheduler.save(State::Zombie(ExitStatus::Exited(0)), &tf, 0, 0);
+    assert_eq!(ids(&scheduler), [other], "reaped");
+
+    // The same handler preempts the next process before leaving the stack.
+    let tf = run(&mut scheduler, other);
+    scheduler.save(State::Ready, &tf, 0, 0);
+    let retired = &scheduler.retired[0];
+    assert_eq!(retired.len(), 1, "the handler still runs on its kernel stack");
+    assert!(retired[0].kernel_stack.is_some());
+
+    scheduler.release_retired();
+    assert!(scheduler.retired[0].is_empty());
+}
+
+#[test]
+fn exits_in_one_handler_are_all_retired() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let first = scheduler.add(Process::new().expect("process")).unwrap();
+    let second = scheduler.add(Process::new().expect("process")).unwrap();
+    let survivor = scheduler.add(Process::new().expect("process")).unwrap();
+
+    // The first exits, and the handler then runs the second, which has a
+    // fatal signal pending and exits too.
+    let tf = run(&mut scheduler, first);
+    scheduler.save(State::Zombie(ExitStatus::Exited(0)), &tf, 0, 0);
+    let tf = run(&mut scheduler, second);
+    scheduler.save(State::Zombie(ExitStatus::Killed(SIGKILL)), &tf, 0, 0);
+    assert_eq!(ids(&scheduler), [survivor]);
+    assert_eq!(scheduler.retired[0].len(), 2, "the first stack is still in use");
+
+    scheduler.release_retired();
+    assert!(scheduler.retired[0].is_empty());
+}
+
+#[test]
+fn user_processes_trap_on_their_kernel_stack() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let user = scheduler.add(Process::new().expect("process")).unwrap();
+    let kernel = scheduler.add(kthread::new("kworker", kernel_work).unwrap()).unwrap();
+
+    let tf = run(&mut scheduler, user);
+    let kernel_stack = scheduler.processes.get(user).unwrap().kernel_stack.as_ref().unwrap();
+    let top = kernel_stack.top().as_u64();
+    assert_eq!(scheduler.kernel_stack_top(), Some(top));
+    let size = core::mem::size_of::<TrapFrame>() as u64;
+    assert_eq!(tf.restore_address(top), top - size);
+
+    scheduler.save(State::Ready, &tf, 0, 0);
+    run(&mut scheduler, kernel);
+    assert_eq!(scheduler.kernel_stack_top(), None, "kernel threads have no kernel stack");
+}
+
+#[test]
+fn processes_sleep_on_their_kernel_stack() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let user = scheduler.add(Process::new().expect("process")).unwrap();
+    let mut tf = run(&mut scheduler, user);
+
+    // A system call blocks: the process is switched out at EL1h, below the
+    // handler's frames on its kernel stack.
+    let top = scheduler.kernel_stack_top().unwrap();
+    let user_frame = tf;
+    tf.set_kernel_entry(0x8_0000, top - 0x800);
+    assert!(scheduler.save(State::Waiting(Box::new(|_| true)), &tf, 0, 0).is_none());
+
+    let resumed = run(&mut scheduler, user);
+    assert!(resumed.is_kernel());
+    assert_eq!(resumed.sp, top - 0x800);
+    assert_eq!(scheduler.kernel_stack_top(), Some(top));
+
+    // It overflows its kernel stack, not its user stack.
+    let process = scheduler.processes.get(user).unwrap();
+    let mut overflowed = resumed;
+    overflowed.sp = process.kernel_stack.as_ref().unwrap().bottom().as_u64() - 8;
+    assert!(process.check_stacks(&overflowed).is_err());
+    assert!(process.check_stacks(&user_frame).is_ok());
+}
+
+/// Handles signal `sig` in process `id` with a handler at `0x1000` that
+/// returns to `0x2000`.
+fn handle(scheduler: &mut Scheduler, id: Id, sig: signal::Signal) {
+    let action = SigAction {
+        handler: 0x1000,
+        restorer: 0x2000,
+        ..SigAction::default()
+    };
+    let process = scheduler.processes.get_mut(id).unwrap();
+    process.signals.set_action(sig, action).unwrap();
+}
+
+#[test]
+fn exit_sends_sigchld() {
+    let (mut scheduler, parent, children) = family(2);
+    exit(&mut scheduler, children[0], 0);
+    let signals = &scheduler.processes.get(parent).unwrap().signals;
+    assert_eq!(signals.pending(), 0, "SIGCHLD is ignored by default");
+
+    handle(&mut scheduler, parent, SIGCHLD);
+    exit(&mut scheduler, children[1], 0);
+    let signals = &scheduler.processes.get(parent).unwrap().signals;
+    assert_eq!(signals.pending(), signal::bit(SIGCHLD));
+}
+
+#[test]
+fn signals_are_delivered_on_the_way_to_user_space() {
+    let (mut scheduler, parent, _) = family(0);
+    let mut tf = run(&mut scheduler, parent);
+    tf.sp = scheduler.processes.get(parent).unwrap().stack.top().as_u64();
+    tf.elr = 0x3000;
+    assert_eq!(scheduler.deliver_signals(&mut tf), None);
+
+    handle(&mut scheduler, parent, SIGUSR1);
+    assert!(scheduler.signal(parent, SIGUSR1));
+    assert_eq!(scheduler.deliver_signals(&mut tf), None);
+    assert_eq!((tf.elr, tf.x0, tf.x30), (0x1000, SIGUSR1 as u64, 0x2000));
+
+    // The handler is entered once, and then the signal is blocked.
+    assert!(scheduler.signal(parent, SIGUSR1));
+    assert_eq!(scheduler.deliver_signals(&mut tf), None);
+    assert_eq!(tf.elr, 0x1000);
+
+    assert!(scheduler.signal(parent, SIGSEGV));
+    assert_eq!(scheduler.deliver_signals(&mut tf), Some(SIGSEGV));
+    assert!(!scheduler.signal(100, SIGUSR1));
+}
+
+#[test]
+fn signals_interrupt_sleeps() {
+    let (mut scheduler, parent, _) = family(0);
+    let mut tf = run(&mut scheduler, parent);
+    tf.set_kernel_entry(0x8_0000, scheduler.kernel_stack_top().unwrap() - 0x800);
+    scheduler.save(State::Waiting(Box::new(|_| false)), &tf, 0, 0);
+    assert_eq!(scheduler.schedule(&mut tf, 0), None);
+
+    handle(&mut scheduler, parent, SIGUSR1);
+    let signals = &mut scheduler.processes.get_mut(parent).unwrap().signals;
+    signals.set_blocked(signal::bit(SIGUSR1));
+    assert!(scheduler.signal(parent, SIGUSR1));
+    assert_eq!(scheduler.schedule(&mut tf, 0), None, "blocked signals do not wake");
+
+    let signals = &mut scheduler.processes.get_mut(parent).unwrap().signals;
+    signals.set_blocked(0);
+    assert_eq!(scheduler.schedule(&mut tf, 0), Some(parent));
+    assert_eq!(tf.x0, crate::process::Interrupted::BLOCK_RESULT);
+    assert_eq!(
+        scheduler.deliver_signals(&mut tf),
+        None,
+        "signals wait for the return to user space"
+    );
+}
+
+#[test]
+fn kernel_threads_ignore_signals() {
+    let mut scheduler = Scheduler::new(Box::new(RoundRobin::new(1_000)));
+    let kernel = scheduler.add(kthread::new("kworker", kernel_work).unwrap()).unwrap();
+    assert!(scheduler.signal(kernel, SIGSEGV));
+    let mut tf = run(&mut scheduler, kernel);
+    assert_eq!(scheduler.deliver_signals(&mut tf), None);
+    assert_eq!(scheduler.processes.get(kernel).unwrap().signals.pending(), 0);
+}
+
+#[test]
+fn exit_closes_files() {
+    let (mut scheduler, parent, children) = family(1);
+    let (reader, writer) = crate::file::pipe();
+    scheduler.processes.get_mut(parent).unwrap().files.insert(reader);
+    let child = scheduler.processes.get_mut(children[0]).unwrap();
+    child.files.insert(writer);
+    child.files.get(0).unwrap().write(b"bye").unwrap();
+
+    exit(&mut scheduler, children[0], 0);
+    let reader = scheduler.processes.get(parent).unwrap().files.get(0).unwrap();
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).unwrap(), 3);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0, "the zombie closed the write end");
+}
+
+#[test]
+fn exit_unmaps_memory() {
+    use crate::vm::{Protection, Vma};
+
+    let (mut scheduler, _, children) = family(1);
+    let child = scheduler.processes.get_mut(children[0]).unwrap();
+    let start = child.vmas.insert(Vma::anonymous(0x1000, Protection::READ).unwrap()).unwrap();
+    child.vmas.brk(0).unwrap();
+    let forked = child.fork(&child.trap_frame.clone()).unwrap();
+
+    exit(&mut scheduler, children[0], 0);
+    let zombie = scheduler.processes.get(children[0]).unwrap();
+    assert!(zombie.vmas.get(start).is_none());
+    assert!(zombie.vmas.heap().is_none());
+    assert!(forked.vmas.get(start).is_some(), "the fork keeps its regions");
+}
+
+#[test]
+fn user_memory_is_the_mapped_memory() {
+    use crate::vm::user::{UserMemory, UserSlice};
+    use crate::vm::{Error, Protection, Vma};
+
+    let mut process = Process::new().expect("process");
+    let start = process.vmas.insert(Vma::anonymous(0x2000, Protection::READ).unwrap()).unwrap();
+    let heap = process.vmas.brk(0).unwrap();
+    process.vmas.brk(heap + 0x100).unwrap();
+    let stack = process.stack.bottom().as_u64();
+
+    let check = |addr, len, prot| UserSlice::new(addr, len).check(&process, prot);
+    assert_eq!(check(start, 0x2000, Protection::READ), Ok(()));
+    assert_eq!(check(start, 0x2000, Protection::WRITE), Err(Error::Fault));
+    assert_eq!(check(start + 0x1000, 0x1001, Protection::READ), Err(Error::Fault));
+    assert_eq!(check(heap, 0x100, Protection::READ_WRITE), Ok(()));
+    assert_eq!(check(heap, 0x101, Protection::READ), Err(Error::Fault), "past the break");
+    assert_eq!(check(stack, 0x10, Protection::WRITE), Ok(()));
+    assert_eq!(check(0, 1, Protection::READ), Err(Error::Fault));
+    let kernel = kthread::new("kworker", kernel_work).unwrap();
+    assert_eq!(kernel.accessible_end(0, Protection::WRITE), Some(u64::MAX));
+}
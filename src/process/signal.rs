@@ -0,0 +1,353 @@
+//! POSIX-like signals.
+//!
+//! Each process has a set of pending signals, a mask of blocked signals and
+//! an action for each signal. Signals are delivered when the process returns
+//! to user space. A signal with a handler runs it on the process's stack: the
+//! interrupted trap frame and FP/SIMD registers are saved in a `SignalFrame`
+//! below the stack pointer, and the handler returns to the `restorer` given to `sigaction`, which makes
+//! the `sigreturn` system call to restore it. A signal without a handler takes
+//! its default action: it terminates the process, or is ignored.
+//!
+//! A process that sleeps waiting for an event is woken by a signal it does
+//! not block. A sleep in the kernel then ends with `Interrupted`.
+
+use core::mem::size_of;
+
+use super::fpu::FpState;
+use super::{Process, Stack};
+use crate::traps::TrapFrame;
+use crate::vm::user::UserPtr;
+
+/// A signal number, from 1 to `NSIG - 1`. The numbers match Linux's.
+pub type Signal = u32;
+
+pub const SIGILL: Signal = 4;
+pub const SIGTRAP: Signal = 5;
+pub const SIGBUS: Signal = 7;
+pub const SIGFPE: Signal = 8;
+pub const SIGKILL: Signal = 9;
+#[allow(dead_code)] // not used yet.
+pub const SIGUSR1: Signal = 10;
+pub const SIGSEGV: Signal = 11;
+#[cfg_attr(test, allow(dead_code))]
+pub const SIGPIPE: Signal = 13;
+#[allow(dead_code)] // not used yet.
+pub const SIGTERM: Signal = 15;
+pub const SIGCHLD: Signal = 17;
+pub const SIGURG: Signal = 23;
+pub const SIGWINCH: Signal = 28;
+
+/// One more than the highest signal number.
+pub const NSIG: usize = 32;
+
+/// A set of signals: bit `n` stands for signal `n`.
+pub type SigSet = u64;
+
+/// `SigAction::handler` value that takes the signal's default action.
+pub const SIG_DFL: u64 = 0;
+/// `SigAction::handler` value that ignores the signal.
+pub const SIG_IGN: u64 = 1;
+
+/// `SigAction::flags` bit: do not block the signal while its handler runs.
+pub const SA_NODEFER: u64 = 0x4000_0000;
+/// `SigAction::flags` bit: reset the action to the default once the handler
+/// is entered.
+pub const SA_RESETHAND: u64 = 0x8000_0000;
+
+/// `sigprocmask` operation: block the given signals too.
+pub const SIG_BLOCK: u64 = 0;
+/// `sigprocmask` operation: unblock the given signals.
+pub const SIG_UNBLOCK: u64 = 1;
+/// `sigprocmask` operation: block exactly the given signals.
+pub const SIG_SETMASK: u64 = 2;
+
+/// Returns the set holding only `sig`.
+pub const fn bit(sig: Signal) -> SigSet {
+    1 << sig
+}
+
+/// Signals that can be neither caught, blocked nor ignored.
+const UNCATCHABLE: SigSet = bit(SIGKILL);
+
+/// Every valid signal.
+const ALL: SigSet = !1 & (u64::MAX >> (64 - NSIG));
+
+/// Returns `true` if `sig` is a valid signal number.
+pub fn is_valid(sig: Signal) -> bool {
+    (1..NSIG as Signal).contains(&sig)
+}
+
+/// Returns `true` if the default action of `sig` is to ignore it, rather than
+/// to terminate the process.
+fn ignored_by_default(sig: Signal) -> bool {
+    matches!(sig, SIGCHLD | SIGURG | SIGWINCH)
+}
+
+/// What a process does when it receives a signal, as set with `sigaction`.
+/// The layout is part of the system call interface.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SigAction {
+    /// The address of the handler, or `SIG_DFL` or `SIG_IGN`. The handler is
+    /// called with the signal number in `x0`.
+    pub handler: u64,
+    /// `SA_*` flags.
+    pub flags: u64,
+    /// The address the handler returns to, which must make the `sigreturn`
+    /// system call. Required with a handler.
+    pub restorer: u64,
+    /// Signals blocked while the handler runs, besides the signal itself.
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    fn has_handler(&self) -> bool {
+        self.handler != SIG_DFL && self.handler != SIG_IGN
+    }
+}
+
+/// What to do with a signal taken for delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Run the signal's handler.
+    Handle(Signal, SigAction),
+    /// Terminate the process.
+    Terminate(Signal),
+}
+
+/// A request the signal state of a process refuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidSignal;
+
+/// The signal state of a process.
+#[derive(Debug, Clone)]
+pub struct Signals {
+    pending: SigSet,
+    blocked: SigSet,
+    actions: [SigAction; NSIG],
+}
+
+impl Default for Signals {
+    fn default() -> Self {
+        Signals {
+            pending: 0,
+            blocked: 0,
+            actions: [SigAction::default(); NSIG],
+        }
+    }
+}
+
+impl Signals {
+    /// Returns the signal state of a forked child: the same actions and
+    /// blocked signals, and nothing pending.
+    pub fn fork(&self) -> Signals {
+        Signals {
+            pending: 0,
+            ..self.clone()
+        }
+    }
+
+    /// Resets the handlers to the default action for a new program. Ignored
+    /// signals stay ignored, and pending and blocked signals are kept.
+    pub fn exec(&mut self) {
+        for action in self.actions.iter_mut().filter(|action| action.has_handler()) {
+            *action = SigAction::default();
+        }
+    }
+
+    /// Returns the pending signals.
+    #[allow(dead_code)] // not used yet.
+    pub fn pending(&self) -> SigSet {
+        self.pending
+    }
+
+    /// Returns the blocked signals.
+    pub fn blocked(&self) -> SigSet {
+        self.blocked
+    }
+
+    /// Blocks the signals in `mask`, except those that cannot be blocked.
+    pub fn set_blocked(&mut self, mask: SigSet) {
+        self.blocked = mask & ALL & !UNCATCHABLE;
+    }
+
+    /// Changes the blocked signals with the `sigprocmask` operation `how`,
+    /// and returns the previously blocked signals.
+    pub fn update_blocked(&mut self, how: u64, set: SigSet) -> Result<SigSet, InvalidSignal> {
+        let old = self.blocked;
+        match how {
+            SIG_BLOCK => self.set_blocked(old | set),
+            SIG_UNBLOCK => self.set_blocked(old & !set),
+            SIG_SETMASK => self.set_blocked(set),
+            _ => return Err(InvalidSignal),
+        }
+        Ok(old)
+    }
+
+    /// Returns the action of `sig`.
+    pub fn action(&self, sig: Signal) -> Result<SigAction, InvalidSignal> {
+        is_valid(sig)
+            .then(|| self.actions[sig as usize])
+            .ok_or(InvalidSignal)
+    }
+
+    /// Sets the action of `sig` and returns the previous one. A handler needs
+    /// a restorer, and `SIGKILL` keeps its default action. Setting a signal
+    /// to be ignored discards it if it is pending.
+    pub fn set_action(&mut self, sig: Signal, action: SigAction) -> Result<SigAction, InvalidSignal> {
+        let previous = self.action(sig)?;
+        if bit(sig) & UNCATCHABLE != 0 || (action.has_handler() && action.restorer == 0) {
+            return Err(InvalidSignal);
+        }
+
+        self.actions[sig as usize] = action;
+        if self.is_ignored(sig) {
+            self.pending &= !bit(sig);
+        }
+        Ok(previous)
+    }
+
+    /// Returns `true` if `sig` is ignored, either explicitly or by default.
+    fn is_ignored(&self, sig: Signal) -> bool {
+        match self.actions[sig as usize].handler {
+            SIG_IGN => true,
+            SIG_DFL => ignored_by_default(sig),
+            _ => false,
+        }
+    }
+
+    /// Makes `sig` pending, unless it is ignored.
+    pub fn send(&mut self, sig: Signal) {
+        if is_valid(sig) && !self.is_ignored(sig) {
+            self.pending |= bit(sig);
+        }
+    }
+
+    /// Makes `sig`, which was raised by a fault, pending. A fault cannot be
+    /// blocked or ignored: if it is, its action is reset to the default.
+    pub fn force(&mut self, sig: Signal) {
+        if self.blocked & bit(sig) != 0 || self.is_ignored(sig) {
+            self.blocked &= !bit(sig);
+            self.actions[sig as usize] = SigAction::default();
+        }
+        self.send(sig);
+    }
+
+    /// Returns `true` if a signal is pending that is not blocked.
+    pub fn is_deliverable(&self) -> bool {
+        self.pending & !self.blocked != 0
+    }
+
+    /// Takes the lowest pending signal that is not blocked and returns what to
+    /// do with it. Running a handler blocks the signal, unless `SA_NODEFER`
+    /// is set, and the handler's mask until `sigreturn`.
+    pub fn take(&mut self) -> Option<Delivery> {
+        loop {
+            let deliverable = self.pending & !self.blocked;
+            if deliverable == 0 {
+                return None;
+            }
+
+            let sig = deliverable.trailing_zeros() as Signal;
+            self.pending &= !bit(sig);
+            let action = self.actions[sig as usize];
+            match action.handler {
+                SIG_IGN => continue,
+                SIG_DFL if ignored_by_default(sig) => continue,
+                SIG_DFL => return Some(Delivery::Terminate(sig)),
+                _ => {
+                    let defer = if action.flags & SA_NODEFER == 0 { bit(sig) } else { 0 };
+                    self.set_blocked(self.blocked | action.mask | defer);
+                    if action.flags & SA_RESETHAND != 0 {
+                        self.actions[sig as usize] = SigAction::default();
+                    }
+                    return Some(Delivery::Handle(sig, action));
+                }
+            }
+        }
+    }
+}
+
+/// Marks a `SignalFrame` ("SIGFRAME").
+const FRAME_MAGIC: u64 = 0x454d_4152_4647_4953;
+
+/// What a handler's entry saves on the user stack, to be restored by
+/// `sigreturn`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalFrame {
+    magic: u64,
+    sig: u64,
+    /// The signals blocked before the handler was entered.
+    blocked: SigSet,
+    _reserved: u64,
+    /// The interrupted state of the process.
+    tf: TrapFrame,
+    /// The interrupted FP/SIMD registers of the process.
+    fp: FpState,
+}
+
+/// Returns `true` if `size` bytes at `addr` lie within `stack`.
+fn fits(stack: &Stack, addr: u64, size: usize) -> bool {
+    let (bottom, top) = (stack.bottom().as_u64(), stack.top().as_u64());
+    addr >= bottom && addr.checked_add(size as u64).is_some_and(|end| end <= top)
+}
+
+/// Enters the handler of `sig`, taken from the signals of `process`, which
+/// returns to user space with the trap frame `tf`. `blocked` are the signals
+/// blocked before the handler was taken. Returns `Err` if the signal frame
+/// does not fit on the process's stack.
+pub fn enter_handler(
+    process: &mut Process,
+    tf: &mut TrapFrame,
+    sig: Signal,
+    action: SigAction,
+    blocked: SigSet,
+) -> Result<(), InvalidSignal> {
+    let addr = tf.sp.wrapping_sub(size_of::<SignalFrame>() as u64) & !0xf;
+    if !fits(&process.stack, addr, size_of::<SignalFrame>()) {
+        return Err(InvalidSignal);
+    }
+
+    process.fp.sync();
+    let frame = SignalFrame {
+        magic: FRAME_MAGIC,
+        sig: sig as u64,
+        blocked,
+        _reserved: 0,
+        tf: *tf,
+        fp: *process.fp.state(),
+    };
+    UserPtr::new(addr).write(&*process, frame).map_err(|_| InvalidSignal)?;
+
+    tf.sp = addr;
+    tf.elr = action.handler;
+    tf.x0 = sig as u64;
+    tf.x30 = action.restorer;
+    Ok(())
+}
+
+/// Restores the state of `process` saved when it entered a signal handler,
+/// from the signal frame at the stack pointer of `tf`, into `tf`. Returns
+/// `Err` if there is no valid signal frame there.
+pub fn sigreturn(process: &mut Process, tf: &mut TrapFrame) -> Result<(), InvalidSignal> {
+    let addr = tf.sp;
+    if addr & 0xf != 0 || !fits(&process.stack, addr, size_of::<SignalFrame>()) {
+        return Err(InvalidSignal);
+    }
+
+    let frame: SignalFrame = UserPtr::new(addr).read(&*process).map_err(|_| InvalidSignal)?;
+    if frame.magic != FRAME_MAGIC {
+        return Err(InvalidSignal);
+    }
+
+    // The handler may have changed the frame: it must return to user space.
+    *tf = frame.tf;
+    tf.sanitize_user();
+    process.fp.restore(&frame.fp);
+    process.signals.set_blocked(frame.blocked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,194 @@
+use core::mem::size_of;
+
+use super::*;
+use crate::process::Process;
+
+/// Returns an action with a handler at `0x1000` that returns to `0x2000`.
+fn handler() -> SigAction {
+    SigAction {
+        handler: 0x1000,
+        restorer: 0x2000,
+        ..SigAction::default()
+    }
+}
+
+/// Returns an action that ignores the signal.
+fn ignore() -> SigAction {
+    SigAction {
+        handler: SIG_IGN,
+        ..SigAction::default()
+    }
+}
+
+#[test]
+fn default_actions() {
+    let mut signals = Signals::default();
+    signals.send(SIGCHLD);
+    signals.send(SIGWINCH);
+    assert_eq!(signals.pending(), 0, "ignored by default");
+    assert_eq!(signals.take(), None);
+
+    signals.send(SIGTERM);
+    signals.send(SIGUSR1);
+    assert!(signals.is_deliverable());
+    assert_eq!(signals.take(), Some(Delivery::Terminate(SIGUSR1)), "lowest first");
+    assert_eq!(signals.take(), Some(Delivery::Terminate(SIGTERM)));
+    assert_eq!(signals.take(), None);
+
+    signals.send(0);
+    signals.send(NSIG as Signal);
+    assert_eq!(signals.pending(), 0, "invalid signals are dropped");
+}
+
+#[test]
+fn ignoring_discards_pending_signals() {
+    let mut signals = Signals::default();
+    signals.send(SIGTERM);
+    assert_eq!(signals.set_action(SIGTERM, ignore()), Ok(SigAction::default()));
+    assert_eq!(signals.pending(), 0);
+    signals.send(SIGTERM);
+    assert_eq!(signals.take(), None);
+}
+
+#[test]
+fn blocked_signals_stay_pending() {
+    let mut signals = Signals::default();
+    assert_eq!(signals.update_blocked(SIG_BLOCK, bit(SIGTERM) | bit(SIGKILL)), Ok(0));
+    assert_eq!(signals.blocked(), bit(SIGTERM), "SIGKILL cannot be blocked");
+
+    signals.send(SIGTERM);
+    assert!(!signals.is_deliverable());
+    assert_eq!(signals.take(), None);
+
+    assert_eq!(signals.update_blocked(SIG_UNBLOCK, bit(SIGTERM)), Ok(bit(SIGTERM)));
+    assert_eq!(signals.take(), Some(Delivery::Terminate(SIGTERM)));
+    assert_eq!(signals.update_blocked(3, 0), Err(InvalidSignal));
+}
+
+#[test]
+fn sigkill_cannot_be_caught() {
+    let mut signals = Signals::default();
+    assert_eq!(signals.set_action(SIGKILL, handler()), Err(InvalidSignal));
+    assert_eq!(signals.set_action(SIGKILL, ignore()), Err(InvalidSignal));
+    assert_eq!(signals.set_action(0, handler()), Err(InvalidSignal));
+    let no_restorer = SigAction {
+        restorer: 0,
+        ..handler()
+    };
+    assert_eq!(signals.set_action(SIGUSR1, no_restorer), Err(InvalidSignal));
+}
+
+#[test]
+fn handlers_block_their_signal() {
+    let mut signals = Signals::default();
+    let action = SigAction {
+        mask: bit(SIGTERM),
+        ..handler()
+    };
+    signals.set_action(SIGUSR1, action).unwrap();
+    signals.send(SIGUSR1);
+    assert_eq!(signals.take(), Some(Delivery::Handle(SIGUSR1, action)));
+    assert_eq!(signals.blocked(), bit(SIGUSR1) | bit(SIGTERM));
+
+    let once = SigAction {
+        flags: SA_NODEFER | SA_RESETHAND,
+        ..handler()
+    };
+    signals.set_blocked(0);
+    signals.set_action(SIGUSR1, once).unwrap();
+    signals.send(SIGUSR1);
+    assert_eq!(signals.take(), Some(Delivery::Handle(SIGUSR1, once)));
+    assert_eq!(signals.blocked(), 0);
+    assert_eq!(signals.action(SIGUSR1), Ok(SigAction::default()));
+}
+
+#[test]
+fn faults_cannot_be_blocked_or_ignored() {
+    let mut signals = Signals::default();
+    signals.set_blocked(bit(SIGSEGV));
+    signals.force(SIGSEGV);
+    assert_eq!(signals.take(), Some(Delivery::Terminate(SIGSEGV)));
+
+    signals.set_action(SIGBUS, ignore()).unwrap();
+    signals.force(SIGBUS);
+    assert_eq!(signals.take(), Some(Delivery::Terminate(SIGBUS)));
+
+    signals.set_action(SIGILL, handler()).unwrap();
+    signals.force(SIGILL);
+    assert_eq!(signals.take(), Some(Delivery::Handle(SIGILL, handler())));
+}
+
+#[test]
+fn fork_and_exec() {
+    let mut signals = Signals::default();
+    signals.set_action(SIGUSR1, handler()).unwrap();
+    signals.set_action(SIGTERM, ignore()).unwrap();
+    signals.set_blocked(bit(SIGSEGV));
+    signals.send(SIGUSR1);
+
+    let mut child = signals.fork();
+    assert_eq!(child.pending(), 0, "pending signals are not inherited");
+    assert_eq!(child.blocked(), bit(SIGSEGV));
+    assert_eq!(child.action(SIGUSR1), Ok(handler()));
+
+    child.exec();
+    assert_eq!(child.action(SIGUSR1), Ok(SigAction::default()), "handlers reset");
+    assert_eq!(child.action(SIGTERM), Ok(ignore()), "ignored signals stay ignored");
+    assert_eq!(child.blocked(), bit(SIGSEGV));
+}
+
+#[test]
+fn handler_frames_round_trip() {
+    let mut process = Process::new().expect("process");
+    let top = process.stack.top().as_u64();
+    let mut tf = TrapFrame::zeroed();
+    tf.set_user_entry(0x8_0000, top - 8);
+    tf.x0 = 7;
+    tf.x19 = 19;
+    tf.x30 = 0x8_0040;
+
+    process.signals.set_action(SIGUSR1, handler()).unwrap();
+    process.signals.send(SIGUSR1);
+    let blocked = process.signals.blocked();
+    let Some(Delivery::Handle(sig, action)) = process.signals.take() else {
+        panic!("no handler");
+    };
+    enter_handler(&mut process, &mut tf, sig, action, blocked).unwrap();
+    assert_eq!((tf.elr, tf.x0, tf.x30), (0x1000, SIGUSR1 as u64, 0x2000));
+    assert_eq!(tf.sp % 16, 0);
+    assert!(tf.sp + size_of::<SignalFrame>() as u64 <= top - 8);
+    assert_eq!(process.signals.blocked(), bit(SIGUSR1));
+
+    // The handler tries to return to EL1h with IRQs masked, and changes the
+    // saved rounding mode.
+    let frame = tf.sp as *mut SignalFrame;
+    unsafe {
+        (*frame).tf.spsr = 0x3c5;
+        (*frame).fp.fpcr = 0b11 << 22;
+    }
+    tf.x19 = 0;
+    sigreturn(&mut process, &mut tf).unwrap();
+    assert_eq!(
+        (tf.elr, tf.sp, tf.x0, tf.x19, tf.x30),
+        (0x8_0000, top - 8, 7, 19, 0x8_0040)
+    );
+    assert!(!tf.is_kernel());
+    assert!(!tf.irqs_masked());
+    assert_eq!(process.signals.blocked(), 0);
+    assert_eq!(process.fp.state().fpcr, 0b11 << 22);
+
+    tf.sp = top - 0x1000;
+    assert_eq!(sigreturn(&mut process, &mut tf), Err(InvalidSignal), "no frame");
+}
+
+#[test]
+fn handler_frames_must_fit_the_stack() {
+    let mut process = Process::new().expect("process");
+    let mut tf = TrapFrame::zeroed();
+    tf.set_user_entry(0x8_0000, process.stack.bottom().as_u64() + 64);
+    assert_eq!(
+        enter_handler(&mut process, &mut tf, SIGUSR1, handler(), 0),
+        Err(InvalidSignal)
+    );
+    assert_eq!(tf.elr, 0x8_0000, "the frame is left unchanged");
+}
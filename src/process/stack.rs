@@ -1,62 +1,151 @@
-use core::alloc::GlobalAlloc;
 use core::fmt;
-use core::ptr::Unique;
+use core::ptr::NonNull;
 
+use super::Id;
 use crate::vm::PhysicalAddr;
-#[cfg(not(test))]
-use crate::ALLOCATOR;
 use alloc::alloc::Layout;
 
-/// A process stack. The default size is 1M1B with an alignment of 16 bytes.
+/// A process stack. The default size is 1MiB.
+///
+/// Below the stack is a guard region filled with a canary pattern. The stack
+/// grows down into the guard when it overflows, which `Stack::check()`
+/// detects. The stack itself starts out zeroed, so the deepest byte that is
+/// no longer zero marks its high-water mark.
+///
+/// Allocations are page-aligned and the guard is one page, so that it can be
+/// unmapped once the kernel has virtual memory.
 pub struct Stack {
-    ptr: Unique<[u8; Stack::SIZE]>,
+    /// The start of the allocation: the guard region.
+    ptr: NonNull<u8>,
+    /// The size of the stack, excluding the guard region.
+    size: usize,
 }
 
+// The stack is owned by its process, so it may move between cores with it.
+unsafe impl Send for Stack {}
+
 impl Stack {
     /// The default stack size is 1MiB.
-    pub const SIZE: usize = 1 << 20;
+    pub const DEFAULT_SIZE: usize = 1 << 20;
+
+    /// The size of the kernel stacks of user processes is 16KiB.
+    pub const KERNEL_SIZE: usize = 16 << 10;
+
+    /// The largest stack size is 64MiB.
+    pub const MAX_SIZE: usize = 64 << 20;
+
+    /// The size of the guard region below the stack, and the granularity of
+    /// stack sizes.
+    pub const GUARD_SIZE: usize = 0x1000;
+
+    /// The pattern the guard region is filled with.
+    const CANARY: [u8; 8] = *b"STKGUARD";
+
+    /// Returns a newly allocated, zeroed kernel stack for a user process.
+    /// Returns `None` if there is no memory.
+    pub fn kernel() -> Option<Self> {
+        Self::with_size(Self::KERNEL_SIZE)
+    }
 
-    /// The default stack alignment is 16 bytes.
-    pub const ALIGN: usize = 16;
+    /// Returns a newly allocated, zeroed process stack of at least `size`
+    /// bytes, rounded up to a multiple of `GUARD_SIZE`. Returns `None` if
+    /// `size` is `0` or larger than `MAX_SIZE`, or if there is no memory.
+    pub fn with_size(size: usize) -> Option<Self> {
+        if size == 0 || size > Self::MAX_SIZE {
+            return None;
+        }
+        let size = size.next_multiple_of(Self::GUARD_SIZE);
 
-    /// The default layout for a stack.
-    fn layout() -> Layout {
-        unsafe { Layout::from_size_align_unchecked(Self::SIZE, Self::ALIGN) }
+        let raw_ptr = unsafe { alloc::alloc::alloc(Self::layout(size)) };
+        let ptr = NonNull::new(raw_ptr)?;
+        let stack = Self { ptr, size };
+        unsafe {
+            for (i, byte) in (0..Self::GUARD_SIZE).zip(Self::CANARY.iter().cycle()) {
+                raw_ptr.add(i).write(*byte);
+            }
+            raw_ptr.add(Self::GUARD_SIZE).write_bytes(0, size);
+        }
+        Some(stack)
     }
 
-    /// Returns a newly allocated process stack, zeroed out, if one could be
-    /// successfully allocated. If there is no memory, or memory allocation
-    /// fails for some other reason, returns `None`.
-    pub fn new() -> Self {
-        let raw_ptr = unsafe {
-            let raw_ptr: *mut u8 = ALLOCATOR.alloc(Stack::layout());
-            raw_ptr.write_bytes(0, Self::SIZE);
-            raw_ptr
-        };
-
-        let ptr = Unique::new(raw_ptr as *mut _).expect("non-null");
-        Self { ptr }
+    /// The layout of a stack of `size` bytes and its guard region.
+    fn layout(size: usize) -> Layout {
+        Layout::from_size_align(Self::GUARD_SIZE + size, Self::GUARD_SIZE).expect("stack layout")
     }
 
-    /// Internal method to cast to a `*mut u8`.
+    /// Internal method to cast to a `*mut u8` at the bottom of the stack.
     unsafe fn as_mut_ptr(&self) -> *mut u8 {
-        self.ptr.as_ptr() as _
+        self.ptr.as_ptr().add(Self::GUARD_SIZE)
+    }
+
+    /// Returns the size of the stack in bytes, excluding the guard region.
+    pub fn size(&self) -> usize {
+        self.size
     }
 
     /// Returns the physical address of top of the stack.
     pub fn top(&self) -> PhysicalAddr {
-        unsafe { self.as_mut_ptr().add(Self::SIZE).into() }
+        unsafe { self.as_mut_ptr().add(self.size).into() }
+    }
+
+    /// Returns the stack's memory.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.as_mut_ptr(), self.size) }
+    }
+
+    /// Returns the stack's memory for writing.
+    #[cfg_attr(test, allow(dead_code))]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.size) }
     }
 
     /// Returns the physical address of bottom of the stack.
     pub fn bottom(&self) -> PhysicalAddr {
         unsafe { self.as_mut_ptr().into() }
     }
+
+    /// Returns the guard region below the stack.
+    fn guard(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), Self::GUARD_SIZE) }
+    }
+
+    /// Returns the most bytes of the stack that were ever in use: the distance
+    /// from the top to the deepest byte that is no longer zero.
+    pub fn high_water_mark(&self) -> usize {
+        self.as_slice()
+            .iter()
+            .position(|&byte| byte != 0)
+            .map_or(0, |deepest| self.size - deepest)
+    }
+
+    /// Checks that the process with ID `id` and the stack pointer `sp` has not
+    /// overflowed this stack: `sp` must not point into the guard region, and
+    /// the guard region must hold its canary pattern.
+    pub fn check(&self, id: Id, sp: u64) -> Result<(), StackOverflow> {
+        let bottom = self.bottom().as_u64();
+        let guard = bottom - Self::GUARD_SIZE as u64;
+        let sp_in_guard = (guard..bottom).contains(&sp);
+        let canary_intact = self
+            .guard()
+            .iter()
+            .zip(Self::CANARY.iter().cycle())
+            .all(|(byte, canary)| byte == canary);
+        if sp_in_guard || !canary_intact {
+            return Err(StackOverflow {
+                id,
+                sp,
+                bottom,
+                top: self.top().as_u64(),
+                canary_intact,
+            });
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Stack {
     fn drop(&mut self) {
-        unsafe { ALLOCATOR.dealloc(self.as_mut_ptr(), Self::layout()) }
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), Self::layout(self.size)) }
     }
 }
 
@@ -65,7 +154,35 @@ impl fmt::Debug for Stack {
         f.debug_struct("Stack")
             .field("top", &self.top())
             .field("bottom", &self.bottom())
-            .field("size", &Self::SIZE)
+            .field("size", &self.size)
             .finish()
     }
 }
+
+/// A stack overflow detected by `Stack::check()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackOverflow {
+    pub id: Id,
+    pub sp: u64,
+    pub bottom: u64,
+    pub top: u64,
+    /// Whether the guard region still held its canary pattern.
+    pub canary_intact: bool,
+}
+
+impl fmt::Display for StackOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stack overflow in pid {}: sp {:#x}, stack {:#x}..{:#x}",
+            self.id, self.sp, self.bottom, self.top
+        )?;
+        if !self.canary_intact {
+            write!(f, ", guard overwritten")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,53 @@
+use super::Stack;
+
+#[test]
+fn sizes() {
+    let stack = Stack::with_size(100).expect("stack");
+    assert_eq!(stack.size(), Stack::GUARD_SIZE);
+    assert_eq!(stack.top().as_u64() - stack.bottom().as_u64(), Stack::GUARD_SIZE as u64);
+    assert_eq!(stack.bottom().as_u64() % Stack::GUARD_SIZE as u64, 0);
+    assert!(stack.as_slice().iter().all(|&byte| byte == 0));
+
+    let stack = Stack::with_size(Stack::DEFAULT_SIZE).expect("stack");
+    assert_eq!(stack.size(), Stack::DEFAULT_SIZE);
+    assert!(Stack::with_size(0).is_none());
+    assert!(Stack::with_size(Stack::MAX_SIZE + 1).is_none());
+}
+
+#[test]
+fn high_water_mark() {
+    let mut stack = Stack::with_size(0x2000).expect("stack");
+    assert_eq!(stack.high_water_mark(), 0);
+    stack.as_mut_slice()[0x2000 - 0x10] = 1;
+    assert_eq!(stack.high_water_mark(), 0x10);
+    stack.as_mut_slice()[0x1000] = 1;
+    stack.as_mut_slice()[0x2000 - 0x10] = 0;
+    assert_eq!(stack.high_water_mark(), 0x1000, "the deepest use is kept");
+}
+
+#[test]
+fn detects_sp_in_guard() {
+    let stack = Stack::with_size(0x1000).expect("stack");
+    let bottom = stack.bottom().as_u64();
+    assert!(stack.check(1, bottom).is_ok());
+    assert!(stack.check(1, stack.top().as_u64()).is_ok());
+    assert!(stack.check(1, 0x10).is_ok(), "another stack");
+
+    let overflow = stack.check(7, bottom - 0x10).unwrap_err();
+    assert_eq!(overflow.id, 7);
+    assert!(overflow.canary_intact);
+    assert_eq!(
+        overflow.to_string(),
+        format!("stack overflow in pid 7: sp {:#x}, stack {bottom:#x}..{:#x}", bottom - 0x10, bottom + 0x1000)
+    );
+}
+
+#[test]
+fn detects_overwritten_canary() {
+    let stack = Stack::with_size(0x1000).expect("stack");
+    let bottom = stack.bottom().as_u64();
+    unsafe { (bottom as *mut u8).sub(0x800).write(0) };
+    let overflow = stack.check(2, bottom + 0x100).unwrap_err();
+    assert!(!overflow.canary_intact);
+    assert!(overflow.to_string().ends_with(", guard overwritten"));
+}
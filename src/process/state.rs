@@ -1,6 +1,7 @@
 use alloc::boxed::Box;
 use core::fmt;
 
+use super::signal::Signal;
 use super::Process;
 
 /// Type of a function used to determine if a process is ready to be scheduled
@@ -19,6 +20,42 @@ pub enum State {
     Waiting(EventPollFn),
     /// The process is currently running.
     Running,
+    /// The process has exited, but its parent has not yet collected its exit
+    /// status with `wait`.
+    Zombie(ExitStatus),
+}
+
+/// How a process ended.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ExitStatus {
+    /// The process called `exit` with this code.
+    Exited(i32),
+    /// The process was terminated by this signal.
+    Killed(Signal),
+}
+
+impl ExitStatus {
+    /// Returns the status encoded like Linux's `wait` status: the exit code in
+    /// bits 15:8 for a process that exited, or the terminating signal in bits
+    /// 6:0 for one that was killed.
+    pub fn to_wait_status(self) -> u64 {
+        match self {
+            ExitStatus::Exited(code) => ((code as u64) & 0xff) << 8,
+            ExitStatus::Killed(sig) => sig as u64 & 0x7f,
+        }
+    }
+}
+
+/// A sleep in the kernel that ended because the process received a signal
+/// rather than because the event it waited for occurred. See
+/// `process::block()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted;
+
+impl Interrupted {
+    /// The value of `x0` in the trap frame of a process sleeping in the
+    /// kernel that makes `process::block()` return `Err(Interrupted)`.
+    pub(crate) const BLOCK_RESULT: u64 = 1;
 }
 
 impl fmt::Debug for State {
@@ -27,6 +64,7 @@ impl fmt::Debug for State {
             State::Ready => write!(f, "State::Ready"),
             State::Running => write!(f, "State::Running"),
             State::Waiting(_) => write!(f, "State::Waiting"),
+            State::Zombie(status) => write!(f, "State::Zombie({status:?})"),
         }
     }
 }
@@ -0,0 +1,166 @@
+//! Per-process CPU accounting and process snapshots.
+//!
+//! The scheduler charges the time between scheduling a process and switching
+//! it out to that process. The part of it spent handling the process's
+//! exceptions is kernel time and the rest is user time. Exception handlers
+//! report their entry and exit with `enter_kernel()` and `leave_kernel()`;
+//! the kernel time is collected per core without taking the scheduler lock.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{Id, Process, State};
+use crate::cpu::{PerCore, CORES};
+
+/// Marks a core that is not handling an exception of a process in
+/// `KERNEL_ENTERED`.
+const NOT_IN_KERNEL: u64 = u64::MAX;
+
+/// When the exception being handled on each core was entered, or the part of
+/// it that has not been charged yet began.
+static KERNEL_ENTERED: PerCore<AtomicU64> =
+    PerCore::new([const { AtomicU64::new(NOT_IN_KERNEL) }; CORES]);
+
+/// The kernel time of each core that has not been charged to a process yet.
+static KERNEL_TIME: PerCore<AtomicU64> = PerCore::new([const { AtomicU64::new(0) }; CORES]);
+
+/// Records that this core entered the kernel at `now` to handle an exception
+/// of the running process.
+#[cfg_attr(test, allow(dead_code))]
+pub fn enter_kernel(now: u64) {
+    KERNEL_ENTERED.get().store(now, Ordering::Relaxed);
+}
+
+/// Records that this core leaves the kernel at `now`, returning from the
+/// exception entered with `enter_kernel()`.
+#[cfg_attr(test, allow(dead_code))]
+pub fn leave_kernel(now: u64) {
+    let entered = KERNEL_ENTERED.get().swap(NOT_IN_KERNEL, Ordering::Relaxed);
+    if entered != NOT_IN_KERNEL {
+        KERNEL_TIME
+            .get()
+            .fetch_add(now.saturating_sub(entered), Ordering::Relaxed);
+    }
+}
+
+/// Returns the kernel time of this core up to `now` that has not been charged
+/// yet. The rest of the exception being handled is charged to whichever
+/// process runs next.
+#[cfg_attr(test, allow(dead_code))]
+pub fn take_kernel_time(now: u64) -> u64 {
+    let entered = KERNEL_ENTERED.get();
+    let current = match entered.load(Ordering::Relaxed) {
+        NOT_IN_KERNEL => 0,
+        since => {
+            entered.store(now, Ordering::Relaxed);
+            now.saturating_sub(since)
+        }
+    };
+    KERNEL_TIME.get().swap(0, Ordering::Relaxed) + current
+}
+
+/// CPU accounting of a process. Times are in microseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CpuStats {
+    /// The time the process ran in user space.
+    pub user_time: u64,
+    /// The time the kernel spent handling the process's exceptions.
+    pub kernel_time: u64,
+    /// How many times the process was switched out.
+    pub switches: u64,
+    /// How many times the process became ready after waiting.
+    pub wakeups: u64,
+    /// When the process was last scheduled.
+    pub last_scheduled: u64,
+}
+
+impl CpuStats {
+    /// Charges `runtime` microseconds, of which `kernel` were spent in the
+    /// kernel, for a run that ended with the process being switched out.
+    pub fn charge(&mut self, runtime: u64, kernel: u64) {
+        let kernel = kernel.min(runtime);
+        self.user_time += runtime - kernel;
+        self.kernel_time += kernel;
+        self.switches += 1;
+    }
+}
+
+/// The state of a process in a `ProcessInfo`.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Ready = 0,
+    Running = 1,
+    Waiting = 2,
+    Zombie = 3,
+}
+
+impl From<&State> for ProcessState {
+    fn from(state: &State) -> ProcessState {
+        match state {
+            State::Ready => ProcessState::Ready,
+            State::Running => ProcessState::Running,
+            State::Waiting(_) => ProcessState::Waiting,
+            State::Zombie(_) => ProcessState::Zombie,
+        }
+    }
+}
+
+/// The longest process name in a `ProcessInfo`, including its NUL padding.
+pub const NAME_LEN: usize = 16;
+
+/// A snapshot of one process, as returned by the `ps` system call. The layout
+/// is part of the system call interface.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub id: Id,
+    /// The parent's ID, or `0` if the process has none.
+    pub parent: Id,
+    pub state: ProcessState,
+    pub nice: i32,
+    /// The process's name, truncated and padded with NULs.
+    pub name: [u8; NAME_LEN],
+    pub user_time: u64,
+    pub kernel_time: u64,
+    pub switches: u64,
+    pub wakeups: u64,
+    pub last_scheduled: u64,
+    /// The bytes of the stack in use when the process was last switched out.
+    pub stack_used: u64,
+    /// The most bytes of the stack the process ever used.
+    pub stack_peak: u64,
+    pub stack_size: u64,
+}
+
+impl ProcessInfo {
+    /// Returns a snapshot of `process`.
+    pub fn new(process: &Process) -> ProcessInfo {
+        let mut name = [0; NAME_LEN];
+        // Keep a NUL terminator.
+        let len = process.name.len().min(NAME_LEN - 1);
+        name[..len].copy_from_slice(&process.name.as_bytes()[..len]);
+
+        let (bottom, top) = (process.stack.bottom().as_u64(), process.stack.top().as_u64());
+        let sp = process.trap_frame.sp;
+        let stack_used = if (bottom..=top).contains(&sp) { top - sp } else { 0 };
+
+        ProcessInfo {
+            id: process.id,
+            parent: process.parent.unwrap_or(0),
+            state: ProcessState::from(&process.state),
+            nice: process.nice as i32,
+            name,
+            user_time: process.stats.user_time,
+            kernel_time: process.stats.kernel_time,
+            switches: process.stats.switches,
+            wakeups: process.stats.wakeups,
+            last_scheduled: process.stats.last_scheduled,
+            stack_used,
+            stack_peak: process.stack.high_water_mark() as u64,
+            stack_size: top - bottom,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
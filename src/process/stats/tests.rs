@@ -0,0 +1,47 @@
+use super::{enter_kernel, leave_kernel, take_kernel_time, CpuStats, ProcessInfo, ProcessState};
+use crate::process::{Process, State};
+
+#[test]
+fn kernel_time_is_split_at_switches() {
+    enter_kernel(10);
+    leave_kernel(15);
+    enter_kernel(20);
+    // A switch at 22 charges 5 + 2 to the outgoing process...
+    assert_eq!(take_kernel_time(22), 7);
+    // ...and the rest of the exception to the incoming one.
+    leave_kernel(30);
+    assert_eq!(take_kernel_time(30), 8);
+    assert_eq!(take_kernel_time(40), 0, "not in the kernel");
+    leave_kernel(50);
+    assert_eq!(take_kernel_time(50), 0, "left without entering");
+}
+
+#[test]
+fn charge_splits_runtime() {
+    let mut stats = CpuStats::default();
+    stats.charge(100, 30);
+    stats.charge(50, 80);
+    assert_eq!(stats.user_time, 70);
+    assert_eq!(stats.kernel_time, 80, "kernel time is capped at the runtime");
+    assert_eq!(stats.switches, 2);
+}
+
+#[test]
+fn process_info() {
+    let mut process = Process::new().expect("process");
+    process.id = 7;
+    process.parent = Some(3);
+    process.nice = -5;
+    process.name = "a-rather-long-process-name".into();
+    process.state = State::Waiting(Box::new(|_| false));
+    process.stats.user_time = 100;
+    process.trap_frame.sp = process.stack.top().as_u64() - 0x40;
+
+    let info = ProcessInfo::new(&process);
+    assert_eq!((info.id, info.parent, info.nice), (7, 3, -5));
+    assert_eq!(info.state, ProcessState::Waiting);
+    assert_eq!(&info.name, b"a-rather-long-p\0");
+    assert_eq!(info.user_time, 100);
+    assert_eq!(info.stack_used, 0x40);
+    assert_eq!(info.stack_size, process.stack.top().as_u64() - process.stack.bottom().as_u64());
+}
@@ -0,0 +1,117 @@
+use alloc::vec::Vec;
+
+use super::{Id, Process};
+
+/// The most processes that can exist at once, including zombies that have not
+/// been reaped.
+pub const MAX_PROCESSES: usize = 256;
+
+/// Process IDs are allocated in increasing order, starting at `1` and wrapping
+/// around before reaching `PID_MAX`, so an ID is only reused long after its
+/// process was reaped.
+pub const PID_MAX: Id = 32_768;
+
+/// The processes known to the scheduler, keyed by ID.
+///
+/// Each process lives in the slot given by its ID modulo the table's capacity,
+/// which makes lookups constant time. IDs whose slot is taken are skipped when
+/// allocating.
+#[derive(Debug)]
+pub struct ProcessTable {
+    slots: Vec<Option<Process>>,
+    len: usize,
+    /// The most recently allocated ID, or `0` if none was.
+    last_id: Id,
+}
+
+impl ProcessTable {
+    /// Returns an empty table with room for `MAX_PROCESSES` processes.
+    pub fn new() -> ProcessTable {
+        ProcessTable::with_capacity(MAX_PROCESSES)
+    }
+
+    /// Returns an empty table with room for `capacity` processes.
+    pub fn with_capacity(capacity: usize) -> ProcessTable {
+        assert!(capacity > 0 && capacity as Id <= PID_MAX);
+        ProcessTable {
+            slots: (0..capacity).map(|_| None).collect(),
+            len: 0,
+            last_id: 0,
+        }
+    }
+
+    /// Returns the number of processes in the table.
+    #[allow(dead_code)] // not used yet.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the table has no processes.
+    #[allow(dead_code)] // not used yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn slot(&self, id: Id) -> usize {
+        (id % self.slots.len() as Id) as usize
+    }
+
+    /// Allocates an ID for `process`, stores it in `process.id`, and adds the
+    /// process to the table. Returns the ID, or `None` if the table is full.
+    pub fn insert(&mut self, mut process: Process) -> Option<Id> {
+        if self.len == self.slots.len() {
+            return None;
+        }
+
+        // Consecutive IDs map to consecutive slots, so a free one turns up
+        // within one pass over the slots, plus one for skipping `0`.
+        let mut id = self.last_id;
+        for _ in 0..=self.slots.len() {
+            id = if id + 1 >= PID_MAX { 1 } else { id + 1 };
+            let slot = self.slot(id);
+            if self.slots[slot].is_none() {
+                process.id = id;
+                self.slots[slot] = Some(process);
+                self.len += 1;
+                self.last_id = id;
+                return Some(id);
+            }
+        }
+        unreachable!("no free slot in a table that is not full")
+    }
+
+    /// Returns the process with ID `id`.
+    pub fn get(&self, id: Id) -> Option<&Process> {
+        self.slots[self.slot(id)]
+            .as_ref()
+            .filter(|process| process.id == id)
+    }
+
+    /// Returns the process with ID `id`.
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut Process> {
+        let slot = self.slot(id);
+        self.slots[slot].as_mut().filter(|process| process.id == id)
+    }
+
+    /// Removes the process with ID `id` from the table and returns it. Its ID
+    /// can be allocated again.
+    pub fn remove(&mut self, id: Id) -> Option<Process> {
+        self.get(id)?;
+        let slot = self.slot(id);
+        self.len -= 1;
+        self.slots[slot].take()
+    }
+
+    /// Returns an iterator over the processes, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Process> {
+        self.slots.iter().flatten()
+    }
+
+    /// Returns an iterator over the processes, in no particular order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Process> {
+        self.slots.iter_mut().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests;
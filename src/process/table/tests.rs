@@ -0,0 +1,64 @@
+use super::{ProcessTable, PID_MAX};
+use crate::process::{Id, Process};
+
+fn ids(table: &ProcessTable) -> Vec<Id> {
+    let mut ids: Vec<_> = table.iter().map(|process| process.id).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn insert_allocates_increasing_ids() {
+    let mut table = ProcessTable::with_capacity(4);
+    assert_eq!(table.insert(Process::new().expect("process")), Some(1));
+    assert_eq!(table.insert(Process::new().expect("process")), Some(2));
+    assert_eq!(table.get(2).map(|process| process.id), Some(2));
+    assert_eq!(table.len(), 2);
+}
+
+#[test]
+fn lookup_checks_the_whole_id() {
+    let mut table = ProcessTable::with_capacity(4);
+    table.insert(Process::new().expect("process"));
+    assert!(table.get(1).is_some());
+    assert!(table.get(5).is_none(), "same slot, different ID");
+    assert!(table.get_mut(5).is_none());
+    assert!(table.remove(5).is_none());
+    assert_eq!(table.len(), 1);
+}
+
+#[test]
+fn removed_ids_are_not_reused_right_away() {
+    let mut table = ProcessTable::with_capacity(4);
+    table.insert(Process::new().expect("process"));
+    table.insert(Process::new().expect("process"));
+    assert_eq!(table.remove(1).map(|process| process.id), Some(1));
+    assert_eq!(table.insert(Process::new().expect("process")), Some(3));
+    assert_eq!(table.insert(Process::new().expect("process")), Some(4));
+    assert_eq!(table.insert(Process::new().expect("process")), Some(5), "slot of reaped ID 1");
+    assert_eq!(ids(&table), [2, 3, 4, 5]);
+}
+
+#[test]
+fn full_table() {
+    let mut table = ProcessTable::with_capacity(2);
+    table.insert(Process::new().expect("process"));
+    table.insert(Process::new().expect("process"));
+    assert_eq!(table.insert(Process::new().expect("process")), None);
+    table.remove(1);
+    assert_eq!(table.insert(Process::new().expect("process")), Some(3));
+}
+
+#[test]
+fn ids_wrap_around_skipping_live_processes() {
+    let mut table = ProcessTable::with_capacity(4);
+    table.insert(Process::new().expect("process"));
+    table.last_id = PID_MAX - 2;
+    assert_eq!(table.insert(Process::new().expect("process")), Some(PID_MAX - 1));
+    assert_eq!(
+        table.insert(Process::new().expect("process")),
+        Some(2),
+        "0 is never used, 1 is live"
+    );
+    assert_eq!(ids(&table), [1, 2, PID_MAX - 1]);
+}
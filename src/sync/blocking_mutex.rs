@@ -0,0 +1,116 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{WaitQueue, Waiter};
+#[cfg(not(test))]
+use crate::process::Interrupted;
+
+/// A mutual exclusion lock that puts processes to sleep instead of spinning.
+///
+/// The lock is meant to be held for the duration of one system call. A system
+/// call that cannot take it sleeps in `lock()` until the lock is released.
+pub struct BlockingMutex<T> {
+    data: UnsafeCell<T>,
+    locked: AtomicBool,
+    waiters: WaitQueue,
+}
+
+unsafe impl<T: Send> Send for BlockingMutex<T> {}
+unsafe impl<T: Send> Sync for BlockingMutex<T> {}
+
+pub struct BlockingMutexGuard<'a, T: 'a> {
+    lock: &'a BlockingMutex<T>,
+}
+
+#[allow(dead_code)] // not used yet.
+impl<T> BlockingMutex<T> {
+    pub fn new(val: T) -> BlockingMutex<T> {
+        BlockingMutex {
+            data: UnsafeCell::new(val),
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Takes the lock if it is free. Returns `None` if it is held.
+    pub fn try_lock(&self) -> Option<BlockingMutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        Some(BlockingMutexGuard { lock: self })
+    }
+
+    /// Takes the lock if it is free. Otherwise returns a `Waiter` that is
+    /// woken when the lock is released, after which the caller should try
+    /// again.
+    pub fn lock_or_wait(&self) -> Result<BlockingMutexGuard<'_, T>, Waiter> {
+        if let Some(guard) = self.try_lock() {
+            return Ok(guard);
+        }
+
+        let waiter = self.waiters.register();
+        match self.try_lock() {
+            Some(guard) => {
+                waiter.cancel();
+                Ok(guard)
+            }
+            None => Err(waiter),
+        }
+    }
+
+    /// Takes the lock, sleeping in the kernel on behalf of the process making
+    /// a system call until it is free. Returns `Err(Interrupted)` if a signal
+    /// arrives first. See `sync::sleep_on()`.
+    #[cfg(not(test))]
+    pub fn lock(&self) -> Result<BlockingMutexGuard<'_, T>, Interrupted> {
+        loop {
+            let waiter = match self.lock_or_wait() {
+                Ok(guard) => return Ok(guard),
+                Err(waiter) => waiter,
+            };
+            super::sleep_on(waiter)?;
+        }
+    }
+
+    fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+        self.waiters.wake_one();
+    }
+}
+
+impl<'a, T: 'a> Deref for BlockingMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> DerefMut for BlockingMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for BlockingMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.unlock()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BlockingMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f
+                .debug_struct("BlockingMutex")
+                .field("data", &&*guard)
+                .finish(),
+            None => f
+                .debug_struct("BlockingMutex")
+                .field("data", &"<locked>")
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Kernel synchronization primitives.
+//!
+//! `RwLock` and `Once`/`Lazy` spin like `mutex::Mutex` and are meant for short
+//! critical sections inside the kernel.
+//!
+//! `WaitQueue`, `Semaphore` and `BlockingMutex` are for system calls that need
+//! to wait on behalf of a process. When they cannot proceed they hand back a
+//! `Waiter` instead of spinning. The system call then gives the waiter to
+//! `sleep_on()`, which blocks the process on its kernel stack until the waiter
+//! is woken and then returns, so the system call carries on where it was.
+//!
+//! A signal can interrupt the sleep, even after the waiter was woken by
+//! `WaitQueue::wake_one()`. `sleep_on()` then `cancel()`s the waiter, which
+//! removes it from its queue or passes the wake-up on to the next waiter.
+
+mod blocking_mutex;
+mod once;
+mod rwlock;
+mod semaphore;
+mod wait_queue;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+
+use crate::process::state::EventPollFn;
+use crate::process::{Interrupted, Process};
+
+#[allow(unused_imports)] // not used yet.
+pub use self::{
+    blocking_mutex::{BlockingMutex, BlockingMutexGuard},
+    once::{Lazy, Once},
+    rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    semaphore::Semaphore,
+    wait_queue::{WaitQueue, Waiter},
+};
+
+/// Sleeps in the kernel until `waiter` is woken, on behalf of the process
+/// making a system call. Returns `Err(Interrupted)` if a signal woke the
+/// process first, after cancelling the waiter. See `process::block()`.
+#[cfg(not(test))]
+pub fn sleep_on(waiter: Waiter) -> Result<(), Interrupted> {
+    sleep_with(waiter, crate::process::block)
+}
+
+/// Sleeps until `waiter` is woken by handing `block` a poll function that
+/// checks it, and cancels the waiter if `block` is interrupted.
+fn sleep_with(
+    waiter: Waiter,
+    block: impl FnOnce(EventPollFn) -> Result<(), Interrupted>,
+) -> Result<(), Interrupted> {
+    let woken = waiter.clone();
+    let result = block(Box::new(move |_: &mut Process| woken.is_woken()));
+    if result.is_err() {
+        waiter.cancel();
+    }
+    result
+}
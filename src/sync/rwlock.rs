@@ -0,0 +1,127 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Set in `RwLock::state` while a writer holds the lock. The remaining bits
+/// count the readers.
+const WRITER: usize = 1;
+const READER: usize = 2;
+
+/// A spinning reader-writer lock. Any number of readers may hold the lock at
+/// once, or a single writer.
+pub struct RwLock<T> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+pub struct RwLockReadGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+}
+
+pub struct RwLockWriteGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+}
+
+#[allow(dead_code)] // not used yet.
+impl<T> RwLock<T> {
+    pub const fn new(val: T) -> RwLock<T> {
+        RwLock {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Takes a read lock if no writer holds the lock.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let state = self.state.fetch_add(READER, Ordering::Acquire);
+        if state & WRITER != 0 {
+            self.state.fetch_sub(READER, Ordering::Release);
+            return None;
+        }
+
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    /// Takes the write lock if no one holds the lock.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    /// Takes a read lock, spinning while a writer holds the lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        loop {
+            match self.try_read() {
+                Some(guard) => return guard,
+                None => core::hint::spin_loop(),
+            }
+        }
+    }
+
+    /// Takes the write lock, spinning while anyone holds the lock.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        loop {
+            match self.try_write() {
+                Some(guard) => return guard,
+                None => core::hint::spin_loop(),
+            }
+        }
+    }
+
+    /// Returns the number of readers holding the lock.
+    pub fn readers(&self) -> usize {
+        self.state.load(Ordering::Relaxed) / READER
+    }
+}
+
+impl<'a, T: 'a> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_sub(READER, Ordering::Release);
+    }
+}
+
+impl<'a, T: 'a> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: 'a> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => f.debug_struct("RwLock").field("data", &&*guard).finish(),
+            None => f
+                .debug_struct("RwLock")
+                .field("data", &"<locked>")
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{WaitQueue, Waiter};
+#[cfg(not(test))]
+use crate::process::Interrupted;
+
+/// A counting semaphore.
+#[derive(Debug)]
+pub struct Semaphore {
+    permits: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+#[allow(dead_code)] // not used yet.
+impl Semaphore {
+    /// Returns a new semaphore with `permits` permits available.
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: AtomicUsize::new(permits),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Takes a permit if one is available. Returns `true` if it did.
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |permits| {
+                permits.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Takes a permit if one is available. Otherwise returns a `Waiter` that
+    /// is woken when a permit is released, after which the caller should try
+    /// again.
+    pub fn acquire_or_wait(&self) -> Result<(), Waiter> {
+        if self.try_acquire() {
+            return Ok(());
+        }
+
+        let waiter = self.waiters.register();
+        if self.try_acquire() {
+            waiter.cancel();
+            Ok(())
+        } else {
+            Err(waiter)
+        }
+    }
+
+    /// Takes a permit, sleeping in the kernel on behalf of the process making a
+    /// system call until one is available. Returns `Err(Interrupted)` if a
+    /// signal arrives first. See `sync::sleep_on()`.
+    #[cfg(not(test))]
+    pub fn acquire(&self) -> Result<(), Interrupted> {
+        loop {
+            let waiter = match self.acquire_or_wait() {
+                Ok(()) => return Ok(()),
+                Err(waiter) => waiter,
+            };
+            super::sleep_on(waiter)?;
+        }
+    }
+
+    /// Gives back a permit and wakes one waiter.
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.waiters.wake_one();
+    }
+
+    /// Returns the number of permits available.
+    pub fn available(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+}
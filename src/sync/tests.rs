@@ -0,0 +1,227 @@
+mod rwlock {
+    use crate::sync::RwLock;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn readers_share_writer_excludes() {
+        let lock = RwLock::new(1);
+        let first = lock.read();
+        let second = lock.read();
+        assert_eq!(*first + *second, 2);
+        assert_eq!(lock.readers(), 2);
+        assert!(lock.try_write().is_none());
+
+        drop((first, second));
+        let mut writer = lock.try_write().expect("write lock");
+        *writer = 5;
+        assert!(lock.try_read().is_none());
+        assert!(lock.try_write().is_none());
+
+        drop(writer);
+        assert_eq!(*lock.read(), 5);
+        assert_eq!(lock.readers(), 0);
+    }
+
+    #[test]
+    fn writers_from_many_threads() {
+        let lock = Arc::new(RwLock::new(0usize));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        *lock.write() += 1;
+                        let _ = *lock.read();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*lock.read(), 4_000);
+    }
+}
+
+mod once {
+    use crate::sync::{Lazy, Once};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn initializes_once() {
+        let once = Once::new();
+        assert!(once.get().is_none());
+        assert_eq!(*once.call_once(|| 1), 1);
+        assert_eq!(*once.call_once(|| 2), 1);
+        assert_eq!(once.get(), Some(&1));
+        assert!(once.is_completed());
+    }
+
+    #[test]
+    fn racing_threads_run_init_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let once = Arc::new(Once::new());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let (calls, once) = (calls.clone(), once.clone());
+                thread::spawn(move || {
+                    *once.call_once(|| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        7
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 7);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn drops_value() {
+        let value = Arc::new(());
+        let once = Once::new();
+        once.call_once(|| value.clone());
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(once);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn lazy_derefs() {
+        static LAZY: Lazy<Vec<u8>> = Lazy::new(|| vec![1, 2, 3]);
+        assert_eq!(LAZY.len(), 3);
+        assert_eq!(*LAZY, [1, 2, 3]);
+    }
+}
+
+// `WaitQueue` is built on `mutex::Mutex`, which tracks its owner by core.
+// Every host thread looks like core 0, so these tests stay single-threaded.
+mod wait_queue {
+    use crate::process::{Interrupted, Process};
+    use crate::sync::{sleep_with, WaitQueue};
+
+    #[test]
+    fn wakes_in_order() {
+        let queue = WaitQueue::new();
+        let first = queue.register();
+        let second = queue.register();
+        assert_eq!(queue.len(), 2);
+
+        assert!(queue.wake_one());
+        assert!(first.is_woken());
+        assert!(!second.is_woken());
+        assert!(queue.wake_one());
+        assert!(second.is_woken());
+        assert!(!queue.wake_one());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn wake_all() {
+        let queue = WaitQueue::new();
+        let waiters: Vec<_> = (0..3).map(|_| queue.register()).collect();
+        assert_eq!(queue.wake_all(), 3);
+        assert!(waiters.iter().all(|waiter| waiter.is_woken()));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cancel_removes_waiter() {
+        let queue = WaitQueue::new();
+        let first = queue.register();
+        let second = queue.register();
+        first.cancel();
+        assert_eq!(queue.len(), 1);
+        queue.wake_one();
+        assert!(second.is_woken());
+    }
+
+    #[test]
+    fn cancel_passes_on_wakeup() {
+        let queue = WaitQueue::new();
+        let first = queue.register();
+        let second = queue.register();
+        queue.wake_one();
+        first.cancel();
+        assert!(second.is_woken());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn interrupted_sleep_cancels_waiter() {
+        let queue = WaitQueue::new();
+        let interrupted = queue.register();
+        let sleeper = queue.register();
+        assert_eq!(sleep_with(interrupted, |_| Err(Interrupted)), Err(Interrupted));
+        assert_eq!(queue.len(), 1);
+
+        assert!(queue.wake_one());
+        assert!(sleeper.is_woken(), "the wake-up went to the live sleeper");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn sleep_polls_waiter() {
+        let queue = WaitQueue::new();
+        let waiter = queue.register();
+        queue.wake_one();
+        let result = sleep_with(waiter, |mut poll| {
+            assert!(poll(&mut Process::new().expect("process")));
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+    }
+}
+
+mod semaphore {
+    use crate::sync::Semaphore;
+
+    #[test]
+    fn counts_permits() {
+        let semaphore = Semaphore::new(2);
+        assert!(semaphore.try_acquire());
+        assert!(semaphore.try_acquire());
+        assert!(!semaphore.try_acquire());
+        assert_eq!(semaphore.available(), 0);
+        semaphore.release();
+        assert_eq!(semaphore.available(), 1);
+        assert!(semaphore.try_acquire());
+    }
+
+    #[test]
+    fn release_wakes_waiter() {
+        let semaphore = Semaphore::new(1);
+        assert!(semaphore.acquire_or_wait().is_ok());
+        let waiter = semaphore.acquire_or_wait().unwrap_err();
+        assert!(!waiter.is_woken());
+
+        semaphore.release();
+        assert!(waiter.is_woken());
+        assert!(semaphore.acquire_or_wait().is_ok());
+    }
+}
+
+mod blocking_mutex {
+    use crate::sync::BlockingMutex;
+
+    #[test]
+    fn excludes_and_wakes() {
+        let mutex = BlockingMutex::new(0);
+        let mut guard = mutex.lock_or_wait().expect("lock");
+        *guard += 1;
+        assert!(mutex.try_lock().is_none());
+
+        let waiter = mutex.lock_or_wait().err().expect("waiter");
+        assert!(!waiter.is_woken());
+        drop(guard);
+        assert!(waiter.is_woken());
+        assert_eq!(*mutex.try_lock().expect("lock"), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::mutex::Mutex;
+
+/// The waiters of a `WaitQueue`, front first.
+type Waiters = Mutex<VecDeque<Waiter>>;
+
+/// A ticket that is woken by the `WaitQueue` it was registered with.
+#[derive(Debug, Clone)]
+pub struct Waiter {
+    woken: Arc<AtomicBool>,
+    /// The waiters of the queue the waiter was registered with.
+    queue: Weak<Waiters>,
+}
+
+impl Waiter {
+    /// Returns `true` once the waiter has been woken.
+    pub fn is_woken(&self) -> bool {
+        self.woken.load(Ordering::Acquire)
+    }
+
+    /// Removes the waiter from its queue. If it was already woken, the
+    /// wake-up is passed on to the next waiter so that it is not lost.
+    pub fn cancel(self) {
+        let Some(queue) = self.queue.upgrade() else {
+            return;
+        };
+        let mut waiters = queue.lock();
+        match waiters.iter().position(|queued| queued.is(&self)) {
+            Some(index) => {
+                waiters.remove(index);
+            }
+            None => {
+                if let Some(next) = waiters.pop_front() {
+                    next.wake();
+                }
+            }
+        }
+    }
+
+    fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+    }
+
+    fn is(&self, other: &Waiter) -> bool {
+        Arc::ptr_eq(&self.woken, &other.woken)
+    }
+}
+
+/// A first-in, first-out queue of waiters.
+///
+/// To wait for a condition without missing a wake-up, register a waiter
+/// first, then check the condition again. If it now holds, `cancel()` the
+/// waiter; otherwise wait for it to be woken.
+#[derive(Debug)]
+pub struct WaitQueue {
+    waiters: Arc<Waiters>,
+}
+
+#[allow(dead_code)] // not all of this is used yet.
+impl WaitQueue {
+    /// Returns a new, empty wait queue.
+    pub fn new() -> WaitQueue {
+        WaitQueue {
+            waiters: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Adds a new waiter to the back of the queue and returns it.
+    pub fn register(&self) -> Waiter {
+        let waiter = Waiter {
+            woken: Arc::new(AtomicBool::new(false)),
+            queue: Arc::downgrade(&self.waiters),
+        };
+        self.waiters.lock().push_back(waiter.clone());
+        waiter
+    }
+
+    /// Wakes the waiter at the front of the queue. Returns `true` if there
+    /// was one.
+    pub fn wake_one(&self) -> bool {
+        match self.waiters.lock().pop_front() {
+            Some(waiter) => {
+                waiter.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wakes every waiter in the queue and returns how many were woken.
+    pub fn wake_all(&self) -> usize {
+        let waiters = core::mem::take(&mut *self.waiters.lock());
+        for waiter in waiters.iter() {
+            waiter.wake();
+        }
+        waiters.len()
+    }
+
+    /// Returns the number of waiters in the queue.
+    pub fn len(&self) -> usize {
+        self.waiters.lock().len()
+    }
+
+    /// Returns `true` if no one is waiting.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,169 @@
+//! Kernel timers.
+//!
+//! Any number of one-shot and periodic software timers are multiplexed onto a
+//! single hardware timer, a `Clock`. The timers are kept in a `Wheel` and the
+//! clock is always armed for the earliest deadline. When it fires,
+//! `GlobalTimers::handle_irq()` runs the callbacks of every expired timer.
+
+mod wheel;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+pub use self::wheel::{Id, Wheel};
+use crate::mutex::Mutex;
+
+/// The length of a timer wheel tick in microseconds.
+const GRANULARITY: u64 = 1_000;
+
+/// The function called when a timer fires. Callbacks run in interrupt context
+/// and must not block.
+pub type Callback = Box<dyn FnMut() + Send>;
+
+/// A hardware timer that drives the kernel's software timers.
+pub trait Clock {
+    /// Returns the current time in microseconds.
+    fn now(&self) -> u64;
+
+    /// Requests an interrupt at `deadline`, replacing any earlier request. A
+    /// deadline beyond the range of the hardware may interrupt early. A
+    /// deadline in the past interrupts as soon as possible.
+    fn arm(&mut self, deadline: u64);
+
+    /// Acknowledges the interrupt so it is no longer pending.
+    fn acknowledge(&mut self);
+}
+
+/// The kernel's software timers for the entire machine.
+#[cfg_attr(test, allow(dead_code))]
+pub struct GlobalTimers(Mutex<Option<Timers>>);
+
+#[cfg_attr(test, allow(dead_code))]
+struct Timers {
+    wheel: Wheel<Callback>,
+    clock: Box<dyn Clock + Send>,
+    /// The timers whose callbacks are running.
+    firing: Vec<Id>,
+    /// The firing timers that were cancelled by a callback.
+    cancelled: Vec<Id>,
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl Timers {
+    /// Arms the clock for the earliest deadline.
+    fn rearm(&mut self) {
+        let deadline = self.wheel.next_deadline().unwrap_or(u64::MAX);
+        self.clock.arm(deadline);
+    }
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl GlobalTimers {
+    /// Returns an uninitialized set of timers.
+    ///
+    /// The timers must be initialized by calling `initialize()` before any
+    /// timer is added. Failure to do will result in panics.
+    pub const fn uninitialized() -> GlobalTimers {
+        GlobalTimers(Mutex::new(None))
+    }
+
+    /// Initializes the timers to be driven by `clock`.
+    pub fn initialize(&self, clock: impl Clock + Send + 'static) {
+        let now = clock.now();
+        let mut timers = Timers {
+            wheel: Wheel::new(GRANULARITY, now),
+            clock: Box::new(clock),
+            firing: Vec::new(),
+            cancelled: Vec::new(),
+        };
+        timers.rearm();
+        *self.0.lock_irqsave() = Some(timers);
+    }
+
+    /// Adds a timer that calls `callback` at `deadline` and then, if `period`
+    /// is `Some`, every `period` microseconds after that. Returns the timer's
+    /// `Id`, which can be used to cancel it.
+    pub fn add(&self, deadline: u64, period: Option<u64>, callback: Callback) -> Id {
+        let mut guard = self.0.lock_irqsave();
+        let timers = guard.as_mut().expect("timers uninitialized");
+        let id = timers.wheel.insert(deadline, period, callback);
+        timers.rearm();
+        id
+    }
+
+    /// Adds a one-shot timer that calls `callback` in `us` microseconds.
+    pub fn after(&self, us: u64, callback: Callback) -> Id {
+        let now = self.now();
+        self.add(now + us, None, callback)
+    }
+
+    /// Adds a periodic timer that calls `callback` every `us` microseconds.
+    #[allow(dead_code)] // not used yet.
+    pub fn every(&self, us: u64, callback: Callback) -> Id {
+        let now = self.now();
+        self.add(now + us, Some(us), callback)
+    }
+
+    /// Cancels the timer `id`. Returns `true` if the timer was pending or
+    /// firing, and `false` if it already fired or never existed.
+    pub fn cancel(&self, id: Id) -> bool {
+        let mut guard = self.0.lock_irqsave();
+        let timers = guard.as_mut().expect("timers uninitialized");
+        if timers.wheel.cancel(id).is_some() {
+            timers.rearm();
+            true
+        } else if timers.firing.contains(&id) {
+            timers.cancelled.push(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the current time of the clock in microseconds.
+    pub fn now(&self) -> u64 {
+        self.try_now().expect("timers uninitialized")
+    }
+
+    /// Returns the current time of the clock in microseconds, or `None` if
+    /// the timers are not initialized yet.
+    pub fn try_now(&self) -> Option<u64> {
+        self.0.lock_irqsave().as_ref().map(|timers| timers.clock.now())
+    }
+
+    /// Handles an interrupt from the clock: runs the callbacks of the expired
+    /// timers, reschedules the periodic ones, and arms the clock for the next
+    /// deadline.
+    ///
+    /// Callbacks run without the timers locked, so they can add or cancel
+    /// timers.
+    pub fn handle_irq(&self) {
+        let mut expired = {
+            let mut guard = self.0.lock_irqsave();
+            let timers = guard.as_mut().expect("timers uninitialized");
+            timers.clock.acknowledge();
+            let expired = timers.wheel.expire(timers.clock.now());
+            timers.firing = expired.iter().map(|entry| entry.id).collect();
+            expired
+        };
+
+        for entry in expired.iter_mut() {
+            (entry.data)();
+        }
+
+        let mut guard = self.0.lock_irqsave();
+        let timers = guard.as_mut().expect("timers uninitialized");
+        let now = timers.clock.now();
+        for entry in expired {
+            if !timers.cancelled.contains(&entry.id) {
+                timers.wheel.reinsert(entry, now);
+            }
+        }
+        timers.firing.clear();
+        timers.cancelled.clear();
+        timers.rearm();
+    }
+}
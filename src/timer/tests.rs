@@ -0,0 +1,102 @@
+mod wheel {
+    use crate::timer::Wheel;
+
+    const GRANULARITY: u64 = 1_000;
+
+    fn ids<T>(entries: &[crate::timer::wheel::Entry<T>]) -> Vec<u64> {
+        entries.iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn expires_in_deadline_order() {
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        let late = wheel.insert(5_500, None, ());
+        let early = wheel.insert(2_000, None, ());
+        let never = wheel.insert(9_000, None, ());
+        assert_eq!(wheel.len(), 3);
+        assert_eq!(wheel.next_deadline(), Some(2_000));
+
+        assert!(wheel.expire(1_999).is_empty());
+        assert_eq!(ids(&wheel.expire(6_000)), [early, late]);
+        assert_eq!(wheel.len(), 1);
+        assert_eq!(wheel.next_deadline(), Some(9_000));
+        assert_eq!(ids(&wheel.expire(9_000)), [never]);
+        assert!(wheel.is_empty());
+        assert_eq!(wheel.next_deadline(), None);
+    }
+
+    #[test]
+    fn same_tick_not_yet_due() {
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        let id = wheel.insert(1_700, None, ());
+        assert!(wheel.expire(1_500).is_empty());
+        assert_eq!(ids(&wheel.expire(1_700)), [id]);
+    }
+
+    #[test]
+    fn past_deadline_expires_next() {
+        let mut wheel = Wheel::new(GRANULARITY, 10_000);
+        let id = wheel.insert(3_000, None, ());
+        assert_eq!(wheel.next_deadline(), Some(3_000));
+        assert_eq!(ids(&wheel.expire(10_000)), [id]);
+    }
+
+    #[test]
+    fn beyond_one_rotation() {
+        let rotation = GRANULARITY * Wheel::<()>::SLOTS as u64;
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        let far = wheel.insert(3 * rotation + 500, None, ());
+        let near = wheel.insert(rotation + 400, None, ());
+        assert_eq!(wheel.next_deadline(), Some(rotation + 400));
+
+        assert!(wheel.expire(500).is_empty());
+        assert!(wheel.expire(rotation).is_empty());
+        assert_eq!(ids(&wheel.expire(2 * rotation)), [near]);
+        assert_eq!(wheel.next_deadline(), Some(3 * rotation + 500));
+        assert!(wheel.expire(3 * rotation).is_empty());
+        assert_eq!(ids(&wheel.expire(10 * rotation)), [far]);
+    }
+
+    #[test]
+    fn periodic_reinsert() {
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        let id = wheel.insert(1_000, Some(1_000), ());
+
+        let expired = wheel.expire(1_200);
+        assert_eq!(ids(&expired), [id]);
+        for entry in expired {
+            wheel.reinsert(entry, 1_200);
+        }
+        assert_eq!(wheel.next_deadline(), Some(2_000));
+
+        // Missed periods are skipped rather than fired in a burst.
+        let expired = wheel.expire(5_300);
+        assert_eq!(ids(&expired), [id]);
+        for entry in expired {
+            wheel.reinsert(entry, 5_300);
+        }
+        assert_eq!(wheel.next_deadline(), Some(6_000));
+    }
+
+    #[test]
+    fn one_shot_reinsert_is_dropped() {
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        wheel.insert(1_000, None, ());
+        for entry in wheel.expire(1_000) {
+            wheel.reinsert(entry, 1_000);
+        }
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn cancel() {
+        let mut wheel = Wheel::new(GRANULARITY, 0);
+        let a = wheel.insert(1_000, None, 'a');
+        let b = wheel.insert(2_000, None, 'b');
+        assert_eq!(wheel.cancel(a), Some('a'));
+        assert_eq!(wheel.cancel(a), None);
+        assert_eq!(wheel.len(), 1);
+        assert_eq!(wheel.next_deadline(), Some(2_000));
+        assert_eq!(ids(&wheel.expire(5_000)), [b]);
+    }
+}
@@ -0,0 +1,172 @@
+use alloc::vec::Vec;
+
+/// Identifies a timer.
+pub type Id = u64;
+
+/// A timer in a `Wheel`.
+#[derive(Debug)]
+pub struct Entry<T> {
+    /// The timer's identifier.
+    pub id: Id,
+    /// The time, in microseconds, at which the timer fires.
+    pub deadline: u64,
+    /// The interval between firings for a periodic timer. `None` for a
+    /// one-shot timer.
+    pub period: Option<u64>,
+    /// The data associated with the timer.
+    pub data: T,
+}
+
+impl<T> Entry<T> {
+    /// Returns the first deadline after `now` of a periodic timer that fired
+    /// at `now`, skipping any periods that were missed. Returns `None` for a
+    /// one-shot timer.
+    fn next_deadline(&self, now: u64) -> Option<u64> {
+        let period = self.period?.max(1);
+        let next = self.deadline + period;
+        if next > now {
+            Some(next)
+        } else {
+            Some(now + period - (now - self.deadline) % period)
+        }
+    }
+}
+
+/// A hashed timing wheel.
+///
+/// Time is divided into ticks of `granularity` microseconds. A timer is kept
+/// in the slot of the tick its deadline falls in, modulo the number of slots,
+/// so expiring timers only visits the slots of the ticks that have passed.
+/// Timers further away than one rotation share slots with nearer ones and
+/// stay put until their deadline passes.
+#[derive(Debug)]
+pub struct Wheel<T> {
+    slots: Vec<Vec<Entry<T>>>,
+    granularity: u64,
+    /// The tick at which timers were last expired.
+    tick: u64,
+    last_id: Id,
+    len: usize,
+}
+
+impl<T> Wheel<T> {
+    /// The number of slots in the wheel.
+    pub const SLOTS: usize = 64;
+
+    /// Returns a new, empty wheel with ticks of `granularity` microseconds,
+    /// starting at the time `now`.
+    pub fn new(granularity: u64, now: u64) -> Wheel<T> {
+        let granularity = granularity.max(1);
+        Wheel {
+            slots: (0..Self::SLOTS).map(|_| Vec::new()).collect(),
+            granularity,
+            tick: now / granularity,
+            last_id: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of timers in the wheel.
+    #[allow(dead_code)] // only used by tests.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no timers in the wheel.
+    #[allow(dead_code)] // only used by tests.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the slot index for tick `tick`.
+    fn slot(tick: u64) -> usize {
+        (tick % Self::SLOTS as u64) as usize
+    }
+
+    /// Places `entry` in the slot for its deadline. Deadlines that have
+    /// already passed go in the current slot.
+    fn place(&mut self, entry: Entry<T>) {
+        let tick = (entry.deadline / self.granularity).max(self.tick);
+        self.slots[Self::slot(tick)].push(entry);
+        self.len += 1;
+    }
+
+    /// Adds a timer that fires at `deadline` and then, if `period` is `Some`,
+    /// every `period` microseconds after that. Returns the new timer's `Id`.
+    pub fn insert(&mut self, deadline: u64, period: Option<u64>, data: T) -> Id {
+        self.last_id += 1;
+        let id = self.last_id;
+        self.place(Entry {
+            id,
+            deadline,
+            period,
+            data,
+        });
+        id
+    }
+
+    /// Adds a periodic timer returned from `expire()` back into the wheel at
+    /// its next deadline after `now`. One-shot timers are dropped.
+    pub fn reinsert(&mut self, mut entry: Entry<T>, now: u64) {
+        if let Some(deadline) = entry.next_deadline(now) {
+            entry.deadline = deadline;
+            self.place(entry);
+        }
+    }
+
+    /// Removes the timer `id` and returns its data, if it is in the wheel.
+    pub fn cancel(&mut self, id: Id) -> Option<T> {
+        for slot in self.slots.iter_mut() {
+            if let Some(index) = slot.iter().position(|entry| entry.id == id) {
+                self.len -= 1;
+                return Some(slot.swap_remove(index).data);
+            }
+        }
+        None
+    }
+
+    /// Removes and returns every timer whose deadline is at or before `now`,
+    /// ordered by deadline. Periodic timers must be given back with
+    /// `reinsert()` to fire again.
+    pub fn expire(&mut self, now: u64) -> Vec<Entry<T>> {
+        let now_tick = now / self.granularity;
+        let ticks = (now_tick.saturating_sub(self.tick) + 1).min(Self::SLOTS as u64);
+
+        let mut expired = Vec::new();
+        for tick in self.tick..self.tick + ticks {
+            let slot = &mut self.slots[Self::slot(tick)];
+            let mut index = 0;
+            while index < slot.len() {
+                if slot[index].deadline <= now {
+                    expired.push(slot.swap_remove(index));
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        self.tick = self.tick.max(now_tick);
+        self.len -= expired.len();
+        expired.sort_by_key(|entry| entry.deadline);
+        expired
+    }
+
+    /// Returns the earliest deadline of all timers in the wheel, if any.
+    pub fn next_deadline(&self) -> Option<u64> {
+        // Within one rotation, the first slot holding a timer for its own
+        // tick holds the earliest deadline.
+        for tick in self.tick..self.tick + Self::SLOTS as u64 {
+            let earliest = self.slots[Self::slot(tick)]
+                .iter()
+                .map(|entry| entry.deadline)
+                .filter(|&deadline| deadline / self.granularity <= tick)
+                .min();
+            if earliest.is_some() {
+                return earliest;
+            }
+        }
+
+        // Every timer is more than one rotation away.
+        self.slots.iter().flatten().map(|entry| entry.deadline).min()
+    }
+}
@@ -1,8 +1,23 @@
 use crate::hw::interrupt::Interrupt;
+use crate::hw::local_interrupt::LocalInterrupt;
 
 use crate::traps::TrapFrame;
+use crate::TIMERS;
 
-#[allow(dead_code)] // not used yet.
-pub(crate) fn handle_irq(_interrupt: Interrupt, _tf: &mut TrapFrame) {
-    unimplemented!("handle_irq()")
+/// Handles the pending interrupt `interrupt` from the BCM2835 interrupt
+/// controller that was taken while `tf` was executing.
+pub(crate) fn handle_irq(interrupt: Interrupt, _tf: &mut TrapFrame) {
+    match interrupt {
+        Interrupt::Timer1 => TIMERS.get().handle_irq(),
+        _ => crate::warn!("unhandled interrupt {}", interrupt as u32),
+    }
+}
+
+/// Handles the pending per-core interrupt `interrupt` from the local interrupt
+/// controller that was taken while `tf` was executing.
+pub(crate) fn handle_local_irq(interrupt: LocalInterrupt, _tf: &mut TrapFrame) {
+    match interrupt {
+        LocalInterrupt::CntPns => TIMERS.get().handle_irq(),
+        _ => crate::warn!("unhandled local interrupt {interrupt:?}"),
+    }
 }
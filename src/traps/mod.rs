@@ -1,10 +1,19 @@
+#[cfg(not(test))]
 mod irq;
 mod syndrome;
-mod syscall;
+#[cfg(not(test))]
+pub(crate) mod syscall;
 mod trap_frame;
 
-use crate::{process::State, SCHEDULER};
+#[cfg(not(test))]
+use crate::hw::interrupt::{Controller, Interrupt};
+#[cfg(not(test))]
+use crate::hw::local_interrupt::{LocalController, LocalInterrupt};
+#[cfg(not(test))]
+use crate::{process, process::ExitStatus, process::State, SCHEDULER, TIMERS};
 
+#[cfg_attr(test, allow(unused_imports))]
+pub use self::syndrome::Syndrome;
 pub use self::trap_frame::TrapFrame;
 
 #[repr(u16)]
@@ -29,24 +38,101 @@ pub enum Source {
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(test, allow(dead_code))]
 pub struct Info {
     source: Source,
     kind: Kind,
 }
 
+/// Returns `true` if the exception was taken from a process: a user process
+/// at EL0, or a kernel thread, which runs at EL1h with IRQs unmasked unlike
+/// exception handlers. The idle task is not a process.
+#[cfg(not(test))]
+fn in_process(info: Info, tf: &TrapFrame) -> bool {
+    match info.source {
+        Source::LowerAArch64 => true,
+        Source::CurrentSpElx => !tf.irqs_masked() && SCHEDULER.current().is_some(),
+        _ => false,
+    }
+}
+
 /// This function is called when an exception occurs. The `info` parameter
 /// specifies the source and kind of exception that has occurred. The `esr` is
 /// the value of the exception syndrome register. Finally, `tf` is a pointer to
 /// the trap frame for the exception.
+///
+/// Returns the address of the trap frame to restore, which is where the frame
+/// of the process that runs next belongs. See `TrapFrame::restore_address()`.
+#[cfg(not(test))]
 #[no_mangle]
-pub extern "C" fn handle_exception(_info: Info, _esr: u32, tf: &mut TrapFrame) {
-    crate::kprintln!("handle_exception enter");
-    let controller = crate::hw::interrupt::Controller::new();
-    if controller.is_pending(crate::hw::interrupt::Interrupt::Timer1) {
-        crate::kprintln!("Timer1 interrupt pending. Setting new tick.");
-        let mut timer = crate::hw::timer::Timer::new();
-        timer.tick_in(crate::TICK);
+pub extern "C" fn handle_exception(info: Info, esr: u32, tf: &mut TrapFrame) -> *mut TrapFrame {
+    crate::trace!("handle_exception enter {info:?}");
+    // Processes reaped by the previous exception on this core: its handler
+    // has left their stacks by now.
+    SCHEDULER.release_retired();
+    match info.source {
+        Source::LowerAArch64 => process::stats::enter_kernel(TIMERS.get().now()),
+        // Kernel threads, the idle task and system calls run on SP_EL1, which
+        // the exception was taken on: the frame ends where they left off.
+        Source::CurrentSpElx => {
+            tf.sp = (tf as *mut TrapFrame).wrapping_add(1) as u64;
+        }
+        _ => {}
+    }
+
+    match info.kind {
+        Kind::Synchronous => match Syndrome::from(esr) {
+            Syndrome::Svc(num) => syscall::handle_syscall(num, tf),
+            // A process's first FP/SIMD instruction since it was scheduled.
+            // It is retried once the registers are loaded.
+            Syndrome::SimdFp if info.source == Source::LowerAArch64 => SCHEDULER.load_fp(),
+            // User processes get a signal, delivered below. Kernel threads
+            // take no signals and are killed.
+            syndrome if info.source == Source::LowerAArch64 => {
+                crate::debug!(
+                    "process {:?}: {syndrome:?} at {:#x}",
+                    SCHEDULER.current(),
+                    tf.elr
+                );
+                SCHEDULER.force_signal(syndrome.signal());
+            }
+            syndrome if in_process(info, tf) => {
+                crate::error!(
+                    "killing kernel thread {:?}: {syndrome:?} at {:#x}",
+                    SCHEDULER.current(),
+                    tf.elr
+                );
+                SCHEDULER.exit(ExitStatus::Killed(syndrome.signal()), tf);
+            }
+            syndrome => crate::error!("unhandled {syndrome:?} at {:#x}", tf.elr),
+        },
+        Kind::Irq => {
+            let core = crate::cpu::cpu_id();
+            let local_controller = LocalController::new();
+            if local_controller.is_pending(core, LocalInterrupt::CntPns) {
+                irq::handle_local_irq(LocalInterrupt::CntPns, tf);
+            }
+
+            if local_controller.is_pending(core, LocalInterrupt::Gpu) {
+                let controller = Controller::new();
+                for interrupt in Interrupt::ALL {
+                    if controller.is_pending(interrupt) {
+                        crate::trace!("interrupt {} pending", interrupt as u32);
+                        irq::handle_irq(interrupt, tf);
+                    }
+                }
+            }
+        }
+        Kind::Fiq | Kind::SError => crate::error!("unhandled {info:?}"),
+    }
+
+    if process::take_preemption_request() {
         let _scheduled_pid = SCHEDULER.switch(State::Ready, tf);
     }
-    crate::kprintln!("handle_exception exit");
+    if !tf.is_kernel() {
+        SCHEDULER.deliver_signals(tf);
+    }
+    process::stats::leave_kernel(TIMERS.get().now());
+    crate::trace!("handle_exception exit");
+    unsafe { TrapFrame::relocate(tf) }
 }
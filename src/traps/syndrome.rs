@@ -1,3 +1,5 @@
+use crate::process::signal::{Signal, SIGBUS, SIGFPE, SIGILL, SIGSEGV, SIGTRAP};
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 #[allow(dead_code)] // not used yet
 pub enum Fault {
@@ -10,9 +12,22 @@ pub enum Fault {
     Other(u8),
 }
 
+/// Converts the fault status code (bits 5:0 of an abort's ISS) into a `Fault`
+/// (ref: D17.2.37).
 impl From<u32> for Fault {
-    fn from(_val: u32) -> Fault {
-        unimplemented!("From<u32> for Fault")
+    fn from(val: u32) -> Fault {
+        let status = val & 0b11_1111;
+        match status >> 2 {
+            0b0000 => Fault::AddressSize,
+            0b0001 => Fault::Translation,
+            0b0010 => Fault::AccessFlag,
+            0b0011 => Fault::Permission,
+            _ => match status {
+                0b10_0001 => Fault::Alignment,
+                0b11_0000 => Fault::TlbConflict,
+                other => Fault::Other(other as u8),
+            },
+        }
     }
 }
 
@@ -45,9 +60,66 @@ pub enum Syndrome {
     Other(u32),
 }
 
+impl Syndrome {
+    /// Returns the signal a process that takes this exception receives.
+    #[cfg_attr(test, allow(dead_code))]
+    pub fn signal(self) -> Signal {
+        match self {
+            Syndrome::InstructionAbort { kind: Fault::Alignment, .. }
+            | Syndrome::DataAbort { kind: Fault::Alignment, .. }
+            | Syndrome::PCAlignmentFault
+            | Syndrome::SpAlignmentFault => SIGBUS,
+            Syndrome::InstructionAbort { .. } | Syndrome::DataAbort { .. } => SIGSEGV,
+            Syndrome::Breakpoint | Syndrome::Step | Syndrome::Watchpoint | Syndrome::Brk(_) => {
+                SIGTRAP
+            }
+            Syndrome::TrappedFpu => SIGFPE,
+            _ => SIGILL,
+        }
+    }
+}
+
 /// Converts a raw syndrome value (ESR) into a `Syndrome` (ref: D1.10.4).
 impl From<u32> for Syndrome {
-    fn from(_esr: u32) -> Syndrome {
-        unimplemented!("From<u32> for Syndrome")
+    fn from(esr: u32) -> Syndrome {
+        // Exception Class (EC) is bits 31:26. The Instruction Specific
+        // Syndrome (ISS) is bits 24:0.
+        let class = esr >> 26;
+        let iss = esr & 0x1FF_FFFF;
+        let imm16 = (iss & 0xFFFF) as u16;
+        let abort = || (Fault::from(iss), (iss & 0b11) as u8);
+
+        match class {
+            0b00_0000 => Syndrome::Unknown,
+            0b00_0001 => Syndrome::WfiWfe,
+            0b00_0011 | 0b00_0101 => Syndrome::McrMrc,
+            0b00_0100 => Syndrome::McrrMrrc,
+            0b00_0110 => Syndrome::LdcStc,
+            0b00_0111 => Syndrome::SimdFp,
+            0b00_1000 => Syndrome::Vmrs,
+            0b00_1100 => Syndrome::Mrrc,
+            0b00_1110 => Syndrome::IllegalExecutionState,
+            0b01_0001 | 0b01_0101 => Syndrome::Svc(imm16),
+            0b01_0010 | 0b01_0110 => Syndrome::Hvc(imm16),
+            0b01_0011 | 0b01_0111 => Syndrome::Smc(imm16),
+            0b01_1000 => Syndrome::MsrMrsSystem,
+            0b10_0000 | 0b10_0001 => {
+                let (kind, level) = abort();
+                Syndrome::InstructionAbort { kind, level }
+            }
+            0b10_0010 => Syndrome::PCAlignmentFault,
+            0b10_0100 | 0b10_0101 => {
+                let (kind, level) = abort();
+                Syndrome::DataAbort { kind, level }
+            }
+            0b10_0110 => Syndrome::SpAlignmentFault,
+            0b10_1000 | 0b10_1100 => Syndrome::TrappedFpu,
+            0b10_1111 => Syndrome::SError,
+            0b11_0000 | 0b11_0001 => Syndrome::Breakpoint,
+            0b11_0010 | 0b11_0011 => Syndrome::Step,
+            0b11_0100 | 0b11_0101 => Syndrome::Watchpoint,
+            0b11_1100 => Syndrome::Brk(imm16),
+            _ => Syndrome::Other(esr),
+        }
     }
 }
@@ -1,4 +1,222 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::elf;
+use crate::file::{self, Fd, File};
+use crate::ipc::{self, Message, Parcel, Transaction};
+use crate::process::signal::{self, SigAction};
+use crate::process::state::EventPollFn;
+use crate::process::stats::ProcessInfo;
+use crate::process::{self, programs, ExitStatus, Id, Process, State, Wait};
 use crate::traps::TrapFrame;
+use crate::vm::shm::SharedMemory;
+use crate::vm::user::{self, UserPtr, UserSlice};
+use crate::vm::{self, Backing, Protection, Vma};
+use crate::{SCHEDULER, TIMERS};
+
+/// System call number of `sleep`.
+pub(crate) const SYS_SLEEP: u16 = 1;
+/// System call number of `dmesg`.
+pub(crate) const SYS_DMESG: u16 = 2;
+/// System call number of `exit`.
+pub(crate) const SYS_EXIT: u16 = 3;
+/// System call number of `wait`.
+pub(crate) const SYS_WAIT: u16 = 4;
+/// System call number of `kill`.
+pub(crate) const SYS_KILL: u16 = 5;
+/// System call number of `fork`.
+pub(crate) const SYS_FORK: u16 = 6;
+/// System call number of `exec`.
+pub(crate) const SYS_EXEC: u16 = 7;
+/// System call number of `nice`.
+pub(crate) const SYS_NICE: u16 = 8;
+/// System call number of `getpid`.
+pub(crate) const SYS_GETPID: u16 = 9;
+/// System call number of `ps`.
+pub(crate) const SYS_PS: u16 = 10;
+/// System call number of `sigaction`.
+pub(crate) const SYS_SIGACTION: u16 = 11;
+/// System call number of `sigprocmask`.
+pub(crate) const SYS_SIGPROCMASK: u16 = 12;
+/// System call number of `sigreturn`.
+pub(crate) const SYS_SIGRETURN: u16 = 13;
+/// System call number of `pipe`.
+pub(crate) const SYS_PIPE: u16 = 14;
+/// System call number of `read`.
+pub(crate) const SYS_READ: u16 = 15;
+/// System call number of `write`.
+pub(crate) const SYS_WRITE: u16 = 16;
+/// System call number of `close`.
+pub(crate) const SYS_CLOSE: u16 = 17;
+/// System call number of `dup2`.
+pub(crate) const SYS_DUP2: u16 = 18;
+/// System call number of `port_create`.
+pub(crate) const SYS_PORT_CREATE: u16 = 19;
+/// System call number of `port_open`.
+pub(crate) const SYS_PORT_OPEN: u16 = 20;
+/// System call number of `send`.
+pub(crate) const SYS_SEND: u16 = 21;
+/// System call number of `receive`.
+pub(crate) const SYS_RECEIVE: u16 = 22;
+/// System call number of `call`.
+pub(crate) const SYS_CALL: u16 = 23;
+/// System call number of `reply`.
+pub(crate) const SYS_REPLY: u16 = 24;
+/// System call number of `shm_create`.
+pub(crate) const SYS_SHM_CREATE: u16 = 25;
+/// System call number of `shm_map`.
+pub(crate) const SYS_SHM_MAP: u16 = 26;
+/// System call number of `shm_unmap`.
+pub(crate) const SYS_SHM_UNMAP: u16 = 27;
+/// System call number of `brk`.
+pub(crate) const SYS_BRK: u16 = 28;
+/// System call number of `sbrk`.
+pub(crate) const SYS_SBRK: u16 = 29;
+/// System call number of `mmap`.
+pub(crate) const SYS_MMAP: u16 = 30;
+/// System call number of `munmap`.
+pub(crate) const SYS_MUNMAP: u16 = 31;
+/// System call number of `mprotect`.
+pub(crate) const SYS_MPROTECT: u16 = 32;
+
+/// System call number the kernel blocks processes with. See
+/// `process::block()`. Only accepted from EL1.
+pub(crate) const SYS_BLOCK: u16 = 0x100;
+
+/// The value of a file descriptor parameter or result that stands for no
+/// file.
+const NO_FD: u64 = u64::MAX;
+
+/// `wait` option: return right away if no child has exited.
+const WNOHANG: u64 = 1;
+
+/// `mmap` flag: share the mapping with other processes.
+const MAP_SHARED: u64 = 0x01;
+/// `mmap` flag: keep the mapping private.
+const MAP_PRIVATE: u64 = 0x02;
+/// `mmap` flag: map at exactly the address hint. Not supported.
+const MAP_FIXED: u64 = 0x10;
+/// `mmap` flag: map newly allocated memory rather than a file.
+const MAP_ANONYMOUS: u64 = 0x20;
+/// The `mmap` flags the kernel knows.
+const MAP_FLAGS: u64 = MAP_SHARED | MAP_PRIVATE | MAP_FIXED | MAP_ANONYMOUS;
+
+/// The longest string `exec` accepts, including its NUL terminator.
+const MAX_STR_LEN: usize = 4096;
+/// The most arguments or environment variables `exec` accepts.
+const MAX_STRS: usize = 64;
+
+/// Error numbers reported to user space in `x7`. The values match Linux's
+/// errno values. A status of `0` in `x7` means the system call succeeded.
+#[repr(u64)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum Errno {
+    /// No such file or directory.
+    NoEnt = 2,
+    /// No such process.
+    Srch = 3,
+    /// Interrupted system call.
+    Intr = 4,
+    /// Argument list too long.
+    TooBig = 7,
+    /// Executable format error.
+    NoExec = 8,
+    /// Bad file descriptor.
+    BadF = 9,
+    /// No child processes.
+    Child = 10,
+    /// Out of memory.
+    NoMem = 12,
+    /// Permission denied.
+    Acces = 13,
+    /// Bad address.
+    Fault = 14,
+    /// File exists.
+    Exist = 17,
+    /// No such device.
+    NoDev = 19,
+    /// Invalid argument.
+    Inval = 22,
+    /// Too many open files.
+    MFile = 24,
+    /// Broken pipe.
+    Pipe = 32,
+    /// Function not implemented.
+    NoSys = 38,
+}
+
+impl From<elf::Error> for Errno {
+    fn from(error: elf::Error) -> Errno {
+        match error {
+            elf::Error::OutOfMemory => Errno::NoMem,
+            elf::Error::StackOverflow => Errno::TooBig,
+            _ => Errno::NoExec,
+        }
+    }
+}
+
+impl From<ipc::Error> for Errno {
+    fn from(error: ipc::Error) -> Errno {
+        match error {
+            ipc::Error::Closed => Errno::Pipe,
+            ipc::Error::NameTaken => Errno::Exist,
+            ipc::Error::NoSuchPort => Errno::NoEnt,
+            ipc::Error::NotReceiver => Errno::BadF,
+            ipc::Error::NoSuchCall => Errno::Inval,
+            ipc::Error::WouldBlock(_) => unreachable!("system calls wait for blocked IPC"),
+        }
+    }
+}
+
+impl From<vm::Error> for Errno {
+    fn from(error: vm::Error) -> Errno {
+        match error {
+            vm::Error::Invalid => Errno::Inval,
+            vm::Error::NoMemory | vm::Error::NotMapped => Errno::NoMem,
+            vm::Error::PermissionDenied => Errno::Acces,
+            vm::Error::Overlap => Errno::Exist,
+            vm::Error::Fault => Errno::Fault,
+            vm::Error::TooLong => Errno::TooBig,
+        }
+    }
+}
+
+/// Sets the status value in `tf` to `errno`.
+fn fail(errno: Errno, tf: &mut TrapFrame) {
+    tf.x7 = errno as u64;
+}
+
+/// The most bytes `read` and `write` copy through the kernel at once.
+const CHUNK_SIZE: usize = 4096;
+
+/// Checks that the calling process may access `slice` with the permissions
+/// `prot`. See `vm::user`.
+fn check_user(slice: UserSlice, prot: Protection) -> Result<(), Errno> {
+    Ok(SCHEDULER.with_current(|process| slice.check(&*process, prot))?)
+}
+
+/// Copies `src`, in the memory of the calling process, into `dst`.
+fn read_user_bytes(src: UserSlice, dst: &mut [u8]) -> Result<(), Errno> {
+    Ok(SCHEDULER.with_current(|process| user::copy_from_user(&*process, src, dst))?)
+}
+
+/// Copies `src` into `dst`, in the memory of the calling process.
+fn write_user_bytes(dst: UserSlice, src: &[u8]) -> Result<(), Errno> {
+    Ok(SCHEDULER.with_current(|process| user::copy_to_user(&*process, dst, src))?)
+}
+
+/// Copies the value at `ptr` out of the memory of the calling process.
+fn read_user<T: Copy>(ptr: UserPtr<T>) -> Result<T, Errno> {
+    Ok(SCHEDULER.with_current(|process| ptr.read(&*process))?)
+}
+
+/// Copies `value` to `ptr` in the memory of the calling process.
+fn write_user<T: Copy>(ptr: UserPtr<T>, value: T) -> Result<(), Errno> {
+    Ok(SCHEDULER.with_current(|process| ptr.write(&*process, value))?)
+}
 
 /// Sleep for `ms` milliseconds.
 ///
@@ -6,13 +224,957 @@ use crate::traps::TrapFrame;
 ///
 /// In addition to the usual status value, this system call returns one
 /// parameter: the approximate true elapsed time from when `sleep` was called to
-/// when `sleep` returned.
-#[allow(dead_code)] // not used yet.
-pub(crate) fn sleep(_ms: u32, _tf: &mut TrapFrame) {
-    unimplemented!("syscall: sleep()")
+/// when `sleep` returned. Fails with `EINTR`, still returning the elapsed
+/// time, if a signal ends the sleep early.
+pub(crate) fn sleep(ms: u32, tf: &mut TrapFrame) {
+    let start = TIMERS.get().now();
+    let deadline = start + ms as u64 * 1_000;
+
+    // Preempt whatever is running at the deadline, so the scheduler polls the
+    // sleeping process promptly instead of at the next tick.
+    TIMERS
+        .get()
+        .add(deadline, None, Box::new(process::request_preemption));
+
+    let slept = process::block(Box::new(move |_: &mut Process| TIMERS.get().now() >= deadline));
+    tf.x0 = (TIMERS.get().now() - start) / 1_000;
+    tf.x7 = 0;
+    if slept.is_err() {
+        fail(Errno::Intr, tf);
+    }
+}
+
+/// Read the kernel log.
+///
+/// This system call takes two parameters: the address and length of a buffer
+/// that the most recent whole kernel log records are copied into.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the number of bytes copied. Fails with `EFAULT` if the buffer
+/// is not writable.
+pub(crate) fn dmesg(buf: UserSlice, tf: &mut TrapFrame) {
+    let mut records = vec![0; buf.size().min(crate::log::BUFFER_SIZE)];
+    let len = crate::log::read(&mut records);
+    match write_user_bytes(buf.prefix(len), &records[..len]) {
+        Ok(()) => {
+            tf.x0 = len as u64;
+            tf.x7 = 0;
+        }
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// End the calling process.
+///
+/// This system call takes one parameter: the exit code, which the parent
+/// collects with `wait`. It does not return.
+pub(crate) fn exit(code: i32, tf: &mut TrapFrame) {
+    SCHEDULER.exit(ExitStatus::Exited(code), tf);
+}
+
+/// Wait for a child process to exit and reap it.
+///
+/// This system call takes two parameters: the ID of the child to wait for, or
+/// `-1` for any child, and options. With the `WNOHANG` option it returns
+/// right away if the children are still running. Otherwise the calling process
+/// sleeps in the kernel until a child exits.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters: the ID of the reaped child, or `0` if there was none, and its
+/// wait status. Fails with `ECHILD` if there are no matching children, and
+/// `EINTR` if a signal arrives while it sleeps.
+pub(crate) fn wait(pid: i64, options: u64, tf: &mut TrapFrame) {
+    if options & !WNOHANG != 0 || pid == 0 || pid < -1 {
+        return fail(Errno::Inval, tf);
+    }
+
+    let id = (pid != -1).then_some(pid as Id);
+    let (child, status) = loop {
+        match SCHEDULER.wait(id, options & WNOHANG == 0) {
+            Wait::Exited(id, status) => break (id, status.to_wait_status()),
+            Wait::Running(Some(waiter)) => {
+                if crate::sync::sleep_on(waiter).is_err() {
+                    return fail(Errno::Intr, tf);
+                }
+            }
+            Wait::Running(None) => break (0, 0),
+            Wait::NoChildren => return fail(Errno::Child, tf),
+        }
+    };
+    tf.x0 = child;
+    tf.x1 = status;
+    tf.x7 = 0;
+}
+
+/// Switches out the process that blocks in the kernel with the trap frame
+/// `tf` until the poll function at `poll` returns `true`. See
+/// `process::block()`.
+fn block(poll: *mut Option<EventPollFn>, tf: &mut TrapFrame) {
+    // Nothing is locked by the handler yet, so any lock is the caller's.
+    assert!(!crate::mutex::holds_locks(), "blocking while holding a lock");
+    match unsafe { (*poll).take() } {
+        Some(poll) => {
+            // `process::block()` returns this unless a signal interrupts it.
+            tf.x0 = 0;
+            let _scheduled_pid = SCHEDULER.switch(State::Waiting(poll), tf);
+        }
+        None => fail(Errno::Inval, tf),
+    }
+}
+
+/// Send a signal to a process.
+///
+/// This system call takes two parameters: the ID of the process and the
+/// signal to send it, or `0` to only check that the process exists. A process
+/// that sends itself `SIGKILL` does not return. Fails with `ESRCH` if there is
+/// no such process, and `EINVAL` if the signal is not valid.
+pub(crate) fn kill(pid: Id, sig: u64, tf: &mut TrapFrame) {
+    if sig != 0 && !signal::is_valid(sig as signal::Signal) {
+        return fail(Errno::Inval, tf);
+    }
+
+    // The status is set first: if the caller kills itself, `tf` then belongs
+    // to the next process.
+    tf.x7 = 0;
+    let found = match sig {
+        0 => SCHEDULER.exists(pid),
+        sig => SCHEDULER.signal(pid, sig as signal::Signal, tf),
+    };
+    if !found {
+        fail(Errno::Srch, tf);
+    }
+}
+
+/// Examine or change the action of a signal.
+///
+/// This system call takes three parameters: the signal, the address of the
+/// new `signal::SigAction` or `0` to leave it unchanged, and the address the
+/// previous action is copied to, or `0`. Fails with `EINVAL` if the signal
+/// is not valid, is `SIGKILL`, or gets a handler without a restorer, and
+/// `EFAULT` if an address is not accessible.
+pub(crate) fn sigaction(sig: u64, act: UserPtr<SigAction>, oldact: UserPtr<SigAction>, tf: &mut TrapFrame) {
+    let action = match (!act.is_null()).then(|| read_user(act)).transpose() {
+        Ok(action) => action,
+        Err(errno) => return fail(errno, tf),
+    };
+    let previous = match SCHEDULER.sigaction(sig as signal::Signal, action) {
+        Ok(previous) => previous,
+        Err(_) => return fail(Errno::Inval, tf),
+    };
+    match oldact.is_null() {
+        true => tf.x7 = 0,
+        false => match write_user(oldact, previous) {
+            Ok(()) => tf.x7 = 0,
+            Err(errno) => fail(errno, tf),
+        },
+    }
+}
+
+/// Examine or change the blocked signals of the calling process.
+///
+/// This system call takes two parameters: how to change the mask, one of
+/// `SIG_BLOCK`, `SIG_UNBLOCK` and `SIG_SETMASK`, and a set of signals, with
+/// bit `n` standing for signal `n`. `SIGKILL` cannot be blocked.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the previously blocked signals. Fails with `EINVAL` if `how` is
+/// not valid.
+pub(crate) fn sigprocmask(how: u64, set: u64, tf: &mut TrapFrame) {
+    match SCHEDULER.sigprocmask(how, set) {
+        Ok(previous) => {
+            tf.x0 = previous;
+            tf.x7 = 0;
+        }
+        Err(_) => fail(Errno::Inval, tf),
+    }
+}
+
+/// Return from a signal handler.
+///
+/// This system call takes no parameters. It is made by the restorer a
+/// handler returns to, with the stack pointer where the handler was entered.
+/// On success it does not return: the process continues where the signal
+/// interrupted it, with the signals blocked then. A process without a valid
+/// signal frame at its stack pointer is killed with `SIGSEGV`.
+pub(crate) fn sigreturn(tf: &mut TrapFrame) {
+    if SCHEDULER.sigreturn(tf).is_err() {
+        crate::error!("killing process {:?}: bad signal frame", SCHEDULER.current());
+        SCHEDULER.exit(ExitStatus::Killed(signal::SIGSEGV), tf);
+    }
+}
+
+/// Create a child process.
+///
+/// This system call takes no parameters. The child is a copy of the calling
+/// process that continues from the same point.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the child's ID in the parent, and `0` in the child.
+pub(crate) fn fork(tf: &mut TrapFrame) {
+    match SCHEDULER.fork(tf) {
+        Some(child) => {
+            tf.x0 = child;
+            tf.x7 = 0;
+        }
+        None => fail(Errno::NoMem, tf),
+    }
+}
+
+/// Copies the NUL-terminated string at `addr` out of user memory.
+fn read_str(addr: u64) -> Result<String, Errno> {
+    if addr == 0 {
+        return Err(Errno::Fault);
+    }
+
+    let bytes = SCHEDULER.with_current(|process| user::copy_str_from_user(&*process, addr, MAX_STR_LEN))?;
+    String::from_utf8(bytes).map_err(|_| Errno::Inval)
+}
+
+/// Copies the NULL-terminated array of strings at `addr` out of user memory.
+/// A null `addr` is an empty array.
+fn read_strs(addr: u64) -> Result<Vec<String>, Errno> {
+    let mut strs = Vec::new();
+    if addr == 0 {
+        return Ok(strs);
+    }
+
+    let ptr = UserPtr::<u64>::new(addr);
+    loop {
+        let str_addr = read_user(ptr.add(strs.len()))?;
+        if str_addr == 0 {
+            return Ok(strs);
+        }
+        if strs.len() == MAX_STRS {
+            return Err(Errno::TooBig);
+        }
+        strs.push(read_str(str_addr)?);
+    }
+}
+
+/// Run another program in the calling process.
+///
+/// This system call takes three parameters: the name of a program embedded
+/// in the kernel, and the NULL-terminated `argv` and `envp` arrays for it.
+/// Strings are NUL-terminated. A null `argv` or `envp` is empty.
+///
+/// On success this system call does not return: the process starts the new
+/// program as described in `Process::exec()`. Fails with `ENOENT` if there is
+/// no such program, and `ENOEXEC` if it is not a valid executable.
+pub(crate) fn exec(name: u64, argv: u64, envp: u64, tf: &mut TrapFrame) {
+    let (name, argv, envp) = match (read_str(name), read_strs(argv), read_strs(envp)) {
+        (Ok(name), Ok(argv), Ok(envp)) => (name, argv, envp),
+        (Err(errno), _, _) | (_, Err(errno), _) | (_, _, Err(errno)) => return fail(errno, tf),
+    };
+    let Some(bytes) = programs::find(&name) else {
+        return fail(Errno::NoEnt, tf);
+    };
+
+    let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+    let envp: Vec<&str> = envp.iter().map(String::as_str).collect();
+    if let Err(error) = SCHEDULER.exec(bytes, &argv, &envp, tf) {
+        crate::debug!("exec {name}: {error}");
+        fail(error.into(), tf);
+    }
+}
+
+/// Set the niceness of a process.
+///
+/// This system call takes two parameters: the ID of the process, or `0` for
+/// the calling process, and the new niceness. Lower niceness asks for more
+/// CPU time. Values outside of -20..=19 are clamped.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the previous niceness. Fails with `ESRCH` if there is no such
+/// process.
+pub(crate) fn nice(pid: Id, nice: i64, tf: &mut TrapFrame) {
+    match SCHEDULER.set_nice((pid != 0).then_some(pid), nice) {
+        Some(previous) => {
+            tf.x0 = previous as i64 as u64;
+            tf.x7 = 0;
+        }
+        None => fail(Errno::Srch, tf),
+    }
+}
+
+/// Get the ID of the calling process.
+///
+/// This system call takes no parameters.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the ID of the calling process.
+pub(crate) fn getpid(tf: &mut TrapFrame) {
+    tf.x0 = SCHEDULER.current().expect("no current process");
+    tf.x7 = 0;
+}
+
+/// Take a snapshot of every process.
+///
+/// This system call takes two parameters: the address of an array of
+/// `process::stats::ProcessInfo` records and its length in records. The
+/// records of the processes with the lowest IDs are copied into it.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters: the number of records copied, and the number of processes.
+/// Fails with `EFAULT` if the array is not writable.
+pub(crate) fn ps(buf: UserPtr<ProcessInfo>, len: usize, tf: &mut TrapFrame) {
+    let snapshot = SCHEDULER.snapshot();
+    let copied = snapshot.len().min(len);
+    for (i, info) in snapshot.iter().take(copied).enumerate() {
+        if let Err(errno) = write_user(buf.add(i), *info) {
+            return fail(errno, tf);
+        }
+    }
+    tf.x0 = copied as u64;
+    tf.x1 = snapshot.len() as u64;
+    tf.x7 = 0;
+}
+
+/// Create a pipe.
+///
+/// This system call takes no parameters. See `file::pipe()`.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters: the file descriptors of the read end and of the write end.
+/// Fails with `EMFILE` if the calling process has too many open files.
+pub(crate) fn pipe(tf: &mut TrapFrame) {
+    let (reader, writer) = file::pipe();
+    let fds = SCHEDULER.with_files(|files| {
+        let reader = files.insert(reader)?;
+        match files.insert(writer) {
+            Some(writer) => Some((reader, writer)),
+            None => {
+                files.close(reader);
+                None
+            }
+        }
+    });
+    match fds {
+        Some((reader, writer)) => {
+            tf.x0 = reader as u64;
+            tf.x1 = writer as u64;
+            tf.x7 = 0;
+        }
+        None => fail(Errno::MFile, tf),
+    }
+}
+
+/// Returns the file the process running on this core has open at `fd`.
+fn open_file(fd: u64) -> Result<Arc<dyn File>, Errno> {
+    SCHEDULER
+        .with_files(|files| files.get(fd as Fd))
+        .ok_or(Errno::BadF)
+}
+
+/// Read from a file.
+///
+/// This system call takes three parameters: the file descriptor, and the
+/// address and length of the buffer to read into. If nothing can be read
+/// yet, the calling process sleeps in the kernel until something can.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the number of bytes read, which is `0` at the end of the file.
+/// Fails with `EBADF` if the file is not open for reading, `EFAULT` if the
+/// buffer is not writable, and `EINTR` if a signal arrives while it sleeps.
+pub(crate) fn read(fd: u64, buf: UserSlice, tf: &mut TrapFrame) {
+    // Checked first, so that nothing is read from the file and then lost.
+    if let Err(errno) = check_user(buf, Protection::WRITE) {
+        return fail(errno, tf);
+    }
+
+    let mut chunk = vec![0; buf.size().min(CHUNK_SIZE)];
+    loop {
+        // The file is looked up again after sleeping: it is not held while
+        // the process sleeps, in case the process is killed meanwhile.
+        let result = match open_file(fd) {
+            Ok(file) => file.read(&mut chunk),
+            Err(errno) => return fail(errno, tf),
+        };
+        match result {
+            Ok(count) => {
+                process::wake_idle_cores();
+                if let Err(errno) = write_user_bytes(buf.prefix(count), &chunk[..count]) {
+                    return fail(errno, tf);
+                }
+                tf.x0 = count as u64;
+                tf.x7 = 0;
+                return;
+            }
+            Err(file::Error::WouldBlock(waiter)) => {
+                if crate::sync::sleep_on(waiter).is_err() {
+                    return fail(Errno::Intr, tf);
+                }
+            }
+            Err(file::Error::BrokenPipe | file::Error::Unsupported) => {
+                return fail(Errno::BadF, tf)
+            }
+        }
+    }
+}
+
+/// Write to a file.
+///
+/// This system call takes three parameters: the file descriptor, and the
+/// address and length of the buffer to write. The calling process sleeps in
+/// the kernel until all of it is written.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the number of bytes written. It is short, and the call
+/// succeeds, only if a signal or a closed pipe stopped it after writing some.
+/// Fails with `EBADF` if the file is not open for writing, `EFAULT` if the
+/// buffer is not readable, `EINTR` if a signal arrives before anything is
+/// written, and `EPIPE` if the file is a pipe nobody reads from, which also
+/// sends the calling process `SIGPIPE`.
+pub(crate) fn write(fd: u64, buf: UserSlice, tf: &mut TrapFrame) {
+    if let Err(errno) = check_user(buf, Protection::READ) {
+        return fail(errno, tf);
+    }
+
+    let mut chunk = vec![0; buf.size().min(CHUNK_SIZE)];
+    let mut written = 0;
+    while written < buf.size() {
+        let rest = buf.skip(written).prefix(CHUNK_SIZE);
+        let chunk = &mut chunk[..rest.size()];
+        if let Err(errno) = read_user_bytes(rest, chunk) {
+            return fail(errno, tf);
+        }
+        // See `read()` for why the file is not held while sleeping.
+        let result = match open_file(fd) {
+            Ok(file) => file.write(chunk),
+            Err(errno) => return fail(errno, tf),
+        };
+        let errno = match result {
+            Ok(count) => {
+                process::wake_idle_cores();
+                written += count;
+                continue;
+            }
+            Err(file::Error::WouldBlock(waiter)) => match crate::sync::sleep_on(waiter) {
+                Ok(()) => continue,
+                Err(_) => Errno::Intr,
+            },
+            Err(file::Error::BrokenPipe) => Errno::Pipe,
+            Err(file::Error::Unsupported) => return fail(Errno::BadF, tf),
+        };
+
+        if written > 0 {
+            break;
+        }
+        if errno == Errno::Pipe {
+            SCHEDULER.raise(signal::SIGPIPE);
+        }
+        return fail(errno, tf);
+    }
+    tf.x0 = written as u64;
+    tf.x7 = 0;
+}
+
+/// Close a file descriptor.
+///
+/// This system call takes one parameter: the file descriptor. The file is
+/// closed once no file descriptor refers to it. Fails with `EBADF` if the
+/// file descriptor is not open.
+pub(crate) fn close(fd: u64, tf: &mut TrapFrame) {
+    if SCHEDULER.with_files(|files| files.close(fd as Fd)) {
+        process::wake_idle_cores();
+        tf.x7 = 0;
+    } else {
+        fail(Errno::BadF, tf);
+    }
+}
+
+/// Duplicate a file descriptor.
+///
+/// This system call takes two parameters: the file descriptor to duplicate
+/// and the one to make refer to the same file, closing what it referred to.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the new file descriptor. Fails with `EBADF` if the first file
+/// descriptor is not open or the second is out of range.
+pub(crate) fn dup2(old: u64, new: u64, tf: &mut TrapFrame) {
+    if SCHEDULER.with_files(|files| files.dup2(old as Fd, new as Fd)) {
+        process::wake_idle_cores();
+        tf.x0 = new;
+        tf.x7 = 0;
+    } else {
+        fail(Errno::BadF, tf);
+    }
+}
+
+/// Opens `handle`, a port handle for the port called `name`, in the calling
+/// process. See `port_create()` and `port_open()`.
+fn open_port(
+    name: u64,
+    handle: fn(&str) -> Result<ipc::PortHandle, ipc::Error>,
+    tf: &mut TrapFrame,
+) {
+    let handle = match read_str(name).and_then(|name| Ok(handle(&name)?)) {
+        Ok(handle) => handle,
+        Err(errno) => return fail(errno, tf),
+    };
+    match SCHEDULER.with_files(|files| files.insert(Arc::new(handle))) {
+        Some(fd) => {
+            tf.x0 = fd as u64;
+            tf.x7 = 0;
+        }
+        None => fail(Errno::MFile, tf),
+    }
+}
+
+/// Create a named IPC port.
+///
+/// This system call takes one parameter: the port's NUL-terminated name. See
+/// `ipc`.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the file descriptor of the port's receive handle. The port is
+/// closed, and its name freed, when the handle is. Fails with `EEXIST` if a
+/// port with that name exists, and `EMFILE` if the calling process has too
+/// many open files.
+pub(crate) fn port_create(name: u64, tf: &mut TrapFrame) {
+    open_port(name, |name| ipc::PORTS.create(name), tf);
+}
+
+/// Open a named IPC port.
+///
+/// This system call takes one parameter: the port's NUL-terminated name.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the file descriptor of a send handle to the port. Fails with
+/// `ENOENT` if there is no port with that name, and `EMFILE` if the calling
+/// process has too many open files.
+pub(crate) fn port_open(name: u64, tf: &mut TrapFrame) {
+    open_port(name, |name| ipc::PORTS.open(name), tf);
+}
+
+/// Calls `f` with the port handle the calling process has open at `fd`.
+fn with_port<T>(fd: u64, f: impl FnOnce(&ipc::PortHandle) -> T) -> Result<T, Errno> {
+    let file = open_file(fd)?;
+    file.as_port().map(f).ok_or(Errno::BadF)
+}
+
+/// Copies the message at `addr` and the file the calling process has open
+/// at `handle`, unless it is `NO_FD`, into a parcel.
+fn read_parcel(addr: UserPtr<Message>, handle: u64) -> Result<Parcel, Errno> {
+    let message = read_user(addr)?;
+    let handle = match handle {
+        NO_FD => None,
+        fd => Some(open_file(fd)?),
+    };
+    Ok(Parcel { message, handle })
+}
+
+/// Opens `handle`, received in a message, in the calling process and returns
+/// its file descriptor, or `NO_FD` if there is none. The caller made sure
+/// there is room for it.
+fn install(handle: Option<Arc<dyn File>>) -> u64 {
+    handle
+        .and_then(|handle| SCHEDULER.with_files(|files| files.insert(handle)))
+        .map_or(NO_FD, |fd| fd as u64)
+}
+
+/// Sleeps in the kernel until the IPC transaction of the calling process
+/// completes, and returns its outcome. See `ipc::Transaction::poll()`.
+fn complete(transaction: Arc<Transaction>) -> Result<Option<Parcel>, Errno> {
+    process::wake_idle_cores();
+    SCHEDULER.with_current(|process| process.transaction = Some(transaction));
+    let poll = || {
+        SCHEDULER.with_current(|process| process.transaction.as_ref().expect("transaction").poll())
+    };
+    let cancel = || {
+        SCHEDULER.with_current(|process| process.transaction.as_ref().expect("transaction").cancel())
+    };
+
+    let outcome = loop {
+        match poll() {
+            Err(ipc::Error::WouldBlock(waiter)) => {
+                if crate::sync::sleep_on(waiter).is_err() {
+                    // A transaction that completed meanwhile is not interrupted.
+                    break if cancel() { Err(Errno::Intr) } else { poll().map_err(Errno::from) };
+                }
+            }
+            outcome => break outcome.map_err(Errno::from),
+        }
+    };
+    SCHEDULER.with_current(|process| process.transaction = None);
+    outcome
+}
+
+/// Send a message to an IPC port.
+///
+/// This system call takes three parameters: the file descriptor of a handle
+/// to the port, the address of the `ipc::Message`, and the file descriptor of
+/// a file to pass along with it, or `-1`. The calling process sleeps in the
+/// kernel until the message is received.
+///
+/// Fails with `EBADF` if a file descriptor is not valid, `EFAULT` if the
+/// message is not readable, `EPIPE` if the port closes first, and `EINTR` if
+/// a signal arrives before the message is received, in which case it is not.
+pub(crate) fn send(fd: u64, msg: UserPtr<Message>, handle: u64, tf: &mut TrapFrame) {
+    let sender = SCHEDULER.current().expect("no current process");
+    let sent = read_parcel(msg, handle)
+        .and_then(|parcel| Ok(with_port(fd, |port| port.send(parcel, sender))??))
+        .and_then(complete);
+    match sent {
+        Ok(_) => tf.x7 = 0,
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// Receive a message from an IPC port.
+///
+/// This system call takes two parameters: the file descriptor of the port's
+/// receive handle, and the address the `ipc::Message` is copied to. The
+/// calling process sleeps in the kernel until a message arrives.
+///
+/// In addition to the usual status value, this system call returns three
+/// parameters: the ID of the sender, the token to `reply` with if the sender
+/// made a `call` or `0`, and the file descriptor of the file passed along
+/// with the message or `-1`. Fails with `EBADF` if the file descriptor is not
+/// a receive handle, `EFAULT` if the message is not writable, `EMFILE` if the
+/// calling process could not open a file passed along, and `EINTR` if a
+/// signal arrives while it sleeps.
+pub(crate) fn receive(fd: u64, msg: UserPtr<Message>, tf: &mut TrapFrame) {
+    // Checked first, so that a message is never received and then lost.
+    if let Err(errno) = check_user(msg.bytes(), Protection::WRITE) {
+        return fail(errno, tf);
+    }
+
+    loop {
+        if SCHEDULER.with_files(|files| files.is_full()) {
+            return fail(Errno::MFile, tf);
+        }
+        let result = match with_port(fd, |port| port.receive()) {
+            Ok(result) => result,
+            Err(errno) => return fail(errno, tf),
+        };
+        match result {
+            Ok(received) => {
+                process::wake_idle_cores();
+                if let Err(errno) = write_user(msg, received.parcel.message) {
+                    return fail(errno, tf);
+                }
+                tf.x0 = received.sender;
+                tf.x1 = received.token.unwrap_or(0);
+                tf.x2 = install(received.parcel.handle);
+                tf.x7 = 0;
+                return;
+            }
+            Err(ipc::Error::WouldBlock(waiter)) => {
+                if crate::sync::sleep_on(waiter).is_err() {
+                    return fail(Errno::Intr, tf);
+                }
+            }
+            Err(error) => return fail(error.into(), tf),
+        }
+    }
+}
+
+/// Call an IPC port: send it a message and wait for the reply.
+///
+/// This system call takes three parameters: the file descriptor of a handle
+/// to the port, the address of the `ipc::Message`, which the reply replaces,
+/// and the file descriptor of a file to pass along with it, or `-1`. The
+/// calling process sleeps in the kernel until the receiver replies.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the file descriptor of the file passed along with the reply, or
+/// `-1`. Fails with `EBADF` if a file descriptor is not valid, `EFAULT` if
+/// the message is not readable and writable, `EMFILE` if the calling process
+/// could not open a file passed along, `EPIPE` if the port closes before
+/// replying, and `EINTR` if a signal arrives first.
+pub(crate) fn call(fd: u64, msg: UserPtr<Message>, handle: u64, tf: &mut TrapFrame) {
+    if SCHEDULER.with_files(|files| files.is_full()) {
+        return fail(Errno::MFile, tf);
+    }
+    if let Err(errno) = check_user(msg.bytes(), Protection::READ_WRITE) {
+        return fail(errno, tf);
+    }
+
+    let caller = SCHEDULER.current().expect("no current process");
+    let reply = read_parcel(msg, handle)
+        .and_then(|parcel| Ok(with_port(fd, |port| port.call(parcel, caller))??))
+        .and_then(complete);
+    match reply {
+        Ok(reply) => {
+            let reply = reply.unwrap_or_default();
+            if let Err(errno) = write_user(msg, reply.message) {
+                return fail(errno, tf);
+            }
+            tf.x0 = install(reply.handle);
+            tf.x7 = 0;
+        }
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// Reply to a call received from an IPC port.
+///
+/// This system call takes four parameters: the file descriptor of the port's
+/// receive handle, the call's token from `receive`, the address of the reply
+/// `ipc::Message`, and the file descriptor of a file to pass along with it,
+/// or `-1`. It does not wait. Fails with `EBADF` if a file descriptor is not
+/// valid, `EFAULT` if the message is not readable, and `EINVAL` if the token
+/// names no call waiting for a reply.
+pub(crate) fn reply(fd: u64, token: u64, msg: UserPtr<Message>, handle: u64, tf: &mut TrapFrame) {
+    let replied = read_parcel(msg, handle)
+        .and_then(|parcel| Ok(with_port(fd, |port| port.reply(token, parcel))??));
+    match replied {
+        Ok(()) => {
+            process::wake_idle_cores();
+            tf.x7 = 0;
+        }
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// Create a shared memory region.
+///
+/// This system call takes one parameter: the size of the region, which is
+/// rounded up to a multiple of the page size. See `vm::shm`.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the file descriptor of a handle to the region, which starts out
+/// zeroed. Fails with `EINVAL` if the size is `0` or too large, `ENOMEM` if
+/// there is no memory for it, and `EMFILE` if the calling process has too
+/// many open files.
+pub(crate) fn shm_create(size: u64, tf: &mut TrapFrame) {
+    let shm = match SharedMemory::new(size as usize) {
+        Ok(shm) => shm,
+        Err(error) => return fail(error.into(), tf),
+    };
+    match SCHEDULER.with_files(|files| files.insert(Arc::new(shm))) {
+        Some(fd) => {
+            tf.x0 = fd as u64;
+            tf.x7 = 0;
+        }
+        None => fail(Errno::MFile, tf),
+    }
+}
+
+/// Maps `vma` into the process running on this core and returns its address
+/// and size.
+fn map(vma: Result<Vma, vm::Error>) -> Result<(u64, usize), Errno> {
+    let vma = vma?;
+    let size = vma.size();
+    let start = SCHEDULER.with_vmas(|vmas| vmas.insert(vma))?;
+    Ok((start, size))
+}
+
+/// Map a shared memory region.
+///
+/// This system call takes two parameters: the file descriptor of a handle to
+/// the region, and the permissions to map it with as `PROT_*` flags. The
+/// permissions are not enforced yet. Like `mmap` of the whole region with
+/// `MAP_SHARED`.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters: the address the region is mapped at, which is the same in
+/// every process, and its size. Fails with `EBADF` if the file descriptor is
+/// not a shared memory handle, `EINVAL` if the permissions are not valid,
+/// `EACCES` if they include `PROT_EXEC`, `EEXIST` if the calling process has
+/// the region mapped already, and `ENOMEM` if it maps too many regions.
+pub(crate) fn shm_map(fd: u64, prot: u64, tf: &mut TrapFrame) {
+    let Some(prot) = Protection::from_bits(prot) else {
+        return fail(Errno::Inval, tf);
+    };
+    let result = open_file(fd).and_then(|file| {
+        let size = file.as_shared_memory().ok_or(Errno::BadF)?.size();
+        map(Vma::file(file, 0, size, prot))
+    });
+    match result {
+        Ok((start, size)) => {
+            tf.x0 = start;
+            tf.x1 = size as u64;
+            tf.x7 = 0;
+        }
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// Unmap a shared memory region.
+///
+/// This system call takes one parameter: the address `shm_map` returned. The
+/// region is freed once it is no longer mapped and its handles are closed.
+///
+/// Fails with `EINVAL` if no shared memory is mapped at the address.
+pub(crate) fn shm_unmap(addr: u64, tf: &mut TrapFrame) {
+    let result = SCHEDULER.with_vmas(|vmas| match vmas.get(addr) {
+        Some(vma) if vma.start() == addr && matches!(vma.backing, Backing::File(_)) => {
+            let size = vma.size();
+            vmas.unmap(addr, size)
+        }
+        _ => Err(vm::Error::Invalid),
+    });
+    match result {
+        Ok(()) => tf.x7 = 0,
+        Err(error) => fail(error.into(), tf),
+    }
+}
+
+/// Set the program break.
+///
+/// This system call takes one parameter: the new program break, or `0` to
+/// ask for the current one. The first call reserves the heap, which the
+/// break starts at the bottom of. See `vm::VmaList::brk()`.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the program break. Fails with `ENOMEM` if the break would leave
+/// the heap, or there is no memory to reserve it.
+pub(crate) fn brk(addr: u64, tf: &mut TrapFrame) {
+    match SCHEDULER.with_vmas(|vmas| vmas.brk(addr)) {
+        Ok(brk) => {
+            tf.x0 = brk;
+            tf.x7 = 0;
+        }
+        Err(error) => fail(error.into(), tf),
+    }
+}
+
+/// Move the program break.
+///
+/// This system call takes one parameter: the signed number of bytes to move
+/// the program break by. See `brk`.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the previous program break, where newly allocated memory
+/// starts. Fails with `ENOMEM` if the break would leave the heap.
+pub(crate) fn sbrk(increment: i64, tf: &mut TrapFrame) {
+    match SCHEDULER.with_vmas(|vmas| vmas.sbrk(increment)) {
+        Ok(brk) => {
+            tf.x0 = brk;
+            tf.x7 = 0;
+        }
+        Err(error) => fail(error.into(), tf),
+    }
+}
+
+/// Map memory.
+///
+/// This system call takes six parameters: an address hint, which is ignored,
+/// the length, the permissions as `PROT_*` flags, the `MAP_*` flags, a file
+/// descriptor and an offset into the file. With `MAP_ANONYMOUS`, the file
+/// descriptor and offset are ignored and the memory is newly allocated and
+/// zeroed. Otherwise the file must be a shared memory handle mapped with
+/// `MAP_SHARED`, and the offset page-aligned. The permissions are not enforced
+/// yet, and memory is allocated when it is mapped.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the address the memory is mapped at. Fails with `EINVAL` if the
+/// length is `0` or too large or the flags are not valid, including
+/// `MAP_FIXED`, which needs page tables, `EBADF` if the file descriptor is not
+/// open, `ENODEV` if the file cannot be mapped, `EACCES` if it cannot be
+/// mapped with the permissions, `EEXIST` if the calling process has the same
+/// memory mapped already, and `ENOMEM` if there is no memory or the process
+/// maps too many regions.
+pub(crate) fn mmap(len: u64, prot: u64, flags: u64, fd: u64, offset: u64, tf: &mut TrapFrame) {
+    let (Some(prot), true) = (Protection::from_bits(prot), flags & !MAP_FLAGS == 0) else {
+        return fail(Errno::Inval, tf);
+    };
+    let sharing = flags & (MAP_SHARED | MAP_PRIVATE);
+    if (sharing != MAP_SHARED && sharing != MAP_PRIVATE) || flags & MAP_FIXED != 0 {
+        return fail(Errno::Inval, tf);
+    }
+
+    let result = if flags & MAP_ANONYMOUS != 0 {
+        map(Vma::anonymous(len as usize, prot))
+    } else if sharing == MAP_SHARED {
+        open_file(fd).and_then(|file| {
+            if file.as_shared_memory().is_none() {
+                return Err(Errno::NoDev);
+            }
+            map(Vma::file(file, offset as usize, len as usize, prot))
+        })
+    } else {
+        Err(Errno::Inval)
+    };
+    match result {
+        Ok((start, _)) => {
+            tf.x0 = start;
+            tf.x7 = 0;
+        }
+        Err(errno) => fail(errno, tf),
+    }
+}
+
+/// Unmap memory.
+///
+/// This system call takes two parameters: the page-aligned address and the
+/// length of the memory to unmap. Parts of the range that are not mapped are
+/// skipped, and regions are split as needed.
+///
+/// Fails with `EINVAL` if the address is not page-aligned, the length is `0`,
+/// or the range includes the heap, and `ENOMEM` if the calling process would
+/// map too many regions.
+pub(crate) fn munmap(addr: u64, len: u64, tf: &mut TrapFrame) {
+    match SCHEDULER.with_vmas(|vmas| vmas.unmap(addr, len as usize)) {
+        Ok(()) => tf.x7 = 0,
+        Err(error) => fail(error.into(), tf),
+    }
+}
+
+/// Change the permissions of memory.
+///
+/// This system call takes three parameters: the page-aligned address and the
+/// length of the memory, and the permissions as `PROT_*` flags. The
+/// permissions are not enforced yet.
+///
+/// Fails with `EINVAL` if the address is not page-aligned, the length is `0`,
+/// the range includes the heap or the permissions are not valid, `EACCES` if
+/// shared memory would be executable, and `ENOMEM` if part of the range is
+/// not mapped or the calling process would map too many regions.
+pub(crate) fn mprotect(addr: u64, len: u64, prot: u64, tf: &mut TrapFrame) {
+    let Some(prot) = Protection::from_bits(prot) else {
+        return fail(Errno::Inval, tf);
+    };
+    match SCHEDULER.with_vmas(|vmas| vmas.protect(addr, len as usize, prot)) {
+        Ok(()) => tf.x7 = 0,
+        Err(error) => fail(error.into(), tf),
+    }
 }
 
-#[allow(dead_code)] // not used yet.
-pub(crate) fn handle_syscall(_num: u16, _tf: &mut TrapFrame) {
-    unimplemented!("handle_syscall()")
+/// Dispatches the system call `num`. Parameters are passed in `x0`..`x6`,
+/// return values are written to `x0`..`x6` and the status to `x7`.
+pub(crate) fn handle_syscall(num: u16, tf: &mut TrapFrame) {
+    crate::trace!("syscall {num}");
+    match num {
+        SYS_SLEEP => sleep(tf.x0 as u32, tf),
+        SYS_DMESG => dmesg(UserSlice::new(tf.x0, tf.x1 as usize), tf),
+        SYS_EXIT => exit(tf.x0 as i32, tf),
+        SYS_WAIT => wait(tf.x0 as i64, tf.x1, tf),
+        SYS_KILL => kill(tf.x0, tf.x1, tf),
+        SYS_FORK => fork(tf),
+        SYS_EXEC => exec(tf.x0, tf.x1, tf.x2, tf),
+        SYS_NICE => nice(tf.x0, tf.x1 as i64, tf),
+        SYS_GETPID => getpid(tf),
+        SYS_PS => ps(UserPtr::new(tf.x0), tf.x1 as usize, tf),
+        SYS_SIGACTION => sigaction(
+            tf.x0,
+            UserPtr::new(tf.x1),
+            UserPtr::new(tf.x2),
+            tf,
+        ),
+        SYS_SIGPROCMASK => sigprocmask(tf.x0, tf.x1, tf),
+        SYS_SIGRETURN if !tf.is_kernel() => sigreturn(tf),
+        SYS_PIPE => pipe(tf),
+        SYS_READ => read(tf.x0, UserSlice::new(tf.x1, tf.x2 as usize), tf),
+        SYS_WRITE => write(tf.x0, UserSlice::new(tf.x1, tf.x2 as usize), tf),
+        SYS_CLOSE => close(tf.x0, tf),
+        SYS_DUP2 => dup2(tf.x0, tf.x1, tf),
+        SYS_PORT_CREATE => port_create(tf.x0, tf),
+        SYS_PORT_OPEN => port_open(tf.x0, tf),
+        SYS_SEND => send(tf.x0, UserPtr::new(tf.x1), tf.x2, tf),
+        SYS_RECEIVE => receive(tf.x0, UserPtr::new(tf.x1), tf),
+        SYS_CALL => call(tf.x0, UserPtr::new(tf.x1), tf.x2, tf),
+        SYS_REPLY => reply(tf.x0, tf.x1, UserPtr::new(tf.x2), tf.x3, tf),
+        SYS_SHM_CREATE => shm_create(tf.x0, tf),
+        SYS_SHM_MAP => shm_map(tf.x0, tf.x1, tf),
+        SYS_SHM_UNMAP => shm_unmap(tf.x0, tf),
+        SYS_BRK => brk(tf.x0, tf),
+        SYS_SBRK => sbrk(tf.x0 as i64, tf),
+        SYS_MMAP => mmap(tf.x1, tf.x2, tf.x3, tf.x4, tf.x5, tf),
+        SYS_MUNMAP => munmap(tf.x0, tf.x1, tf),
+        SYS_MPROTECT => mprotect(tf.x0, tf.x1, tf.x2, tf),
+        SYS_BLOCK if tf.is_kernel() => block(tf.x0 as *mut Option<EventPollFn>, tf),
+        _ => fail(Errno::NoSys, tf),
+    }
 }
@@ -1,3 +1,20 @@
+/// SPSR_EL1 value that returns to AArch64 EL0 using SP_EL0 (M[4:0] = 0) with
+/// the D, A, I and F bits clear, so user processes take interrupts.
+const SPSR_EL0T: u64 = 0;
+
+/// SPSR_EL1 value that returns to AArch64 EL1 using SP_EL1 (M[4:0] = 0b00101)
+/// with the D, A, I and F bits clear, so the kernel takes interrupts.
+const SPSR_EL1H: u64 = 0b0101;
+
+/// The mode bits, M[4:0], of SPSR_EL1.
+const SPSR_MODE: u64 = 0b1_1111;
+
+/// The condition flags, NZCV, of SPSR_EL1.
+const SPSR_NZCV: u64 = 0b1111 << 28;
+
+/// The IRQ mask bit, I, of SPSR_EL1.
+const SPSR_IRQ_MASKED: u64 = 1 << 7;
+
 #[repr(C)]
 #[derive(Default, Debug, Copy, Clone)]
 pub struct TrapFrame {
@@ -5,43 +22,10 @@ pub struct TrapFrame {
     pub(crate) elr: u64,
     /// EL1 Saved Program Status Register
     pub(crate) spsr: u64,
-    /// EL0 Stack Pointer Register
+    /// EL0 Stack Pointer Register, or SP_EL1 if the frame returns to EL1h
     pub(crate) sp: u64,
     /// EL0 Thread ID Register
     pub(crate) tpidr: u64,
-    /// 128-bit floating point registers
-    pub(crate) q0: u128,
-    pub(crate) q1: u128,
-    pub(crate) q2: u128,
-    pub(crate) q3: u128,
-    pub(crate) q4: u128,
-    pub(crate) q5: u128,
-    pub(crate) q6: u128,
-    pub(crate) q7: u128,
-    pub(crate) q8: u128,
-    pub(crate) q9: u128,
-    pub(crate) q10: u128,
-    pub(crate) q11: u128,
-    pub(crate) q12: u128,
-    pub(crate) q13: u128,
-    pub(crate) q14: u128,
-    pub(crate) q15: u128,
-    pub(crate) q16: u128,
-    pub(crate) q17: u128,
-    pub(crate) q18: u128,
-    pub(crate) q19: u128,
-    pub(crate) q20: u128,
-    pub(crate) q21: u128,
-    pub(crate) q22: u128,
-    pub(crate) q23: u128,
-    pub(crate) q24: u128,
-    pub(crate) q25: u128,
-    pub(crate) q26: u128,
-    pub(crate) q27: u128,
-    pub(crate) q28: u128,
-    pub(crate) q29: u128,
-    pub(crate) q30: u128,
-    pub(crate) q31: u128,
     /// 64-bit registers
     pub(crate) x1: u64,
     pub(crate) x2: u64,
@@ -78,44 +62,83 @@ pub struct TrapFrame {
 }
 
 impl TrapFrame {
+    /// Sets up the frame to return to EL0 at `elr` with the stack pointer
+    /// `sp` and interrupts unmasked.
+    #[cfg_attr(test, allow(dead_code))]
+    pub(crate) fn set_user_entry(&mut self, elr: u64, sp: u64) {
+        self.elr = elr;
+        self.spsr = SPSR_EL0T;
+        self.sp = sp;
+    }
+
+    /// Sets up the frame to return to EL1h at `elr` with the stack pointer
+    /// `sp` and interrupts unmasked.
+    ///
+    /// The exception level's stack pointer, SP_EL1, is not part of the saved
+    /// state, so for frames that return to EL1h, `sp` holds it instead of
+    /// SP_EL0. See `TrapFrame::restore_address()`.
+    pub(crate) fn set_kernel_entry(&mut self, elr: u64, sp: u64) {
+        self.elr = elr;
+        self.spsr = SPSR_EL1H;
+        self.sp = sp;
+    }
+
+    /// Returns `true` if the frame returns to EL1h, to a kernel thread or an
+    /// idle task, rather than to a user process at EL0.
+    pub(crate) fn is_kernel(&self) -> bool {
+        self.spsr & SPSR_MODE == SPSR_EL1H
+    }
+
+    /// Makes the frame, which user code may have changed, return to EL0 with
+    /// interrupts unmasked, keeping only the condition flags of its SPSR.
+    pub(crate) fn sanitize_user(&mut self) {
+        self.spsr = (self.spsr & SPSR_NZCV) | SPSR_EL0T;
+    }
+
+    /// Returns `true` if the frame returns with IRQs masked, as exception
+    /// handlers run.
+    pub(crate) fn irqs_masked(&self) -> bool {
+        self.spsr & SPSR_IRQ_MASKED != 0
+    }
+
+    /// Returns the address the frame must be restored from. Returning from an
+    /// exception leaves SP_EL1 just past the restored frame.
+    ///
+    /// Frames that return to EL1h resume with their own stack pointer, `sp`,
+    /// so they are restored from just below it. Frames that return to EL0 are
+    /// restored from the top of the process's kernel stack, `kernel_stack_top`,
+    /// which is where its next exception is taken.
+    pub(crate) fn restore_address(&self, kernel_stack_top: u64) -> u64 {
+        let top = if self.is_kernel() {
+            self.sp
+        } else {
+            kernel_stack_top
+        };
+        top - core::mem::size_of::<TrapFrame>() as u64
+    }
+
+    /// Copies the frame at `tf` to its restore address on this core and
+    /// returns the copy. See `TrapFrame::restore_address()`. Processes
+    /// without a kernel stack take exceptions from EL0 on the core's stack.
+    ///
+    /// # Safety
+    ///
+    /// `tf` must be valid, and nothing in use may live at the restore address.
+    #[cfg(not(test))]
+    pub(crate) unsafe fn relocate(tf: *const TrapFrame) -> *mut TrapFrame {
+        let kernel_stack_top = crate::process::kernel_stack_top()
+            .unwrap_or_else(|| crate::cpu::stack_end(crate::cpu::cpu_id()) as u64);
+        let dst = (*tf).restore_address(kernel_stack_top) as *mut TrapFrame;
+        core::ptr::copy(tf, dst, 1);
+        dst
+    }
+
     pub(crate) fn zeroed() -> Self {
         Self {
             elr: 0,
             spsr: 0,
             sp: 0,
             tpidr: 0,
-            q0: 0,
-            q1: 0,
-            q2: 0,
-            q3: 0,
-            q4: 0,
-            q5: 0,
-            q6: 0,
-            q7: 0,
-            q8: 0,
-            q9: 0,
-            q10: 0,
-            q11: 0,
-            q12: 0,
-            q13: 0,
-            q14: 0,
-            q15: 0,
-            q16: 0,
-            q17: 0,
-            q18: 0,
-            q19: 0,
-            q20: 0,
-            q21: 0,
-            q22: 0,
-            q23: 0,
-            q24: 0,
-            q25: 0,
-            q26: 0,
-            q27: 0,
-            q28: 0,
-            q29: 0,
-            q30: 0,
-            q31: 0,
             x1: 0,
             x2: 0,
             x3: 0,
@@ -151,3 +174,6 @@ impl TrapFrame {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,45 @@
+use super::TrapFrame;
+
+const SIZE: u64 = core::mem::size_of::<TrapFrame>() as u64;
+
+#[test]
+fn user_frames_restore_from_the_core_stack() {
+    let mut tf = TrapFrame::zeroed();
+    tf.set_user_entry(0x8_0000, 0x20_0000);
+    assert!(!tf.is_kernel());
+    assert_eq!(tf.restore_address(0x1_0000), 0x1_0000 - SIZE);
+}
+
+#[test]
+fn kernel_frames_restore_below_their_stack_pointer() {
+    let mut tf = TrapFrame::zeroed();
+    tf.set_kernel_entry(0x8_0000, 0x20_0000);
+    assert!(tf.is_kernel());
+    assert!(!tf.irqs_masked());
+    assert_eq!(tf.restore_address(0x1_0000), 0x20_0000 - SIZE);
+}
+
+#[test]
+fn handlers_run_with_irqs_masked() {
+    let mut tf = TrapFrame::zeroed();
+    tf.spsr = 0x3c5;
+    assert!(tf.is_kernel());
+    assert!(tf.irqs_masked());
+}
+
+#[test]
+fn frame_leaves_out_fp_registers() {
+    // ELR, SPSR, SP_EL0, TPIDR_EL0, then x1..x29, an unused slot, x30 and x0.
+    assert_eq!(SIZE, 0x120);
+}
+
+#[test]
+fn sanitized_frames_return_to_user_space() {
+    let mut tf = TrapFrame::zeroed();
+    tf.set_kernel_entry(0x8_0000, 0x20_0000);
+    tf.spsr |= 0xf000_03c0;
+    tf.sanitize_user();
+    assert!(!tf.is_kernel());
+    assert!(!tf.irqs_masked());
+    assert_eq!(tf.spsr, 0xf000_0000, "the condition flags are kept");
+}
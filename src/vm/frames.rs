@@ -0,0 +1,61 @@
+use alloc::alloc::Layout;
+use core::fmt;
+use core::ptr::NonNull;
+
+use super::PAGE_SIZE;
+
+/// Page-aligned, zeroed frames, shared by the regions that map them through
+/// an `Arc`. They are freed with the last region.
+pub struct Frames {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+// The frames are only accessed through the addresses they are mapped at.
+unsafe impl Send for Frames {}
+unsafe impl Sync for Frames {}
+
+impl Frames {
+    /// Allocates `size` bytes of zeroed frames, which must be a multiple of
+    /// `PAGE_SIZE`. Returns `None` if there is no memory.
+    pub fn new(size: usize) -> Option<Frames> {
+        assert!(size != 0 && size & (PAGE_SIZE - 1) == 0, "frames of {size} bytes");
+        let raw_ptr = unsafe { alloc::alloc::alloc_zeroed(Self::layout(size)) };
+        NonNull::new(raw_ptr).map(|ptr| Frames { ptr, size })
+    }
+
+    fn layout(size: usize) -> Layout {
+        Layout::from_size_align(size, PAGE_SIZE).expect("frames layout")
+    }
+
+    /// Returns the address of the first frame.
+    pub fn start(&self) -> u64 {
+        self.ptr.as_ptr() as u64
+    }
+
+    /// Returns the size of the frames in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Zeroes the bytes at `offset..offset + len`.
+    pub fn zero(&self, offset: usize, len: usize) {
+        assert!(offset + len <= self.size, "zeroing past the frames");
+        unsafe { self.ptr.as_ptr().add(offset).write_bytes(0, len) }
+    }
+}
+
+impl Drop for Frames {
+    fn drop(&mut self) {
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), Self::layout(self.size)) }
+    }
+}
+
+impl fmt::Debug for Frames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frames")
+            .field("start", &format_args!("{:#x}", self.start()))
+            .field("size", &self.size)
+            .finish()
+    }
+}
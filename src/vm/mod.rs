@@ -1,3 +1,48 @@
+//! Memory management.
+//!
+//! The kernel runs without an MMU: addresses are physical, and every process
+//! sees all of memory. Regions record the permissions they are mapped with,
+//! but they are not enforced until the kernel has page tables.
+
 mod address;
+mod frames;
+mod protection;
+pub mod shm;
+pub mod user;
+pub mod vma;
+
+#[cfg(test)]
+mod tests;
 
 pub use self::address::{PhysicalAddr, VirtualAddr};
+pub use self::frames::Frames;
+pub use self::protection::Protection;
+#[cfg_attr(test, allow(unused_imports))]
+pub use self::vma::{Backing, Vma, VmaList};
+
+/// The size of a page, the granularity memory is mapped with.
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// The largest region a process can map is 64MiB.
+pub const MAX_MAP_SIZE: usize = 64 << 20;
+
+/// Why a memory operation failed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The size is `0` or too large, or an address is not page-aligned.
+    Invalid,
+    /// There is no memory for the frames, or the process maps too many
+    /// regions.
+    NoMemory,
+    /// The region cannot be mapped with these permissions.
+    PermissionDenied,
+    /// The region overlaps one that is already mapped.
+    Overlap,
+    /// Part of the range is not mapped.
+    NotMapped,
+    /// User memory cannot be accessed: it is not mapped, or not with the
+    /// permissions the access needs.
+    Fault,
+    /// A string in user memory is longer than allowed.
+    TooLong,
+}
@@ -0,0 +1,67 @@
+use core::fmt;
+
+use crate::elf::Flags;
+
+/// Access permissions of a memory region. The bits are those of `mmap`'s
+/// `PROT_*` flags.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Protection(u64);
+
+impl Protection {
+    pub const READ: Protection = Protection(1);
+    pub const WRITE: Protection = Protection(2);
+    pub const EXECUTE: Protection = Protection(4);
+    pub const READ_WRITE: Protection = Protection(3);
+
+    /// Returns the permissions `bits` stands for, or `None` if it has bits
+    /// set that are not permissions.
+    pub fn from_bits(bits: u64) -> Option<Protection> {
+        let all = Protection::READ | Protection::WRITE | Protection::EXECUTE;
+        (bits & !all.0 == 0).then_some(Protection(bits))
+    }
+
+    /// Returns the permissions as `PROT_*` flags.
+    #[allow(dead_code)] // not used yet.
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if every permission in `other` is set in `self`.
+    pub fn contains(self, other: Protection) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl From<Flags> for Protection {
+    fn from(flags: Flags) -> Protection {
+        [
+            (Flags::READ, Protection::READ),
+            (Flags::WRITE, Protection::WRITE),
+            (Flags::EXECUTE, Protection::EXECUTE),
+        ]
+        .into_iter()
+        .filter(|&(flag, _)| flags.contains(flag))
+        .fold(Protection::default(), |prot, (_, allowed)| prot | allowed)
+    }
+}
+
+impl core::ops::BitOr for Protection {
+    type Output = Protection;
+
+    fn bitor(self, other: Protection) -> Protection {
+        Protection(self.0 | other.0)
+    }
+}
+
+impl fmt::Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |flag, c| if self.contains(flag) { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(Protection::READ, 'r'),
+            flag(Protection::WRITE, 'w'),
+            flag(Protection::EXECUTE, 'x')
+        )
+    }
+}
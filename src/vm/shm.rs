@@ -0,0 +1,55 @@
+//! Shared memory: frames that several processes map, to exchange data without
+//! copying it through the kernel.
+//!
+//! `SharedMemory::new()` allocates zeroed frames and returns a handle to them.
+//! The handle is a `File`, so processes share it like any other: by `fork`,
+//! or by passing it along in an IPC message. A process maps the frames with
+//! `Vma::file()`. The frames are reference counted, and freed once no handle
+//! or mapping refers to them anymore.
+//!
+//! Without an MMU, the frames appear at the same address in every process,
+//! and a mapping's permissions are recorded but not enforced.
+
+use alloc::sync::Arc;
+
+use super::{Error, Frames, MAX_MAP_SIZE, PAGE_SIZE};
+use crate::file::File;
+
+/// A handle to a shared memory region.
+#[derive(Debug)]
+pub struct SharedMemory {
+    frames: Arc<Frames>,
+}
+
+impl SharedMemory {
+    /// Returns a handle to a new region of at least `size` bytes, rounded up
+    /// to a multiple of `PAGE_SIZE`. The region starts out zeroed.
+    pub fn new(size: usize) -> Result<SharedMemory, Error> {
+        if size == 0 || size > MAX_MAP_SIZE {
+            return Err(Error::Invalid);
+        }
+        let frames = Frames::new(size.next_multiple_of(PAGE_SIZE)).ok_or(Error::NoMemory)?;
+        Ok(SharedMemory {
+            frames: Arc::new(frames),
+        })
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> usize {
+        self.frames.size()
+    }
+
+    /// Returns the region's frames.
+    pub(super) fn frames(&self) -> &Arc<Frames> {
+        &self.frames
+    }
+}
+
+impl File for SharedMemory {
+    fn as_shared_memory(&self) -> Option<&SharedMemory> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,58 @@
+use alloc::sync::Arc;
+
+use super::SharedMemory;
+use crate::file::File;
+use crate::vm::{Error, Protection, Vma, MAX_MAP_SIZE, PAGE_SIZE};
+
+#[test]
+fn regions_are_whole_zeroed_pages() {
+    let shm = SharedMemory::new(100).unwrap();
+    assert_eq!(shm.size(), PAGE_SIZE);
+    let start = shm.frames().start();
+    assert_eq!(start as usize % PAGE_SIZE, 0);
+    let bytes = unsafe { core::slice::from_raw_parts(start as *const u8, shm.size()) };
+    assert!(bytes.iter().all(|&byte| byte == 0));
+
+    assert_eq!(SharedMemory::new(3 * PAGE_SIZE).unwrap().size(), 3 * PAGE_SIZE);
+    assert_eq!(SharedMemory::new(0).unwrap_err(), Error::Invalid);
+    assert_eq!(SharedMemory::new(MAX_MAP_SIZE + 1).unwrap_err(), Error::Invalid);
+}
+
+#[test]
+fn mappings_share_frames() {
+    let shm: Arc<dyn File> = Arc::new(SharedMemory::new(PAGE_SIZE).unwrap());
+    let producer = Vma::file(shm.clone(), 0, PAGE_SIZE, Protection::READ_WRITE).unwrap();
+    let consumer = Vma::file(shm.clone(), 0, PAGE_SIZE, Protection::READ).unwrap();
+    assert_eq!(producer.start(), consumer.start());
+    assert_eq!((producer.prot, consumer.prot), (Protection::READ_WRITE, Protection::READ));
+
+    unsafe { (producer.start() as *mut u64).write(0xfeed) };
+    assert_eq!(unsafe { (consumer.start() as *const u64).read() }, 0xfeed);
+    let exec = Protection::READ | Protection::EXECUTE;
+    assert_eq!(Vma::file(shm, 0, PAGE_SIZE, exec).unwrap_err(), Error::PermissionDenied);
+}
+
+#[test]
+fn frames_live_while_referenced() {
+    let shm = SharedMemory::new(PAGE_SIZE).unwrap();
+    let frames = Arc::downgrade(shm.frames());
+    let handle: Arc<dyn File> = Arc::new(shm);
+    let mapping = Vma::file(handle.clone(), 0, PAGE_SIZE, Protection::READ_WRITE).unwrap();
+    let forked = mapping.clone();
+
+    drop(handle);
+    assert!(frames.upgrade().is_some(), "still mapped");
+    drop(mapping);
+    assert!(frames.upgrade().is_some(), "still mapped by the fork");
+    drop(forked);
+    assert!(frames.upgrade().is_none());
+}
+
+#[test]
+fn handles_are_files() {
+    let handle: Arc<dyn File> = Arc::new(SharedMemory::new(PAGE_SIZE).unwrap());
+    assert!(handle.as_port().is_none());
+    let shm = handle.as_shared_memory().unwrap();
+    assert_eq!(shm.size(), PAGE_SIZE);
+    assert!(handle.write(b"no").is_err());
+}
@@ -0,0 +1,13 @@
+use super::Protection;
+
+#[test]
+fn protection_from_bits() {
+    assert_eq!(Protection::from_bits(0), Some(Protection::default()));
+    assert_eq!(Protection::from_bits(3), Some(Protection::READ | Protection::WRITE));
+    assert_eq!(Protection::READ_WRITE, Protection::READ | Protection::WRITE);
+    assert_eq!(Protection::from_bits(8), None);
+    let all = Protection::from_bits(7).unwrap();
+    assert!(all.contains(Protection::EXECUTE | Protection::WRITE));
+    assert!(!Protection::READ.contains(Protection::WRITE));
+    assert_eq!(alloc::format!("{all} {}", Protection::READ), "rwx r--");
+}
@@ -0,0 +1,208 @@
+//! Access to the memory of user processes.
+//!
+//! System calls get addresses from user code, which may point anywhere.
+//! Before the kernel touches one, it checks that the process has the memory
+//! mapped with the permissions the access needs, so that a bad address fails
+//! with `Error::Fault` instead of faulting the kernel or reaching into memory
+//! the process does not own. A `UserPtr` or `UserSlice` stands for memory that
+//! was not checked yet, and the kernel only reaches it by copying values and
+//! bytes in and out, which checks it.
+//!
+//! Without an MMU the check is all there is: once it passes, the memory is
+//! accessed directly. Memory stays mapped while the process is in a system
+//! call, since only the process itself can unmap it.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::ops::Range;
+
+use super::{Error, Protection};
+
+/// The memory a process may access.
+pub trait UserMemory {
+    /// Returns the end of the memory starting at `addr` that the process may
+    /// access with the permissions `prot`, or `None` if it may not access
+    /// `addr`.
+    fn accessible_end(&self, addr: u64, prot: Protection) -> Option<u64>;
+}
+
+/// Returns the end of the furthest of `regions` that contains `addr` and
+/// allows `prot`, for implementing `UserMemory::accessible_end()`.
+pub fn accessible_end(
+    regions: impl Iterator<Item = (Range<u64>, Protection)>,
+    addr: u64,
+    prot: Protection,
+) -> Option<u64> {
+    regions
+        .filter(|(range, allowed)| range.contains(&addr) && allowed.contains(prot))
+        .map(|(range, _)| range.end)
+        .max()
+}
+
+/// Bytes in the memory of a user process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserSlice {
+    addr: u64,
+    len: usize,
+}
+
+impl UserSlice {
+    /// Returns the `len` bytes at `addr`.
+    pub fn new(addr: u64, len: usize) -> UserSlice {
+        UserSlice { addr, len }
+    }
+
+    /// Returns the number of bytes.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the bytes from `offset` on, or none if there are fewer.
+    pub fn skip(&self, offset: usize) -> UserSlice {
+        let offset = offset.min(self.len);
+        UserSlice::new(self.addr.wrapping_add(offset as u64), self.len - offset)
+    }
+
+    /// Returns the first `len` bytes, or all of them if there are fewer.
+    pub fn prefix(&self, len: usize) -> UserSlice {
+        UserSlice::new(self.addr, self.len.min(len))
+    }
+
+    /// Checks that the process whose memory is `memory` may access all of
+    /// the bytes with the permissions `prot`. No bytes are always accessible.
+    pub fn check(&self, memory: &impl UserMemory, prot: Protection) -> Result<(), Error> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let end = self.addr.checked_add(self.len as u64).ok_or(Error::Fault)?;
+        let mut addr = self.addr;
+        while addr < end {
+            addr = memory.accessible_end(addr, prot).ok_or(Error::Fault)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes for reading.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must have been checked with `Protection::READ`, and must stay
+    /// mapped while the slice is used.
+    unsafe fn as_slice<'a>(&self) -> &'a [u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        core::slice::from_raw_parts(self.addr as *const u8, self.len)
+    }
+
+    /// Returns the bytes for writing.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must have been checked with `Protection::WRITE`, must stay
+    /// mapped while the slice is used, and may not be aliased.
+    unsafe fn as_mut_slice<'a>(&self) -> &'a mut [u8] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        core::slice::from_raw_parts_mut(self.addr as *mut u8, self.len)
+    }
+}
+
+/// Copies `src`, in the memory of the process whose memory is `memory`, into
+/// `dst`, which must be as long.
+pub fn copy_from_user(memory: &impl UserMemory, src: UserSlice, dst: &mut [u8]) -> Result<(), Error> {
+    assert_eq!(src.len, dst.len(), "copy of mismatched lengths");
+    src.check(memory, Protection::READ)?;
+    dst.copy_from_slice(unsafe { src.as_slice() });
+    Ok(())
+}
+
+/// Copies `src` into `dst`, in the memory of the process whose memory is
+/// `memory`, which must be as long.
+pub fn copy_to_user(memory: &impl UserMemory, dst: UserSlice, src: &[u8]) -> Result<(), Error> {
+    assert_eq!(dst.len, src.len(), "copy of mismatched lengths");
+    dst.check(memory, Protection::WRITE)?;
+    unsafe { dst.as_mut_slice() }.copy_from_slice(src);
+    Ok(())
+}
+
+/// Copies the NUL-terminated string at `addr`, in the memory of the process
+/// whose memory is `memory`, and returns its bytes without the NUL. Fails
+/// with `Error::TooLong` if there is no NUL in the first `max_len` bytes.
+pub fn copy_str_from_user(memory: &impl UserMemory, addr: u64, max_len: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    let mut addr = addr;
+    while bytes.len() < max_len {
+        let end = memory.accessible_end(addr, Protection::READ).ok_or(Error::Fault)?;
+        let len = ((end - addr) as usize).min(max_len - bytes.len());
+        let chunk = unsafe { UserSlice::new(addr, len).as_slice() };
+        if let Some(nul) = chunk.iter().position(|&byte| byte == 0) {
+            bytes.extend_from_slice(&chunk[..nul]);
+            return Ok(bytes);
+        }
+        bytes.extend_from_slice(chunk);
+        addr = end;
+    }
+    Err(Error::TooLong)
+}
+
+/// A `T` in the memory of a user process.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UserPtr<T> {
+    addr: u64,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> Clone for UserPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UserPtr<T> {}
+
+impl<T: Copy> UserPtr<T> {
+    /// Returns the `T` at `addr`, which need not be aligned.
+    pub fn new(addr: u64) -> UserPtr<T> {
+        UserPtr {
+            addr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the address is `0`, which system calls take to mean
+    /// no value.
+    pub fn is_null(self) -> bool {
+        self.addr == 0
+    }
+
+    /// Returns the `T` `count` places after this one, as in an array.
+    pub fn add(self, count: usize) -> UserPtr<T> {
+        UserPtr::new(self.addr.wrapping_add((count * size_of::<T>()) as u64))
+    }
+
+    /// Returns the bytes of the value.
+    pub fn bytes(self) -> UserSlice {
+        UserSlice::new(self.addr, size_of::<T>())
+    }
+
+    /// Copies the value out of the memory of the process whose memory is
+    /// `memory`.
+    pub fn read(self, memory: &impl UserMemory) -> Result<T, Error> {
+        self.bytes().check(memory, Protection::READ)?;
+        Ok(unsafe { (self.addr as *const T).read_unaligned() })
+    }
+
+    /// Copies `value` into the memory of the process whose memory is
+    /// `memory`.
+    pub fn write(self, memory: &impl UserMemory, value: T) -> Result<(), Error> {
+        self.bytes().check(memory, Protection::WRITE)?;
+        unsafe { (self.addr as *mut T).write_unaligned(value) };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
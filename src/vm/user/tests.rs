@@ -0,0 +1,129 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::{copy_from_user, copy_str_from_user, copy_to_user, UserMemory, UserPtr, UserSlice};
+use crate::vm::{Error, Protection};
+
+/// The memory of a process with the given regions.
+struct Regions(Vec<(Range<u64>, Protection)>);
+
+impl UserMemory for Regions {
+    fn accessible_end(&self, addr: u64, prot: Protection) -> Option<u64> {
+        super::accessible_end(self.0.iter().cloned(), addr, prot)
+    }
+}
+
+/// Returns the address range of `buf`.
+fn range(buf: &[u8]) -> Range<u64> {
+    let start = buf.as_ptr() as u64;
+    start..start + buf.len() as u64
+}
+
+#[test]
+fn slices_must_be_mapped() {
+    let buf = vec![0u8; 64];
+    let memory = Regions(vec![(range(&buf), Protection::READ_WRITE)]);
+    let start = range(&buf).start;
+
+    assert_eq!(UserSlice::new(start, 64).check(&memory, Protection::READ_WRITE), Ok(()));
+    assert_eq!(UserSlice::new(start + 60, 4).check(&memory, Protection::READ), Ok(()));
+    assert_eq!(UserSlice::new(start + 60, 5).check(&memory, Protection::READ), Err(Error::Fault));
+    assert_eq!(UserSlice::new(start - 1, 2).check(&memory, Protection::READ), Err(Error::Fault));
+    assert_eq!(UserSlice::new(0, 1).check(&memory, Protection::READ), Err(Error::Fault));
+    assert_eq!(UserSlice::new(u64::MAX, 2).check(&memory, Protection::READ), Err(Error::Fault));
+    assert_eq!(UserSlice::new(0, 0).check(&memory, Protection::READ_WRITE), Ok(()), "nothing to access");
+}
+
+#[test]
+fn slices_may_span_regions() {
+    let buf = vec![0u8; 64];
+    let start = range(&buf).start;
+    let memory = Regions(vec![
+        (start..start + 16, Protection::READ_WRITE),
+        (start + 16..start + 32, Protection::READ),
+        (start + 40..start + 64, Protection::READ_WRITE),
+    ]);
+
+    assert_eq!(UserSlice::new(start, 32).check(&memory, Protection::READ), Ok(()));
+    let spans_read_only = UserSlice::new(start + 8, 16).check(&memory, Protection::WRITE);
+    assert_eq!(spans_read_only, Err(Error::Fault));
+    assert_eq!(UserSlice::new(start, 48).check(&memory, Protection::READ), Err(Error::Fault), "gap");
+}
+
+#[test]
+fn copies_check_permissions() {
+    let mut buf = vec![0u8; 32];
+    let start = range(&buf).start;
+    buf[..5].copy_from_slice(b"hello");
+    let memory = Regions(vec![
+        (start..start + 16, Protection::READ),
+        (start + 16..start + 32, Protection::READ_WRITE),
+    ]);
+
+    let mut hello = [0; 5];
+    copy_from_user(&memory, UserSlice::new(start, 5), &mut hello).unwrap();
+    assert_eq!(&hello, b"hello");
+    copy_to_user(&memory, UserSlice::new(start + 16, 5), b"world").unwrap();
+    assert_eq!(&buf[16..21], b"world");
+
+    let refused = copy_to_user(&memory, UserSlice::new(start, 5), b"oops!");
+    assert_eq!(refused, Err(Error::Fault));
+    assert_eq!(&buf[..5], b"hello", "nothing is copied");
+    let mut past = [0; 8];
+    let past_end = copy_from_user(&memory, UserSlice::new(start + 28, 8), &mut past);
+    assert_eq!(past_end, Err(Error::Fault));
+}
+
+#[test]
+fn slices_are_cut() {
+    let slice = UserSlice::new(0x1000, 16);
+    assert_eq!(slice.skip(4), UserSlice::new(0x1004, 12));
+    assert_eq!(slice.skip(20), UserSlice::new(0x1010, 0));
+    assert_eq!(slice.skip(4).prefix(8), UserSlice::new(0x1004, 8));
+    assert_eq!(slice.prefix(20).size(), 16);
+}
+
+#[test]
+fn pointers_read_and_write_values() {
+    let buf = vec![0u8; 24];
+    let start = range(&buf).start;
+    let memory = Regions(vec![(range(&buf), Protection::READ_WRITE)]);
+
+    let array = UserPtr::<u64>::new(start + 1);
+    array.write(&memory, 7).unwrap();
+    array.add(1).write(&memory, 8).unwrap();
+    assert_eq!(array.add(1).read(&memory), Ok(8));
+    assert_eq!(array.add(2).read(&memory), Err(Error::Fault));
+    assert_eq!(u64::from_le_bytes(buf[1..9].try_into().unwrap()), 7);
+    assert!(UserPtr::<u64>::new(0).is_null());
+
+    let read_only = Regions(vec![(range(&buf), Protection::READ)]);
+    assert_eq!(array.write(&read_only, 9), Err(Error::Fault));
+}
+
+#[test]
+fn strings_are_bounded() {
+    let buf = b"init\0sleeper".to_vec();
+    let start = range(&buf).start;
+    let memory = Regions(vec![(range(&buf), Protection::READ)]);
+
+    assert_eq!(copy_str_from_user(&memory, start, 64).unwrap(), b"init");
+    assert_eq!(copy_str_from_user(&memory, start + 4, 64).unwrap(), b"");
+    assert_eq!(copy_str_from_user(&memory, start, 4), Err(Error::TooLong));
+    assert_eq!(copy_str_from_user(&memory, start, 5).unwrap(), b"init");
+    let unterminated = copy_str_from_user(&memory, start + 5, 64);
+    assert_eq!(unterminated, Err(Error::Fault), "runs off the mapped memory");
+    assert_eq!(copy_str_from_user(&memory, 0, 64), Err(Error::Fault));
+}
+
+#[test]
+fn strings_may_span_regions() {
+    let buf = b"a longer string\0".to_vec();
+    let start = range(&buf).start;
+    let memory = Regions(vec![
+        (start..start + 4, Protection::READ),
+        (start + 4..start + 16, Protection::READ_WRITE),
+    ]);
+    assert_eq!(copy_str_from_user(&memory, start, 64).unwrap(), b"a longer string");
+}
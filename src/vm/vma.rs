@@ -0,0 +1,322 @@
+//! The memory regions of a process.
+//!
+//! A process's `VmaList` holds the regions it mapped with `mmap` or
+//! `shm_map` as `Vma`s, which do not overlap, and its heap. A region is backed
+//! by anonymous memory or by a file, such as a shared memory region, and holds
+//! a reference to its frames: unmapping part of a region splits it, and the
+//! frames are freed with the last part.
+//!
+//! The heap is reserved in one piece the first time the program break is
+//! asked for, and `brk` moves the break within it.
+//!
+//! Neither `mmap` nor `brk` is demand paged: without page tables nothing can
+//! fault a page in, so a region's frames are allocated when it is mapped, and
+//! the whole heap when it is reserved. Demand paging waits for the MMU.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::{Error, Frames, Protection, MAX_MAP_SIZE, PAGE_SIZE};
+use crate::file::File;
+
+/// The most regions a process can map at once, not counting its heap.
+pub const MAX_VMAS: usize = 64;
+
+/// The size of the heap, the most the program break can move, is 8MiB.
+pub const HEAP_SIZE: usize = 8 << 20;
+
+/// What a region maps.
+#[derive(Debug, Clone)]
+pub enum Backing {
+    /// Zeroed memory of its own.
+    Anonymous,
+    /// The memory of a file: a shared memory region.
+    File(#[allow(dead_code)] Arc<dyn File>),
+}
+
+/// A mapped memory region.
+#[derive(Debug, Clone)]
+pub struct Vma {
+    start: u64,
+    size: usize,
+    /// The permissions the region is mapped with. Not enforced yet.
+    pub prot: Protection,
+    pub backing: Backing,
+    /// The frames the region maps, kept alive while it is.
+    #[allow(dead_code)]
+    frames: Arc<Frames>,
+}
+
+/// Returns `len` rounded up to whole pages, or `Error::Invalid` if it is `0`
+/// or larger than `MAX_MAP_SIZE`.
+fn page_len(len: usize) -> Result<usize, Error> {
+    if len == 0 || len > MAX_MAP_SIZE {
+        return Err(Error::Invalid);
+    }
+    Ok(len.next_multiple_of(PAGE_SIZE))
+}
+
+impl Vma {
+    /// Returns a region of `len` bytes of newly allocated, zeroed memory,
+    /// rounded up to whole pages.
+    pub fn anonymous(len: usize, prot: Protection) -> Result<Vma, Error> {
+        let size = page_len(len)?;
+        let frames = Frames::new(size).ok_or(Error::NoMemory)?;
+        Ok(Vma {
+            start: frames.start(),
+            size,
+            prot,
+            backing: Backing::Anonymous,
+            frames: Arc::new(frames),
+        })
+    }
+
+    /// Returns a region mapping `len` bytes of `file`, rounded up to whole
+    /// pages, from `offset` on. The file must be a shared memory handle, and
+    /// the region may not be executable.
+    pub fn file(file: Arc<dyn File>, offset: usize, len: usize, prot: Protection) -> Result<Vma, Error> {
+        let shm = file.as_shared_memory().ok_or(Error::Invalid)?;
+        let size = page_len(len)?;
+        if offset & (PAGE_SIZE - 1) != 0 || size > shm.size().saturating_sub(offset) {
+            return Err(Error::Invalid);
+        }
+        if prot.contains(Protection::EXECUTE) {
+            return Err(Error::PermissionDenied);
+        }
+
+        let frames = shm.frames().clone();
+        Ok(Vma {
+            start: frames.start() + offset as u64,
+            size,
+            prot,
+            backing: Backing::File(file),
+            frames,
+        })
+    }
+
+    /// Returns the address the region starts at.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the address right after the region.
+    pub fn end(&self) -> u64 {
+        self.start + self.size as u64
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Splits the region at `addr`, keeping the part below it and returning
+    /// the rest.
+    fn split_off(&mut self, addr: u64) -> Vma {
+        let mut upper = self.clone();
+        upper.start = addr;
+        upper.size = (self.end() - addr) as usize;
+        self.size = (addr - self.start) as usize;
+        upper
+    }
+}
+
+/// The heap of a process: reserved frames that the program break moves in.
+#[derive(Debug, Clone)]
+struct Heap {
+    frames: Arc<Frames>,
+    brk: u64,
+}
+
+impl Heap {
+    fn reserved(&self) -> Range<u64> {
+        self.frames.start()..self.frames.start() + self.frames.size() as u64
+    }
+}
+
+/// The memory regions a process has mapped, and its heap.
+#[derive(Debug, Clone, Default)]
+pub struct VmaList {
+    vmas: BTreeMap<u64, Vma>,
+    heap: Option<Heap>,
+}
+
+/// Returns the end of the range `start..start + len`, rounded up to whole
+/// pages, or `Error::Invalid` if `start` is not page-aligned or `len` is `0`.
+fn page_range(start: u64, len: usize) -> Result<u64, Error> {
+    if start & (PAGE_SIZE as u64 - 1) != 0 || len == 0 {
+        return Err(Error::Invalid);
+    }
+    let len = len.checked_next_multiple_of(PAGE_SIZE).ok_or(Error::Invalid)?;
+    start.checked_add(len as u64).ok_or(Error::Invalid)
+}
+
+impl VmaList {
+    /// Adds `vma` and returns the address it is mapped at.
+    pub fn insert(&mut self, vma: Vma) -> Result<u64, Error> {
+        if self.vmas.len() >= MAX_VMAS {
+            return Err(Error::NoMemory);
+        }
+        if self.overlaps(vma.start, vma.end()) {
+            return Err(Error::Overlap);
+        }
+        let start = vma.start;
+        self.vmas.insert(start, vma);
+        Ok(start)
+    }
+
+    /// Returns `true` if a region or the heap overlaps `start..end`.
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.overlaps_heap(start, end)
+            || self
+                .vmas
+                .range(..end)
+                .next_back()
+                .is_some_and(|(_, vma)| vma.end() > start)
+    }
+
+    fn overlaps_heap(&self, start: u64, end: u64) -> bool {
+        self.heap.as_ref().is_some_and(|heap| {
+            let reserved = heap.reserved();
+            reserved.start < end && start < reserved.end
+        })
+    }
+
+    /// Returns the region that contains `addr`, if any.
+    pub fn get(&self, addr: u64) -> Option<&Vma> {
+        let (_, vma) = self.vmas.range(..=addr).next_back()?;
+        (addr < vma.end()).then_some(vma)
+    }
+
+    /// Returns the regions, lowest first. The heap is not one of them.
+    pub fn iter(&self) -> impl Iterator<Item = &Vma> {
+        self.vmas.values()
+    }
+
+    /// Returns the part of the heap below the program break, if the heap was
+    /// reserved.
+    pub fn heap(&self) -> Option<Range<u64>> {
+        self.heap.as_ref().map(|heap| heap.frames.start()..heap.brk)
+    }
+
+    /// Returns the addresses of `start` and `end` that lie inside a region
+    /// rather than at its start.
+    fn crossed(&self, start: u64, end: u64) -> Vec<u64> {
+        [start, end]
+            .into_iter()
+            .filter(|&addr| self.get(addr).is_some_and(|vma| vma.start != addr))
+            .collect()
+    }
+
+    /// Splits the regions at `start` and `end`, so that no region crosses
+    /// either.
+    fn split(&mut self, start: u64, end: u64) {
+        for addr in self.crossed(start, end) {
+            let (_, vma) = self.vmas.range_mut(..addr).next_back().expect("crossed region");
+            let upper = vma.split_off(addr);
+            self.vmas.insert(addr, upper);
+        }
+    }
+
+    /// Unmaps the pages in `start..start + len`, which may not be part of the
+    /// heap. Pages that are not mapped are skipped.
+    pub fn unmap(&mut self, start: u64, len: usize) -> Result<(), Error> {
+        let end = page_range(start, len)?;
+        if self.overlaps_heap(start, end) {
+            return Err(Error::Invalid);
+        }
+
+        let punched = self
+            .get(start)
+            .is_some_and(|vma| vma.start != start && vma.end() > end);
+        if punched && self.vmas.len() >= MAX_VMAS {
+            return Err(Error::NoMemory);
+        }
+
+        self.split(start, end);
+        let unmapped: Vec<u64> = self.vmas.range(start..end).map(|(&start, _)| start).collect();
+        for start in unmapped {
+            self.vmas.remove(&start);
+        }
+        Ok(())
+    }
+
+    /// Changes the permissions of the pages in `start..start + len` to
+    /// `prot`. Every page must be mapped, and none may be part of the heap.
+    pub fn protect(&mut self, start: u64, len: usize, prot: Protection) -> Result<(), Error> {
+        let end = page_range(start, len)?;
+        if self.overlaps_heap(start, end) {
+            return Err(Error::Invalid);
+        }
+
+        let mut addr = start;
+        while addr < end {
+            let vma = self.get(addr).ok_or(Error::NotMapped)?;
+            if matches!(vma.backing, Backing::File(_)) && prot.contains(Protection::EXECUTE) {
+                return Err(Error::PermissionDenied);
+            }
+            addr = vma.end();
+        }
+
+        if self.vmas.len() + self.crossed(start, end).len() > MAX_VMAS {
+            return Err(Error::NoMemory);
+        }
+        self.split(start, end);
+        for vma in self.vmas.range_mut(start..end).map(|(_, vma)| vma) {
+            vma.prot = prot;
+        }
+        Ok(())
+    }
+
+    /// Moves the program break to `brk` and returns it, or returns the
+    /// current break if `brk` is `0`. The break starts at the bottom of the
+    /// heap, which is reserved the first time it is asked for. Memory the
+    /// break moves over reads as zero.
+    pub fn brk(&mut self, brk: u64) -> Result<u64, Error> {
+        if self.heap.is_none() {
+            let frames = Frames::new(HEAP_SIZE).ok_or(Error::NoMemory)?;
+            let start = frames.start();
+            if self.overlaps(start, start + HEAP_SIZE as u64) {
+                return Err(Error::Overlap);
+            }
+            self.heap = Some(Heap {
+                frames: Arc::new(frames),
+                brk: start,
+            });
+        }
+
+        let heap = self.heap.as_mut().expect("reserved heap");
+        if brk == 0 {
+            return Ok(heap.brk);
+        }
+        let reserved = heap.reserved();
+        if !(reserved.start..=reserved.end).contains(&brk) {
+            return Err(Error::NoMemory);
+        }
+        if brk > heap.brk {
+            let offset = (heap.brk - reserved.start) as usize;
+            heap.frames.zero(offset, (brk - heap.brk) as usize);
+        }
+        heap.brk = brk;
+        Ok(brk)
+    }
+
+    /// Moves the program break by `increment` bytes and returns the previous
+    /// break. See `brk()`.
+    pub fn sbrk(&mut self, increment: i64) -> Result<u64, Error> {
+        let old = self.brk(0)?;
+        let new = old.checked_add_signed(increment).ok_or(Error::NoMemory)?;
+        self.brk(new)?;
+        Ok(old)
+    }
+
+    /// Unmaps every region and frees the heap.
+    pub fn clear(&mut self) {
+        self.vmas.clear();
+        self.heap = None;
+    }
+}
+
+#[cfg(test)]
+mod tests;
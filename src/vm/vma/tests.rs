@@ -0,0 +1,179 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use super::{Backing, Vma, VmaList, HEAP_SIZE, MAX_VMAS};
+use crate::file::{pipe, File};
+use crate::vm::shm::SharedMemory;
+use crate::vm::{Error, Protection, MAX_MAP_SIZE, PAGE_SIZE};
+
+const PAGE: u64 = PAGE_SIZE as u64;
+
+/// Returns the start, size and permissions of each region of `vmas`.
+fn regions(vmas: &VmaList) -> Vec<(u64, usize, Protection)> {
+    vmas.iter().map(|vma| (vma.start(), vma.size(), vma.prot)).collect()
+}
+
+#[test]
+fn anonymous_regions_are_zeroed_pages() {
+    let vma = Vma::anonymous(PAGE_SIZE + 1, Protection::READ_WRITE).unwrap();
+    assert_eq!(vma.size(), 2 * PAGE_SIZE);
+    assert_eq!(vma.start() % PAGE, 0);
+    assert!(matches!(vma.backing, Backing::Anonymous));
+    let bytes = unsafe { core::slice::from_raw_parts(vma.start() as *const u8, vma.size()) };
+    assert!(bytes.iter().all(|&byte| byte == 0));
+
+    assert_eq!(Vma::anonymous(0, Protection::READ_WRITE).unwrap_err(), Error::Invalid);
+    assert_eq!(Vma::anonymous(MAX_MAP_SIZE + 1, Protection::READ_WRITE).unwrap_err(), Error::Invalid);
+}
+
+#[test]
+fn file_regions_stay_within_the_file() {
+    let shm: Arc<dyn File> = Arc::new(SharedMemory::new(4 * PAGE_SIZE).unwrap());
+    let whole = Vma::file(shm.clone(), 0, 4 * PAGE_SIZE, Protection::READ).unwrap();
+    let tail = Vma::file(shm.clone(), 3 * PAGE_SIZE, 1, Protection::READ).unwrap();
+    assert_eq!(tail.start(), whole.start() + 3 * PAGE);
+    assert_eq!(tail.end(), whole.end());
+
+    let past = Vma::file(shm.clone(), 3 * PAGE_SIZE, PAGE_SIZE + 1, Protection::READ);
+    assert_eq!(past.unwrap_err(), Error::Invalid);
+    let unaligned = Vma::file(shm, 1, PAGE_SIZE, Protection::READ);
+    assert_eq!(unaligned.unwrap_err(), Error::Invalid);
+    let (reader, _) = pipe();
+    assert_eq!(Vma::file(reader, 0, PAGE_SIZE, Protection::READ).unwrap_err(), Error::Invalid);
+}
+
+#[test]
+fn regions_do_not_overlap() {
+    let shm: Arc<dyn File> = Arc::new(SharedMemory::new(4 * PAGE_SIZE).unwrap());
+    let mut vmas = VmaList::default();
+    let start = vmas.insert(Vma::file(shm.clone(), PAGE_SIZE, PAGE_SIZE, Protection::READ).unwrap());
+    let start = start.unwrap();
+
+    let same = Vma::file(shm.clone(), PAGE_SIZE, PAGE_SIZE, Protection::READ_WRITE).unwrap();
+    assert_eq!(vmas.insert(same), Err(Error::Overlap));
+    let below = Vma::file(shm.clone(), 0, 2 * PAGE_SIZE, Protection::READ).unwrap();
+    assert_eq!(vmas.insert(below), Err(Error::Overlap));
+    let around = Vma::file(shm.clone(), 0, 4 * PAGE_SIZE, Protection::READ).unwrap();
+    assert_eq!(vmas.insert(around), Err(Error::Overlap));
+
+    vmas.insert(Vma::file(shm.clone(), 0, PAGE_SIZE, Protection::READ).unwrap()).unwrap();
+    vmas.insert(Vma::file(shm, 2 * PAGE_SIZE, PAGE_SIZE, Protection::READ).unwrap()).unwrap();
+    assert_eq!(vmas.iter().count(), 3, "adjacent regions do not overlap");
+    assert_eq!(vmas.get(start + 10).unwrap().start(), start);
+    assert!(vmas.get(start + 3 * PAGE).is_none());
+}
+
+#[test]
+fn unmap_splits_regions() {
+    let mut vmas = VmaList::default();
+    let start = vmas.insert(Vma::anonymous(4 * PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+
+    vmas.unmap(start + PAGE, PAGE_SIZE).unwrap();
+    assert_eq!(
+        regions(&vmas),
+        [(start, PAGE_SIZE, Protection::READ_WRITE), (start + 2 * PAGE, 2 * PAGE_SIZE, Protection::READ_WRITE)]
+    );
+    vmas.unmap(start, 3 * PAGE_SIZE).expect("holes are skipped");
+    assert_eq!(regions(&vmas), [(start + 3 * PAGE, PAGE_SIZE, Protection::READ_WRITE)]);
+    vmas.unmap(start + 3 * PAGE, 1).unwrap();
+    assert!(vmas.get(start + 3 * PAGE).is_none());
+
+    assert_eq!(vmas.unmap(start + 1, PAGE_SIZE), Err(Error::Invalid));
+    assert_eq!(vmas.unmap(start, 0), Err(Error::Invalid));
+    assert_eq!(vmas.unmap(u64::MAX - PAGE + 1, 2 * PAGE_SIZE), Err(Error::Invalid));
+}
+
+#[test]
+fn frames_are_freed_with_the_last_part() {
+    let shm = SharedMemory::new(3 * PAGE_SIZE).unwrap();
+    let frames = Arc::downgrade(shm.frames());
+    let mut vmas = VmaList::default();
+    let start = vmas.insert(Vma::file(Arc::new(shm), 0, 3 * PAGE_SIZE, Protection::READ).unwrap());
+    let start = start.unwrap();
+
+    vmas.unmap(start + PAGE, PAGE_SIZE).unwrap();
+    vmas.unmap(start, PAGE_SIZE).unwrap();
+    assert!(frames.upgrade().is_some());
+    vmas.unmap(start + 2 * PAGE, PAGE_SIZE).unwrap();
+    assert!(frames.upgrade().is_none());
+}
+
+#[test]
+fn protect_changes_mapped_pages() {
+    let mut vmas = VmaList::default();
+    let start = vmas.insert(Vma::anonymous(3 * PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+
+    vmas.protect(start + PAGE, PAGE_SIZE, Protection::READ).unwrap();
+    assert_eq!(
+        regions(&vmas),
+        [
+            (start, PAGE_SIZE, Protection::READ_WRITE),
+            (start + PAGE, PAGE_SIZE, Protection::READ),
+            (start + 2 * PAGE, PAGE_SIZE, Protection::READ_WRITE),
+        ]
+    );
+    vmas.protect(start, 3 * PAGE_SIZE, Protection::READ | Protection::EXECUTE).unwrap();
+    assert!(vmas.iter().all(|vma| vma.prot.contains(Protection::EXECUTE)));
+
+    vmas.unmap(start + PAGE, PAGE_SIZE).unwrap();
+    assert_eq!(vmas.protect(start, 3 * PAGE_SIZE, Protection::READ), Err(Error::NotMapped));
+    assert_eq!(vmas.get(start).unwrap().prot, Protection::READ | Protection::EXECUTE, "unchanged");
+
+    let shm: Arc<dyn File> = Arc::new(SharedMemory::new(PAGE_SIZE).unwrap());
+    let start = vmas.insert(Vma::file(shm, 0, PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+    let exec = Protection::READ | Protection::EXECUTE;
+    assert_eq!(vmas.protect(start, PAGE_SIZE, exec), Err(Error::PermissionDenied));
+}
+
+#[test]
+fn regions_are_bounded() {
+    let mut vmas = VmaList::default();
+    let start = vmas.insert(Vma::anonymous(3 * PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+    for _ in 1..MAX_VMAS - 1 {
+        vmas.insert(Vma::anonymous(PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+    }
+    vmas.protect(start + 2 * PAGE, PAGE_SIZE, Protection::READ).expect("splits once");
+    let split_again = vmas.protect(start + PAGE, PAGE_SIZE, Protection::READ);
+    assert_eq!(split_again, Err(Error::NoMemory));
+    vmas.unmap(start, PAGE_SIZE).expect("unmapping the end of a region does not add one");
+    vmas.unmap(start + 2 * PAGE, PAGE_SIZE).unwrap();
+    let punch = vmas.insert(Vma::anonymous(3 * PAGE_SIZE, Protection::READ_WRITE).unwrap()).unwrap();
+    assert_eq!(vmas.unmap(punch + PAGE, PAGE_SIZE), Err(Error::NoMemory), "would add one");
+    let one_more = Vma::anonymous(PAGE_SIZE, Protection::READ_WRITE).unwrap();
+    assert_eq!(vmas.insert(one_more), Err(Error::NoMemory));
+}
+
+#[test]
+fn brk_moves_in_the_heap() {
+    let mut vmas = VmaList::default();
+    assert_eq!(vmas.heap(), None);
+    let start = vmas.brk(0).unwrap();
+    assert_eq!(vmas.heap(), Some(start..start));
+
+    assert_eq!(vmas.sbrk(100).unwrap(), start);
+    assert_eq!(vmas.brk(0).unwrap(), start + 100);
+    unsafe { (start as *mut u8).write_bytes(0xff, 100) };
+    assert_eq!(vmas.brk(start + 50).unwrap(), start + 50);
+    assert_eq!(vmas.sbrk(-50).unwrap(), start + 50);
+    assert_eq!(vmas.brk(start + 100).unwrap(), start + 100);
+    let bytes = unsafe { core::slice::from_raw_parts(start as *const u8, 100) };
+    assert!(bytes.iter().all(|&byte| byte == 0), "grown memory reads as zero");
+
+    let end = start + HEAP_SIZE as u64;
+    assert_eq!(vmas.brk(end).unwrap(), end);
+    assert_eq!(vmas.brk(end + 1), Err(Error::NoMemory));
+    assert_eq!(vmas.brk(start - 1), Err(Error::NoMemory));
+    assert_eq!(vmas.sbrk(1), Err(Error::NoMemory));
+    assert_eq!(vmas.brk(0).unwrap(), end, "unchanged");
+}
+
+#[test]
+fn heap_is_not_unmapped() {
+    let mut vmas = VmaList::default();
+    let start = vmas.brk(0).unwrap();
+    assert_eq!(vmas.unmap(start, PAGE_SIZE), Err(Error::Invalid));
+    assert_eq!(vmas.protect(start, PAGE_SIZE, Protection::READ), Err(Error::Invalid));
+
+    vmas.clear();
+    assert_eq!(vmas.heap(), None);
+}